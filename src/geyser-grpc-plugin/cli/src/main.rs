@@ -3,18 +3,21 @@ use std::{
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use futures_util::StreamExt;
 use geyser_grpc_plugin_client::interceptor::GrpcInterceptor;
 use jito_geyser_protos::solana::geyser::{
-    geyser_client::GeyserClient, EmptyRequest, SlotUpdateStatus, SubscribeAccountUpdatesRequest,
-    SubscribeBlockUpdatesRequest, SubscribePartialAccountUpdatesRequest,
-    SubscribeProgramsUpdatesRequest, SubscribeSlotUpdateRequest,
-    SubscribeTransactionUpdatesRequest, TimestampedAccountUpdate,
+    geyser_admin_client::GeyserAdminClient, geyser_client::GeyserClient, maybe_account_update,
+    maybe_slot_tip_update, maybe_slot_update, BackpressurePolicy, CoalesceOptions, CommitmentLevel,
+    EmptyRequest, KillSubscriptionRequest, ListSubscriptionsRequest, MaybeAccountUpdate,
+    SlotUpdateStatus, SubscribeAccountUpdatesRequest, SubscribeBlockUpdatesRequest,
+    SubscribePartialAccountUpdatesRequest, SubscribeProgramsUpdatesRequest,
+    SubscribeSlotTipsRequest, SubscribeSlotUpdateRequest, SubscribeTransactionUpdatesRequest,
+    UpdateTiming,
 };
 use prost_types::Timestamp;
 use solana_sdk::pubkey::Pubkey;
-use tonic::{transport::channel::Endpoint, Streaming};
+use tonic::{codec::CompressionEncoding, transport::channel::Endpoint, Streaming};
 use uuid::Uuid;
 
 #[derive(Parser, Debug)]
@@ -27,6 +30,10 @@ struct Args {
     #[arg(long, env)]
     access_token: Option<Uuid>,
 
+    /// Compression encodings the server may respond with, in order of preference
+    #[arg(long, value_enum, value_delimiter = ',')]
+    accept_compressed: Vec<Compression>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -42,6 +49,26 @@ enum Commands {
         /// A space-separated list of programs to subscribe to
         #[arg(required = true)]
         programs: Vec<String>,
+
+        /// Stream the current state of the program's accounts before live updates
+        #[arg(long)]
+        send_initial_state: bool,
+
+        /// Replay journaled updates starting at this slot before live updates
+        #[arg(long)]
+        from_slot: Option<u64>,
+
+        /// What the server should do when this client falls behind
+        #[arg(long, value_enum, default_value_t = Backpressure::DropNewest)]
+        backpressure_policy: Backpressure,
+
+        /// Only stream the final write of each account per slot
+        #[arg(long)]
+        coalesce: bool,
+
+        /// Max time the server holds writes for when coalescing, 0 uses the server's default
+        #[arg(long, default_value_t = 0, requires = "coalesce")]
+        coalesce_max_delay_ms: u32,
     },
 
     /// Subscribe to a set of accounts
@@ -49,11 +76,40 @@ enum Commands {
         /// A space-separated list of accounts to subscribe to
         #[arg(required = true)]
         accounts: Vec<String>,
+
+        /// Stream the current state of the accounts before live updates
+        #[arg(long)]
+        send_initial_state: bool,
+
+        /// Replay journaled updates starting at this slot before live updates
+        #[arg(long)]
+        from_slot: Option<u64>,
+
+        /// What the server should do when this client falls behind
+        #[arg(long, value_enum, default_value_t = Backpressure::DropNewest)]
+        backpressure_policy: Backpressure,
+
+        /// Only stream the final write of each account per slot
+        #[arg(long)]
+        coalesce: bool,
+
+        /// Max time the server holds writes for when coalescing, 0 uses the server's default
+        #[arg(long, default_value_t = 0, requires = "coalesce")]
+        coalesce_max_delay_ms: u32,
     },
 
     /// Get the heartbeat interval
     GetHeartbeatInterval,
 
+    /// Get the tip at each commitment level and the fork tree above the latest root
+    GetSlotState,
+
+    /// Subscribe to tip changes at the given commitment levels, all of them if none are given
+    SlotTips {
+        #[arg(value_enum)]
+        commitments: Vec<Commitment>,
+    },
+
     /// Get partial account updates
     PartialAccounts { skip_votes: bool },
 
@@ -62,6 +118,63 @@ enum Commands {
 
     /// Subscribe to blocks
     Blocks,
+
+    /// List the server's subscriptions, authenticating with its admin access token
+    ListSubscriptions,
+
+    /// Kill one of the server's subscriptions, authenticating with its admin access token
+    KillSubscription { uuid: Uuid },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Backpressure {
+    DropNewest,
+    DropOldest,
+    Disconnect,
+    Coalesce,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl From<Commitment> for CommitmentLevel {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => CommitmentLevel::ProcessedCommitment,
+            Commitment::Confirmed => CommitmentLevel::ConfirmedCommitment,
+            Commitment::Finalized => CommitmentLevel::FinalizedCommitment,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl From<Compression> for CompressionEncoding {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => CompressionEncoding::Gzip,
+            Compression::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
+impl From<Backpressure> for BackpressurePolicy {
+    fn from(policy: Backpressure) -> Self {
+        match policy {
+            Backpressure::DropNewest => BackpressurePolicy::DropNewest,
+            Backpressure::DropOldest => BackpressurePolicy::DropOldest,
+            Backpressure::Disconnect => BackpressurePolicy::Disconnect,
+            Backpressure::Coalesce => BackpressurePolicy::CoalesceLatestPerAccount,
+        }
+    }
 }
 
 #[tokio::main]
@@ -83,18 +196,23 @@ async fn main() {
             .map(|uuid| uuid.to_string())
             .unwrap_or_default(),
     };
+    let mut admin_client =
+        GeyserAdminClient::with_interceptor(channel.clone(), interceptor.clone());
     let mut client = GeyserClient::with_interceptor(channel, interceptor);
+    for compression in args.accept_compressed {
+        client = client.accept_compressed(compression.into());
+    }
 
     match args.command {
         Commands::Slots => {
             let mut stream = client
-                .subscribe_slot_updates(SubscribeSlotUpdateRequest {})
+                .subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
                 .await
                 .expect("subscribes to slot stream")
                 .into_inner();
             while let Some(msg) = stream.next().await {
-                match msg {
-                    Ok(update) => {
+                match msg.map(|u| u.msg.unwrap()) {
+                    Ok(maybe_slot_update::Msg::SlotUpdate(update)) => {
                         let slot_update = update.slot_update.unwrap();
                         println!(
                             "slot: {} parent: {:?} status: {:?}",
@@ -103,34 +221,61 @@ async fn main() {
                             SlotUpdateStatus::try_from(slot_update.status).unwrap()
                         );
                     }
+                    Ok(maybe_slot_update::Msg::Hb(_)) => {}
                     Err(e) => {
                         println!("subscribe_slot_updates error: {e:?}");
                     }
                 }
             }
         }
-        Commands::Programs { programs: accounts } => {
+        Commands::Programs {
+            programs: accounts,
+            send_initial_state,
+            from_slot,
+            backpressure_policy,
+            coalesce,
+            coalesce_max_delay_ms,
+        } => {
             println!("subscribing to programs: {accounts:?}");
             let response = client
-                .subscribe_program_updates(SubscribeProgramsUpdatesRequest {
+                .subscribe_program_updates_v2(SubscribeProgramsUpdatesRequest {
                     programs: accounts
                         .iter()
                         .map(|a| Pubkey::from_str(a).unwrap().to_bytes().to_vec())
                         .collect(),
+                    send_initial_state,
+                    from_slot,
+                    backpressure_policy: BackpressurePolicy::from(backpressure_policy) as i32,
+                    coalesce: coalesce.then_some(CoalesceOptions {
+                        max_delay_ms: coalesce_max_delay_ms,
+                    }),
                 })
                 .await
                 .expect("subscribe to geyser")
                 .into_inner();
             print_account_updates(response).await;
         }
-        Commands::Accounts { accounts } => {
+        Commands::Accounts {
+            accounts,
+            send_initial_state,
+            from_slot,
+            backpressure_policy,
+            coalesce,
+            coalesce_max_delay_ms,
+        } => {
             println!("subscribing to accounts: {accounts:?}");
             let response = client
-                .subscribe_account_updates(SubscribeAccountUpdatesRequest {
+                .subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
                     accounts: accounts
                         .iter()
                         .map(|a| Pubkey::from_str(a).unwrap().to_bytes().to_vec())
                         .collect(),
+                    send_initial_state,
+                    from_slot,
+                    backpressure_policy: BackpressurePolicy::from(backpressure_policy) as i32,
+                    coalesce: coalesce.then_some(CoalesceOptions {
+                        max_delay_ms: coalesce_max_delay_ms,
+                    }),
                 })
                 .await
                 .expect("subscribe to geyser")
@@ -145,6 +290,57 @@ async fn main() {
                 .into_inner();
             println!("heartbeat interval: {response:?}");
         }
+        Commands::GetSlotState => {
+            let response = client
+                .get_slot_state(EmptyRequest {})
+                .await
+                .expect("gets slot state")
+                .into_inner();
+            println!(
+                "processed: {} confirmed: {} finalized: {}",
+                response.processed_slot, response.confirmed_slot, response.finalized_slot
+            );
+            for slot in response.slots {
+                println!(
+                    "slot: {} parent: {:?} commitment: {:?} dead: {}",
+                    slot.slot,
+                    slot.parent_slot,
+                    slot.commitment
+                        .map(|c| CommitmentLevel::try_from(c).unwrap()),
+                    slot.dead
+                );
+            }
+        }
+        Commands::SlotTips { commitments } => {
+            let mut stream = client
+                .subscribe_slot_tips(SubscribeSlotTipsRequest {
+                    commitments: commitments
+                        .into_iter()
+                        .map(|c| CommitmentLevel::from(c) as i32)
+                        .collect(),
+                })
+                .await
+                .expect("subscribes to slot tip stream")
+                .into_inner();
+            while let Some(msg) = stream.next().await {
+                match msg.map(|u| u.msg.unwrap()) {
+                    Ok(maybe_slot_tip_update::Msg::TipUpdate(update)) => {
+                        let tip_update = update.tip_update.unwrap();
+                        println!(
+                            "commitment: {:?} slot: {} previous: {:?} fork switch: {}",
+                            tip_update.commitment(),
+                            tip_update.slot,
+                            tip_update.previous_slot,
+                            tip_update.fork_switch
+                        );
+                    }
+                    Ok(maybe_slot_tip_update::Msg::Hb(_)) => {}
+                    Err(e) => {
+                        println!("subscribe_slot_tips error: {e:?}");
+                    }
+                }
+            }
+        }
         Commands::PartialAccounts { skip_votes } => {
             let mut response = client
                 .subscribe_partial_account_updates(SubscribePartialAccountUpdatesRequest {
@@ -170,7 +366,7 @@ async fn main() {
         }
         Commands::Transactions => {
             let mut response = client
-                .subscribe_transaction_updates(SubscribeTransactionUpdatesRequest {})
+                .subscribe_transaction_updates_v2(SubscribeTransactionUpdatesRequest {})
                 .await
                 .expect("subscribes to transaction updates")
                 .into_inner();
@@ -188,7 +384,7 @@ async fn main() {
         }
         Commands::Blocks => {
             let mut response = client
-                .subscribe_block_updates(SubscribeBlockUpdatesRequest {})
+                .subscribe_block_updates_v2(SubscribeBlockUpdatesRequest {})
                 .await
                 .expect("subscribes to block updates")
                 .into_inner();
@@ -204,6 +400,35 @@ async fn main() {
                 }
             }
         }
+        Commands::ListSubscriptions => {
+            let response = admin_client
+                .list_subscriptions(ListSubscriptionsRequest {})
+                .await
+                .expect("lists subscriptions")
+                .into_inner();
+            for subscription in response.subscriptions {
+                println!(
+                    "{} type: {} filter: {:?} peer: {} client: {:?} queued: {} dropped: {} connected: {:.0}s ago",
+                    subscription.uuid,
+                    subscription.subscription_type,
+                    subscription.filter_summary,
+                    subscription.peer_address,
+                    subscription.client_identity,
+                    subscription.queued_updates,
+                    subscription.dropped_updates,
+                    subscription.connected_at.as_ref().map_or(0.0, calc_skew),
+                );
+            }
+        }
+        Commands::KillSubscription { uuid } => {
+            admin_client
+                .kill_subscription(KillSubscriptionRequest {
+                    uuid: uuid.to_string(),
+                })
+                .await
+                .expect("kills subscription");
+            println!("killed subscription {uuid}");
+        }
     }
 }
 
@@ -216,24 +441,47 @@ fn calc_skew(ts: &Timestamp) -> f64 {
         .unwrap_or_else(|| packet_ts.checked_sub(now).unwrap().as_secs_f64() * -1.0)
 }
 
-async fn print_account_updates(mut response: Streaming<TimestampedAccountUpdate>) {
+// time spent in each stage between the plugin receiving an update and streaming it
+fn format_timing(timing: &UpdateTiming) -> String {
+    format!(
+        "enqueue: {}us dispatch: {}us send: {}us",
+        timing.enqueue_us.wrapping_sub(timing.plugin_receive_us),
+        timing.dispatch_us.wrapping_sub(timing.enqueue_us),
+        timing.send_us.wrapping_sub(timing.dispatch_us),
+    )
+}
+
+async fn print_account_updates(mut response: Streaming<MaybeAccountUpdate>) {
     loop {
         let account_update = response.message().await.expect("get account update");
-        match account_update {
+        match account_update.map(|u| u.msg.unwrap()) {
             None => {
                 println!("error, exiting...");
                 break;
             }
-            Some(update) => {
+            Some(maybe_account_update::Msg::LagReport(lag_report)) => {
+                println!("lag report: {lag_report:?}");
+            }
+            Some(maybe_account_update::Msg::Hb(_)) => {}
+            Some(maybe_account_update::Msg::StartupComplete(_)) => {
+                println!("startup complete");
+            }
+            Some(maybe_account_update::Msg::AccountUpdate(update)) => {
                 let ts = update.ts.unwrap();
                 let account_update = update.account_update.unwrap();
                 let skew = calc_skew(&ts);
                 println!(
-                    "# {:?} slot: {:?} pubkey: {:?} clock skew: {:.3}s",
+                    "# {:?} slot: {:?} pubkey: {:?} snapshot: {} clock skew: {:.3}s {}",
                     account_update.seq,
                     account_update.slot,
                     Pubkey::try_from(account_update.pubkey).unwrap(),
-                    skew
+                    account_update.is_snapshot,
+                    skew,
+                    update
+                        .timing
+                        .as_ref()
+                        .map(format_timing)
+                        .unwrap_or_default()
                 );
             }
         }
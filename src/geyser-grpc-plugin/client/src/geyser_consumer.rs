@@ -5,6 +5,8 @@
 //!     1. Account updates will be streamed in monotonically; i.e. updates for older slots
 //!        are discarded in the event that they were streamed by the server late.
 //!     2. Account updates received out of order will trigger an error.
+//!     3. Streams are considered dead, triggering an error, once nothing was received on them
+//!        for `max_allowable_missed_heartbeats` heartbeat intervals.
 
 use std::{
     collections::HashMap,
@@ -17,19 +19,20 @@ use std::{
 };
 
 use jito_geyser_protos::solana::geyser::{
-    geyser_client::GeyserClient, maybe_partial_account_update, EmptyRequest,
-    MaybePartialAccountUpdate, SubscribeAccountUpdatesRequest,
-    SubscribePartialAccountUpdatesRequest, SubscribeSlotEntryUpdateRequest,
-    SubscribeSlotUpdateRequest, TimestampedAccountUpdate,
+    geyser_client::GeyserClient, maybe_account_update, maybe_partial_account_update,
+    maybe_slot_entry_update, maybe_slot_update, EmptyRequest, GetAccountRequest,
+    GetMultipleAccountsRequest, MaybeAccountUpdate, MaybePartialAccountUpdate,
+    SubscribeAccountUpdatesRequest, SubscribePartialAccountUpdatesRequest,
+    SubscribeSlotEntryUpdateRequest, SubscribeSlotUpdateRequest,
 };
 use log::*;
 use lru::LruCache;
 use thiserror::Error;
 use tokio::{
     sync::mpsc::UnboundedSender,
-    time::{interval, Instant},
+    time::{interval, Instant, Interval},
 };
-use tonic::{codegen::InterceptedService, transport::Channel, Response, Status};
+use tonic::{codegen::InterceptedService, transport::Channel, Code, Response, Status};
 
 use crate::{
     geyser_consumer::GeyserConsumerError::{MissedHeartbeat, StreamClosed},
@@ -86,23 +89,128 @@ pub struct AccountWriteSeq {
     slot_seq: u64,
 }
 
+type Client = GeyserClient<InterceptedService<Channel, GrpcInterceptor>>;
+
+/// Tracks when a stream was last heard from, be it an update or a heartbeat.
+struct HeartbeatMonitor {
+    /// Used to look the heartbeat interval up again, as the server may have changed it.
+    client: Client,
+    max_allowable_missed_heartbeats: usize,
+    last_message: Instant,
+    heartbeat_interval_ms: u64,
+    expected_heartbeat_interval: Interval,
+}
+
+impl HeartbeatMonitor {
+    async fn new(client: &Client, max_allowable_missed_heartbeats: usize) -> Result<Self> {
+        let mut client = client.clone();
+        let heartbeat_interval_ms = Self::get_heartbeat_interval_ms(&mut client).await?;
+
+        Ok(Self {
+            client,
+            max_allowable_missed_heartbeats,
+            last_message: Instant::now(),
+            heartbeat_interval_ms,
+            expected_heartbeat_interval: interval(Duration::from_millis(heartbeat_interval_ms)),
+        })
+    }
+
+    /// Returns the server's heartbeat interval, which is at least 1ms since a zero interval can't
+    /// be ticked at.
+    async fn get_heartbeat_interval_ms(client: &mut Client) -> Result<u64> {
+        let heartbeat_interval_ms = client
+            .get_heartbeat_interval(EmptyRequest {})
+            .await?
+            .into_inner()
+            .heartbeat_interval_ms;
+        Ok(heartbeat_interval_ms.max(1))
+    }
+
+    fn expiration(&self) -> Duration {
+        Duration::from_millis(
+            self.heartbeat_interval_ms * self.max_allowable_missed_heartbeats as u64,
+        )
+    }
+
+    fn record_message<T>(&mut self, maybe_message: &std::result::Result<Option<T>, Status>) {
+        if let Ok(Some(_)) = maybe_message {
+            self.last_message = Instant::now();
+        }
+    }
+
+    /// Waits for the next heartbeat interval, failing if the stream was silent for too long.
+    /// The interval is looked up again before failing, in case the server has since raised it.
+    async fn check(&mut self) -> Result<()> {
+        let now = self.expected_heartbeat_interval.tick().await;
+        if now.duration_since(self.last_message) <= self.expiration() {
+            return Ok(());
+        }
+
+        let heartbeat_interval_ms = Self::get_heartbeat_interval_ms(&mut self.client).await?;
+        if heartbeat_interval_ms != self.heartbeat_interval_ms {
+            info!(
+                "heartbeat interval changed from {}ms to {heartbeat_interval_ms}ms",
+                self.heartbeat_interval_ms
+            );
+            self.heartbeat_interval_ms = heartbeat_interval_ms;
+            self.expected_heartbeat_interval =
+                interval(Duration::from_millis(heartbeat_interval_ms));
+        }
+        if Instant::now().duration_since(self.last_message) > self.expiration() {
+            return Err(MissedHeartbeat);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 pub struct GeyserConsumer {
     /// Geyser client.
-    client: GeyserClient<InterceptedService<Channel, GrpcInterceptor>>,
+    client: Client,
 
     /// Exit signal.
     exit: Arc<AtomicBool>,
 }
 
 impl GeyserConsumer {
-    pub fn new(
-        client: GeyserClient<InterceptedService<Channel, GrpcInterceptor>>,
-        exit: Arc<AtomicBool>,
-    ) -> Self {
+    pub fn new(client: Client, exit: Arc<AtomicBool>) -> Self {
         Self { client, exit }
     }
 
+    /// Returns the latest write of the account from the server's account cache, if it's cached.
+    pub async fn get_account(&self, pubkey: &Pubkey) -> Result<Option<AccountUpdate>> {
+        let mut c = self.client.clone();
+        match c
+            .get_account(GetAccountRequest {
+                pubkey: pubkey.to_bytes().to_vec(),
+            })
+            .await
+        {
+            Ok(resp) => Ok(resp.into_inner().account.map(AccountUpdate::from)),
+            Err(status) if status.code() == Code::NotFound => Ok(None),
+            Err(status) => Err(status.into()),
+        }
+    }
+
+    /// Same as [Self::get_account] for multiple accounts, returned in the order requested.
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<AccountUpdate>>> {
+        let mut c = self.client.clone();
+        let resp = c
+            .get_multiple_accounts(GetMultipleAccountsRequest {
+                pubkeys: pubkeys.iter().map(|p| p.to_bytes().to_vec()).collect(),
+            })
+            .await?;
+        Ok(resp
+            .into_inner()
+            .accounts
+            .into_iter()
+            .map(|cached| cached.account.map(AccountUpdate::from))
+            .collect())
+    }
+
     pub async fn consume_account_updates(
         &self,
         account_updates_tx: UnboundedSender<AccountUpdate>,
@@ -113,16 +221,21 @@ impl GeyserConsumer {
         //    new slot = 13
         //    new slot = 6 -> Error
         max_rooted_slot_distance: u64,
-        accounts: Vec<Vec<u8>>,
+        max_allowable_missed_heartbeats: usize,
+        // Accounts to subscribe to along with whether to stream their initial state, replay from a
+        // given slot, and the backpressure policy to apply if this consumer falls behind.
+        request: SubscribeAccountUpdatesRequest,
     ) -> Result<()> {
         let mut c = self.client.clone();
         let mut account_write_sequences =
             LruCache::new(NonZeroUsize::new(ACCOUNT_WRITE_SEQS_CACHE_SIZE).unwrap());
+        let mut heartbeat_monitor =
+            HeartbeatMonitor::new(&c, max_allowable_missed_heartbeats).await?;
 
-        let resp = c
-            .subscribe_account_updates(SubscribeAccountUpdatesRequest { accounts })
-            .await?;
-        let oldest_write_slot = extract_highest_write_slot_header(&resp)?;
+        let from_slot = request.from_slot;
+        let resp = c.subscribe_account_updates_v2(request).await?;
+        let highest_write_slot = extract_highest_write_slot_header(&resp)?;
+        let oldest_write_slot = from_slot.map_or(highest_write_slot, |s| s.min(highest_write_slot));
         let mut stream = resp.into_inner();
 
         let mut latest_write_slot = 0;
@@ -130,7 +243,9 @@ impl GeyserConsumer {
 
         while !self.exit.load(Ordering::Relaxed) {
             tokio::select! {
+                result = heartbeat_monitor.check() => result?,
                 maybe_message = stream.message() => {
+                    heartbeat_monitor.record_message(&maybe_message);
                     if let Some(account_update) = Self::process_account_update(
                         maybe_message,
                         &mut account_write_sequences,
@@ -164,12 +279,8 @@ impl GeyserConsumer {
         let mut c = self.client.clone();
         let mut account_write_sequences =
             LruCache::new(NonZeroUsize::new(ACCOUNT_WRITE_SEQS_CACHE_SIZE).unwrap());
-
-        let expected_heartbeat_interval_ms = c
-            .get_heartbeat_interval(EmptyRequest {})
-            .await?
-            .into_inner()
-            .heartbeat_interval_ms;
+        let mut heartbeat_monitor =
+            HeartbeatMonitor::new(&c, max_allowable_missed_heartbeats).await?;
 
         let resp = c
             .subscribe_partial_account_updates(SubscribePartialAccountUpdatesRequest {
@@ -180,27 +291,16 @@ impl GeyserConsumer {
         let mut stream = resp.into_inner();
 
         let mut latest_write_slot = 0;
-        let mut last_heartbeat = Instant::now();
-        let heartbeat_expiration =
-            expected_heartbeat_interval_ms * max_allowable_missed_heartbeats as u64;
-        let heartbeat_expiration = Duration::from_millis(heartbeat_expiration);
-        let mut expected_heartbeat_interval =
-            interval(Duration::from_millis(expected_heartbeat_interval_ms));
 
         while !self.exit.load(Ordering::Relaxed) {
             tokio::select! {
-                now = expected_heartbeat_interval.tick() => {
-                    let heartbeat_expired = now.duration_since(last_heartbeat).gt(&heartbeat_expiration);
-                    if heartbeat_expired {
-                        return Err(MissedHeartbeat);
-                    }
-                }
+                result = heartbeat_monitor.check() => result?,
                 maybe_message = stream.message() => {
+                    heartbeat_monitor.record_message(&maybe_message);
                     if let Some(account_update) = Self::process_partial_account_update(
                         maybe_message,
                         &mut account_write_sequences,
                         &highest_rooted_slot,
-                        &mut last_heartbeat,
                         oldest_write_slot,
                         max_rooted_slot_distance,
                     ).await? {
@@ -219,26 +319,35 @@ impl GeyserConsumer {
     pub async fn consume_slot_updates(
         &self,
         slot_updates_tx: UnboundedSender<SlotUpdate>,
+        max_allowable_missed_heartbeats: usize,
     ) -> Result<()> {
         let mut c = self.client.clone();
+        let mut heartbeat_monitor =
+            HeartbeatMonitor::new(&c, max_allowable_missed_heartbeats).await?;
 
         let resp = c
-            .subscribe_slot_updates(SubscribeSlotUpdateRequest {})
+            .subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
             .await?;
         let mut stream = resp.into_inner();
 
         while !self.exit.load(Ordering::Relaxed) {
-            match stream.message().await {
-                Ok(Some(slot_update)) => {
-                    if slot_updates_tx
-                        .send(slot_update.slot_update.unwrap().into())
-                        .is_err()
-                    {
-                        return Err(GeyserConsumerError::ConsumerChannelDisconnected);
-                    };
+            tokio::select! {
+                result = heartbeat_monitor.check() => result?,
+                maybe_message = stream.message() => {
+                    heartbeat_monitor.record_message(&maybe_message);
+                    match maybe_message?.ok_or(StreamClosed)?.msg {
+                        Some(maybe_slot_update::Msg::SlotUpdate(slot_update)) => {
+                            if slot_updates_tx
+                                .send(slot_update.slot_update.unwrap().into())
+                                .is_err()
+                            {
+                                return Err(GeyserConsumerError::ConsumerChannelDisconnected);
+                            };
+                        }
+                        Some(maybe_slot_update::Msg::Hb(_)) => {}
+                        None => unreachable!("msg must be Some"),
+                    }
                 }
-                Ok(None) => return Err(StreamClosed),
-                Err(e) => return Err(e.into()),
             }
         }
 
@@ -248,26 +357,35 @@ impl GeyserConsumer {
     pub async fn consume_slot_entry_updates(
         &self,
         slot_updates_tx: UnboundedSender<SlotEntryUpdate>,
+        max_allowable_missed_heartbeats: usize,
     ) -> Result<()> {
         let mut c = self.client.clone();
+        let mut heartbeat_monitor =
+            HeartbeatMonitor::new(&c, max_allowable_missed_heartbeats).await?;
 
         let resp = c
-            .subscribe_slot_entry_updates(SubscribeSlotEntryUpdateRequest {})
+            .subscribe_slot_entry_updates_v2(SubscribeSlotEntryUpdateRequest {})
             .await?;
         let mut stream = resp.into_inner();
 
         while !self.exit.load(Ordering::Relaxed) {
-            match stream.message().await {
-                Ok(Some(slot_update)) => {
-                    if slot_updates_tx
-                        .send(SlotEntryUpdate::from(slot_update.entry_update.unwrap()))
-                        .is_err()
-                    {
-                        return Err(GeyserConsumerError::ConsumerChannelDisconnected);
-                    };
+            tokio::select! {
+                result = heartbeat_monitor.check() => result?,
+                maybe_message = stream.message() => {
+                    heartbeat_monitor.record_message(&maybe_message);
+                    match maybe_message?.ok_or(StreamClosed)?.msg {
+                        Some(maybe_slot_entry_update::Msg::EntryUpdate(slot_update)) => {
+                            if slot_updates_tx
+                                .send(SlotEntryUpdate::from(slot_update.entry_update.unwrap()))
+                                .is_err()
+                            {
+                                return Err(GeyserConsumerError::ConsumerChannelDisconnected);
+                            };
+                        }
+                        Some(maybe_slot_entry_update::Msg::Hb(_)) => {}
+                        None => unreachable!("msg must be Some"),
+                    }
                 }
-                Ok(None) => return Err(StreamClosed),
-                Err(e) => return Err(GeyserConsumerError::from(e)),
             }
         }
 
@@ -276,28 +394,45 @@ impl GeyserConsumer {
 
     #[allow(clippy::too_many_arguments)]
     fn process_account_update(
-        maybe_message: std::result::Result<Option<TimestampedAccountUpdate>, Status>,
+        maybe_message: std::result::Result<Option<MaybeAccountUpdate>, Status>,
         account_write_sequences: &mut AccountWriteSeqsCache,
         highest_rooted_slot: &Arc<AtomicU64>,
         oldest_write_slot: Slot,
         max_rooted_slot_distance: u64,
     ) -> Result<Option<AccountUpdate>> {
         match maybe_message {
-            Ok(Some(maybe_update)) => {
-                let mut update: AccountUpdate = maybe_update.account_update.unwrap().into();
-                if let Err(e) = Self::process_update(
-                    &mut update,
-                    account_write_sequences,
-                    highest_rooted_slot,
-                    max_rooted_slot_distance,
-                    oldest_write_slot,
-                ) {
-                    error!("error processing update: {:?}", e);
-                    Err(e)
-                } else {
-                    Ok(Some(update))
+            Ok(Some(maybe_update)) => match maybe_update.msg {
+                Some(maybe_account_update::Msg::AccountUpdate(update)) => {
+                    let mut update: AccountUpdate = update.account_update.unwrap().into();
+                    if let Err(e) = Self::process_update(
+                        &mut update,
+                        account_write_sequences,
+                        highest_rooted_slot,
+                        max_rooted_slot_distance,
+                        oldest_write_slot,
+                    ) {
+                        error!("error processing update: {:?}", e);
+                        Err(e)
+                    } else {
+                        Ok(Some(update))
+                    }
                 }
-            }
+                Some(maybe_account_update::Msg::LagReport(lag_report)) => {
+                    warn!(
+                        "server dropped updates: [dropped={}, coalesced={}, upstream_dropped={}]",
+                        lag_report.dropped_updates,
+                        lag_report.coalesced_updates,
+                        lag_report.upstream_dropped_updates
+                    );
+                    Ok(None)
+                }
+                Some(maybe_account_update::Msg::Hb(_)) => Ok(None),
+                Some(maybe_account_update::Msg::StartupComplete(_)) => {
+                    info!("validator finished streaming startup accounts");
+                    Ok(None)
+                }
+                None => unreachable!("msg must be Some"),
+            },
             Ok(None) => Err(StreamClosed),
             Err(e) => Err(e.into()),
         }
@@ -308,7 +443,6 @@ impl GeyserConsumer {
         maybe_message: std::result::Result<Option<MaybePartialAccountUpdate>, Status>,
         account_write_sequences: &mut AccountWriteSeqsCache,
         highest_rooted_slot: &Arc<AtomicU64>,
-        last_heartbeat: &mut Instant,
         oldest_write_slot: Slot,
         max_rooted_slot_distance: u64,
     ) -> Result<Option<PartialAccountUpdate>> {
@@ -329,8 +463,9 @@ impl GeyserConsumer {
                         Ok(Some(update))
                     }
                 }
-                Some(maybe_partial_account_update::Msg::Hb(_)) => {
-                    *last_heartbeat = Instant::now();
+                Some(maybe_partial_account_update::Msg::Hb(_)) => Ok(None),
+                Some(maybe_partial_account_update::Msg::StartupComplete(_)) => {
+                    info!("validator finished streaming startup accounts");
                     Ok(None)
                 }
                 None => unreachable!("msg must be Some"),
@@ -348,7 +483,9 @@ impl GeyserConsumer {
         oldest_write_slot: u64,
     ) -> Result<()> {
         let update_slot = update.slot();
-        if update_slot < oldest_write_slot {
+        // Snapshots carry the slot the account was last written at, which may well be older than
+        // the highest write slot at the time of subscribing.
+        if update_slot < oldest_write_slot && !update.is_snapshot() {
             return Err(GeyserConsumerError::StaleAccountUpdate {
                 update_slot,
                 rooted_slot: highest_rooted_slot.load(Ordering::Relaxed),
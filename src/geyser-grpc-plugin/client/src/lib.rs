@@ -9,17 +9,47 @@ use std::{
 
 use jito_geyser_protos::solana::geyser::geyser_client::GeyserClient;
 use solana_sdk::{clock::Slot, pubkey::Pubkey};
-use tonic::transport::{ClientTlsConfig, Endpoint};
+use tonic::{
+    codec::CompressionEncoding,
+    transport::{ClientTlsConfig, Endpoint, Identity},
+};
 
 use crate::{geyser_consumer::GeyserConsumer, interceptor::GrpcInterceptor};
 
+/// Optional settings for [connect]; the default connects in plaintext without compression.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    /// TLS settings to connect with.
+    pub tls_config: Option<ClientTlsConfig>,
+
+    /// Certificate and key to authenticate with, for servers requiring client certificates.
+    /// Connects over TLS with the native and webpki roots if no `tls_config` is given.
+    pub client_identity: Option<Identity>,
+
+    /// Compression encodings the server may respond with in order of preference, provided it has
+    /// compression enabled.
+    pub accept_compressed: Vec<CompressionEncoding>,
+}
+
 pub async fn connect(
     geyser_addr: String,
     access_token: String,
-    tls_config: Option<ClientTlsConfig>,
+    options: ConnectOptions,
     exit: Arc<AtomicBool>,
 ) -> GeyserConsumer {
+    let ConnectOptions {
+        tls_config,
+        client_identity,
+        accept_compressed,
+    } = options;
     let endpoint = Endpoint::from_str(&geyser_addr).unwrap();
+    let tls_config = match (tls_config, client_identity) {
+        (tls, None) => tls,
+        (tls, Some(identity)) => Some(
+            tls.unwrap_or_else(|| ClientTlsConfig::new().with_enabled_roots())
+                .identity(identity),
+        ),
+    };
     let ch = if let Some(tls) = tls_config {
         endpoint.tls_config(tls).expect("tls_config")
     } else {
@@ -30,7 +60,10 @@ pub async fn connect(
     .expect("failed to connect");
 
     let interceptor = GrpcInterceptor { access_token };
-    let c = GeyserClient::with_interceptor(ch, interceptor);
+    let c = accept_compressed.into_iter().fold(
+        GeyserClient::with_interceptor(ch, interceptor),
+        |c, encoding| c.accept_compressed(encoding),
+    );
 
     GeyserConsumer::new(c, exit)
 }
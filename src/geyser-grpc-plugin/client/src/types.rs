@@ -1,7 +1,9 @@
 //! Contains application specific representations of proto definitions.
 
 use jito_geyser_protos::solana::geyser;
-use solana_sdk::{pubkey::Pubkey, slot_hashes::Slot};
+use std::ops::Range;
+
+use solana_sdk::{hash::Hash, pubkey::Pubkey, slot_hashes::Slot};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SlotStatus {
@@ -15,6 +17,9 @@ pub(crate) trait AccountUpdateNotification {
     fn set_seq(&mut self, seq: u64);
     fn seq(&self) -> u64;
     fn slot(&self) -> Slot;
+    fn is_snapshot(&self) -> bool {
+        false
+    }
 }
 
 pub struct AccountUpdate {
@@ -29,6 +34,9 @@ pub struct AccountUpdate {
     pub replica_version: u32,
     pub is_executable: bool,
     pub is_startup: bool,
+    pub is_snapshot: bool,
+    /// Only set if the server links writes to their transactions.
+    pub tx_context: Option<TransactionContext>,
 }
 
 impl AccountUpdateNotification for AccountUpdate {
@@ -44,6 +52,9 @@ impl AccountUpdateNotification for AccountUpdate {
     fn slot(&self) -> Slot {
         self.slot
     }
+    fn is_snapshot(&self) -> bool {
+        self.is_snapshot
+    }
 }
 
 impl From<geyser::AccountUpdate> for AccountUpdate {
@@ -62,7 +73,31 @@ impl From<geyser::AccountUpdate> for AccountUpdate {
             seq: proto.seq,
             is_executable: proto.is_executable,
             is_startup: proto.is_startup,
+            is_snapshot: proto.is_snapshot,
             replica_version: proto.replica_version,
+            tx_context: proto.tx_context.map(TransactionContext::from),
+        }
+    }
+}
+
+/// The transaction that caused an account write.
+pub struct TransactionContext {
+    pub tx_idx: u64,
+    /// Programs invoked by the transaction, in the order they were first invoked.
+    pub program_ids: Vec<Pubkey>,
+    pub is_success: bool,
+}
+
+impl From<geyser::TransactionContext> for TransactionContext {
+    fn from(proto: geyser::TransactionContext) -> Self {
+        Self {
+            tx_idx: proto.tx_idx,
+            program_ids: proto
+                .program_ids
+                .into_iter()
+                .map(|program_id| Pubkey::try_from(program_id).unwrap())
+                .collect(),
+            is_success: proto.is_success,
         }
     }
 }
@@ -148,6 +183,19 @@ impl From<geyser::SlotUpdate> for SlotUpdate {
 pub struct SlotEntryUpdate {
     pub slot: Slot,
     pub index: u64,
+    pub executed_transaction_count: u64,
+    pub num_hashes: u64,
+    pub hash: Hash,
+    /// Index within the block of the entry's first transaction, if reported by the validator.
+    pub starting_transaction_index: Option<u64>,
+}
+
+impl SlotEntryUpdate {
+    /// Indexes within the block of the entry's transactions, matching `TransactionUpdate.tx_idx`.
+    pub fn transaction_indexes(&self) -> Option<Range<u64>> {
+        self.starting_transaction_index
+            .map(|start| start..start + self.executed_transaction_count)
+    }
 }
 
 impl From<geyser::SlotEntryUpdate> for SlotEntryUpdate {
@@ -155,6 +203,10 @@ impl From<geyser::SlotEntryUpdate> for SlotEntryUpdate {
         Self {
             slot: proto.slot,
             index: proto.index,
+            executed_transaction_count: proto.executed_transaction_count,
+            num_hashes: proto.num_hashes,
+            hash: Hash::new_from_array(proto.hash.try_into().unwrap()),
+            starting_transaction_index: proto.starting_transaction_index,
         }
     }
 }
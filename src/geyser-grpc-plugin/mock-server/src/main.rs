@@ -0,0 +1,506 @@
+//! Serves the geyser gRPC service from a scripted source instead of a validator, so that
+//! consumers can be tested without running one.
+
+use std::{
+    error::Error,
+    fs,
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{Parser, Subcommand};
+use crossbeam_channel::{bounded, Sender};
+use geyser_grpc_plugin_server::{
+    journal,
+    metrics::GeyserMetrics,
+    server::{GeyserService, GeyserServiceConfig, SlotEntryNotification, StartupNotification},
+    service::geyser_server::GeyserServer,
+};
+use jito_geyser_protos::solana::geyser::{
+    AccountUpdate, SlotUpdate, SlotUpdateStatus, TimestampedAccountUpdate, TimestampedBlockUpdate,
+    TimestampedSlotUpdate, TimestampedTransactionUpdate,
+};
+use log::*;
+use serde_derive::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use tonic::{
+    codec::CompressionEncoding,
+    transport::{server::TcpIncoming, Server},
+};
+
+const DEFAULT_FIXTURES_DIR: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../test_validator/test_accounts"
+);
+
+/// Size of the channels between the scripted source and the service.
+const FEED_BUFFER_SIZE: usize = 100_000;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, env, default_value = "127.0.0.1:10000")]
+    bind_address: SocketAddr,
+
+    /// Time between slots
+    #[arg(long, env, default_value_t = 400)]
+    slot_interval_ms: u64,
+
+    /// JSON file holding a geyser_service_config as found in the plugin config, defaults are
+    /// used if unset
+    #[arg(long, env)]
+    service_config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    source: Source,
+}
+
+#[derive(Debug, Subcommand)]
+enum Source {
+    /// Replay account updates recorded by the plugin's journal
+    Journal {
+        /// Journal directory or a single segment file
+        path: PathBuf,
+
+        /// Times the journal is played, forever if 0. Slots and seqs are shifted past those of
+        /// the previous pass so that consumers see them increase.
+        #[arg(long, default_value_t = 1)]
+        passes: u64,
+    },
+
+    /// Write every fixture account each slot, bumping its lamports each time
+    Fixtures {
+        /// Directory of account JSON files as written by `solana account --output json`
+        #[arg(long, default_value = DEFAULT_FIXTURES_DIR)]
+        dir: PathBuf,
+
+        /// Stop after this many slots, runs forever if unset
+        #[arg(long)]
+        slots: Option<u64>,
+    },
+}
+
+/// Pipes updates to the geyser service as the plugin would.
+struct Feed {
+    account_update_sender: Sender<TimestampedAccountUpdate>,
+    slot_update_sender: Sender<TimestampedSlotUpdate>,
+    /// Never sent on, held so the service doesn't see its receivers disconnect.
+    _unused_senders: (
+        Sender<SlotEntryNotification>,
+        Sender<TimestampedBlockUpdate>,
+        Sender<TimestampedTransactionUpdate>,
+        Sender<StartupNotification>,
+    ),
+    highest_write_slot: Arc<AtomicU64>,
+}
+
+impl Feed {
+    fn send_account_update(&self, account_update: AccountUpdate) {
+        self.highest_write_slot
+            .fetch_max(account_update.slot, Ordering::SeqCst);
+        self.account_update_sender
+            .send(TimestampedAccountUpdate {
+                ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                account_update: Some(account_update),
+                timing: None,
+            })
+            .expect("geyser service exited");
+    }
+
+    /// Every slot is processed, confirmed and rooted in turn.
+    fn complete_slot(&self, slot: u64, parent_slot: Option<u64>) {
+        for (status, parent_slot) in [
+            (SlotUpdateStatus::Processed, parent_slot),
+            (SlotUpdateStatus::Confirmed, None),
+            (SlotUpdateStatus::Rooted, None),
+        ] {
+            self.slot_update_sender
+                .send(TimestampedSlotUpdate {
+                    ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                    slot_update: Some(SlotUpdate {
+                        slot,
+                        parent_slot,
+                        status: status as i32,
+                    }),
+                    timing: None,
+                })
+                .expect("geyser service exited");
+        }
+    }
+}
+
+fn build_service(service_config: GeyserServiceConfig) -> (Feed, GeyserService) {
+    let highest_write_slot = Arc::new(AtomicU64::new(0));
+    let metrics = Arc::new(GeyserMetrics::new(highest_write_slot.clone()).unwrap());
+    let (account_update_sender, account_update_rx) = bounded(FEED_BUFFER_SIZE);
+    let (slot_update_sender, slot_update_rx) = bounded(FEED_BUFFER_SIZE);
+    let (slot_entry_update_sender, slot_entry_update_rx) = bounded(FEED_BUFFER_SIZE);
+    let (block_update_sender, block_update_rx) = bounded(FEED_BUFFER_SIZE);
+    let (transaction_update_sender, transaction_update_rx) = bounded(FEED_BUFFER_SIZE);
+    let (startup_notification_sender, startup_notification_rx) = bounded(1);
+
+    let svc = GeyserService::new(
+        service_config,
+        account_update_rx,
+        slot_update_rx,
+        slot_entry_update_rx,
+        block_update_rx,
+        transaction_update_rx,
+        startup_notification_rx,
+        highest_write_slot.clone(),
+        None,
+        None,
+        metrics,
+    );
+    let feed = Feed {
+        account_update_sender,
+        slot_update_sender,
+        _unused_senders: (
+            slot_entry_update_sender,
+            block_update_sender,
+            transaction_update_sender,
+            startup_notification_sender,
+        ),
+        highest_write_slot,
+    };
+    (feed, svc)
+}
+
+async fn serve(
+    listener: TcpListener,
+    svc: GeyserService,
+    compression_enabled: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut svc = GeyserServer::new(svc);
+    if compression_enabled {
+        for encoding in [CompressionEncoding::Zstd, CompressionEncoding::Gzip] {
+            svc = svc.send_compressed(encoding).accept_compressed(encoding);
+        }
+    }
+    listener.set_nonblocking(true)?;
+    let incoming =
+        TcpIncoming::from_listener(tokio::net::TcpListener::from_std(listener)?, true, None)?;
+    Server::builder()
+        .add_service(svc)
+        .serve_with_incoming(incoming)
+        .await?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct FixtureFile {
+    pubkey: String,
+    account: FixtureAccount,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureAccount {
+    /// Encoded data followed by its encoding.
+    data: (String, String),
+    executable: bool,
+    lamports: u64,
+    owner: String,
+    rent_epoch: u64,
+}
+
+/// Loads the accounts in the directory, sorted by file name.
+fn load_fixtures(dir: &Path) -> Result<Vec<AccountUpdate>, Box<dyn Error>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|e| e == "json"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let fixture: FixtureFile = serde_json::from_slice(&fs::read(path)?)?;
+            let (data, encoding) = &fixture.account.data;
+            if encoding != "base64" {
+                return Err(format!("{}: unsupported encoding {encoding}", path.display()).into());
+            }
+            Ok(AccountUpdate {
+                pubkey: fixture.pubkey.parse::<Pubkey>()?.to_bytes().to_vec(),
+                owner: fixture.account.owner.parse::<Pubkey>()?.to_bytes().to_vec(),
+                lamports: fixture.account.lamports,
+                is_executable: fixture.account.executable,
+                rent_epoch: fixture.account.rent_epoch,
+                data: STANDARD.decode(data)?,
+                replica_version: 2,
+                ..AccountUpdate::default()
+            })
+        })
+        .collect()
+}
+
+fn generate_from_fixtures(
+    feed: &Feed,
+    mut accounts: Vec<AccountUpdate>,
+    slot_interval: Duration,
+    slots: Option<u64>,
+) {
+    let mut seq = 0;
+    for slot in 1..=slots.unwrap_or(u64::MAX) {
+        for account in &mut accounts {
+            seq += 1;
+            account.slot = slot;
+            account.seq = seq;
+            account.lamports += 1;
+            feed.send_account_update(account.clone());
+        }
+        feed.complete_slot(slot, slot.checked_sub(1).filter(|parent| *parent > 0));
+        thread::sleep(slot_interval);
+    }
+}
+
+/// Plays the updates slot by slot, in the order they were journaled.
+fn play_journal(
+    feed: &Feed,
+    updates: &[TimestampedAccountUpdate],
+    slot_interval: Duration,
+    passes: u64,
+) {
+    let updates: Vec<&AccountUpdate> = updates
+        .iter()
+        .filter_map(|u| u.account_update.as_ref())
+        .collect();
+    let (Some(min_slot), Some(max_slot)) = (
+        updates.iter().map(|u| u.slot).min(),
+        updates.iter().map(|u| u.slot).max(),
+    ) else {
+        warn!("journal is empty");
+        return;
+    };
+    let max_seq = updates.iter().map(|u| u.seq).max().unwrap_or_default();
+
+    let mut parent_slot = None;
+    for pass in 0..if passes == 0 { u64::MAX } else { passes } {
+        let mut current_slot = None;
+        for update in &updates {
+            let mut update = (*update).clone();
+            update.slot += pass * (max_slot - min_slot + 1);
+            update.seq += pass * max_seq;
+            if current_slot.is_some_and(|slot| slot != update.slot) {
+                feed.complete_slot(current_slot.unwrap(), parent_slot);
+                parent_slot = current_slot;
+                thread::sleep(slot_interval);
+            }
+            current_slot = Some(update.slot);
+            feed.send_account_update(update);
+        }
+        if let Some(slot) = current_slot {
+            feed.complete_slot(slot, parent_slot);
+            parent_slot = current_slot;
+            thread::sleep(slot_interval);
+        }
+    }
+    info!("journal played");
+}
+
+#[tokio::main]
+async fn main() {
+    solana_logger::setup_with_default("info");
+    let args: Args = Args::parse();
+    info!("Started with args: {args:?}");
+
+    let service_config: GeyserServiceConfig = match &args.service_config {
+        Some(path) => serde_json::from_slice(&fs::read(path).unwrap()).unwrap(),
+        None => serde_json::from_str(
+            r#"{"heartbeat_interval_ms": 1000, "subscriber_buffer_size": 100000}"#,
+        )
+        .unwrap(),
+    };
+    let compression_enabled = service_config.compression_enabled();
+    let (feed, svc) = build_service(service_config);
+
+    let slot_interval = Duration::from_millis(args.slot_interval_ms);
+    let source = match args.source {
+        Source::Journal { path, passes } => {
+            let updates = journal::read_journal(&path).unwrap();
+            info!("read {} journaled updates", updates.len());
+            thread::spawn(move || play_journal(&feed, &updates, slot_interval, passes))
+        }
+        Source::Fixtures { dir, slots } => {
+            let accounts = load_fixtures(&dir).unwrap();
+            info!("loaded {} fixture accounts", accounts.len());
+            thread::spawn(move || generate_from_fixtures(&feed, accounts, slot_interval, slots))
+        }
+    };
+
+    let listener = TcpListener::bind(args.bind_address).unwrap();
+    info!("serving on {}", args.bind_address);
+    serve(listener, svc, compression_enabled).await.unwrap();
+    let _ = source.join();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use crossbeam_channel::Receiver;
+    use geyser_grpc_plugin_client::ConnectOptions;
+    use geyser_grpc_plugin_server::journal::{JournalConfig, JournalWriter};
+    use jito_geyser_protos::solana::geyser::{
+        geyser_client::GeyserClient, maybe_account_update, SubscribeAccountUpdatesRequest,
+    };
+
+    use super::*;
+
+    fn test_service_config() -> GeyserServiceConfig {
+        serde_json::from_str(r#"{"heartbeat_interval_ms": 1000, "subscriber_buffer_size": 1000}"#)
+            .unwrap()
+    }
+
+    fn test_feed() -> (
+        Feed,
+        Receiver<TimestampedAccountUpdate>,
+        Receiver<TimestampedSlotUpdate>,
+    ) {
+        let (account_update_sender, account_update_rx) = bounded(FEED_BUFFER_SIZE);
+        let (slot_update_sender, slot_update_rx) = bounded(FEED_BUFFER_SIZE);
+        let feed = Feed {
+            account_update_sender,
+            slot_update_sender,
+            _unused_senders: (bounded(0).0, bounded(0).0, bounded(0).0, bounded(0).0),
+            highest_write_slot: Arc::new(AtomicU64::new(0)),
+        };
+        (feed, account_update_rx, slot_update_rx)
+    }
+
+    #[test]
+    fn test_play_journal_passes() {
+        let dir = std::env::temp_dir().join(format!("mock-server-journal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config: JournalConfig =
+            serde_json::from_value(serde_json::json!({ "dir": dir.to_str().unwrap() })).unwrap();
+        let (writer, _) = JournalWriter::new(&config).unwrap();
+        for (seq, slot) in [(1, 10), (2, 10), (3, 11)] {
+            assert!(writer.send(TimestampedAccountUpdate {
+                ts: None,
+                account_update: Some(AccountUpdate {
+                    slot,
+                    seq,
+                    ..AccountUpdate::default()
+                }),
+                timing: None,
+            }));
+        }
+        writer.join();
+        let updates = journal::read_journal(&dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        let (feed, account_update_rx, slot_update_rx) = test_feed();
+        play_journal(&feed, &updates, Duration::ZERO, 2);
+        let played: Vec<(u64, u64)> = account_update_rx
+            .try_iter()
+            .map(|u| u.account_update.map(|u| (u.slot, u.seq)).unwrap())
+            .collect();
+        assert_eq!(
+            played,
+            vec![(10, 1), (10, 2), (11, 3), (12, 4), (12, 5), (13, 6)]
+        );
+        let processed: Vec<(u64, Option<u64>)> = slot_update_rx
+            .try_iter()
+            .filter_map(|u| u.slot_update)
+            .filter(|u| u.status == SlotUpdateStatus::Processed as i32)
+            .map(|u| (u.slot, u.parent_slot))
+            .collect();
+        assert_eq!(
+            processed,
+            vec![(10, None), (11, Some(10)), (12, Some(11)), (13, Some(12))]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_streams_fixture_updates() {
+        let accounts = load_fixtures(Path::new(DEFAULT_FIXTURES_DIR)).unwrap();
+        assert!(!accounts.is_empty());
+        let fixture = accounts[0].clone();
+
+        let (feed, svc) = build_service(test_service_config());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, svc, false));
+
+        let mut client = GeyserClient::connect(url).await.unwrap();
+        let mut stream = client
+            .subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+                accounts: vec![fixture.pubkey.clone()],
+                ..SubscribeAccountUpdatesRequest::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        thread::spawn(move || {
+            generate_from_fixtures(&feed, accounts, Duration::from_millis(10), Some(1_000))
+        });
+
+        loop {
+            let msg = stream.message().await.unwrap().unwrap();
+            if let Some(maybe_account_update::Msg::AccountUpdate(update)) = msg.msg {
+                let update = update.account_update.unwrap();
+                assert_eq!(update.pubkey, fixture.pubkey);
+                assert_eq!(update.data, fixture.data);
+                assert_eq!(update.lamports, fixture.lamports + update.slot);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_consumer_streams_fixture_updates() {
+        let accounts = load_fixtures(Path::new(DEFAULT_FIXTURES_DIR)).unwrap();
+        let fixture = accounts[0].clone();
+
+        let (feed, svc) = build_service(test_service_config());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, svc, false));
+
+        let exit = Arc::new(AtomicBool::new(false));
+        let consumer = geyser_grpc_plugin_client::connect(
+            url,
+            String::new(),
+            ConnectOptions::default(),
+            exit.clone(),
+        )
+        .await;
+        let (account_updates_tx, mut account_updates_rx) = tokio::sync::mpsc::unbounded_channel();
+        let request = SubscribeAccountUpdatesRequest {
+            accounts: vec![fixture.pubkey.clone()],
+            ..SubscribeAccountUpdatesRequest::default()
+        };
+        let consumer = tokio::spawn(async move {
+            consumer
+                .consume_account_updates(
+                    account_updates_tx,
+                    Arc::new(AtomicU64::new(0)),
+                    u64::MAX,
+                    10,
+                    request,
+                )
+                .await
+        });
+        // give the consumer time to subscribe, so that no write predates its subscription
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        thread::spawn(move || {
+            generate_from_fixtures(&feed, accounts, Duration::from_millis(10), Some(1_000))
+        });
+
+        let update = account_updates_rx.recv().await.unwrap();
+        assert_eq!(update.pubkey.to_bytes().as_slice(), fixture.pubkey);
+        assert_eq!(update.data, fixture.data);
+        assert_eq!(update.lamports, fixture.lamports + update.slot);
+
+        exit.store(true, Ordering::Relaxed);
+        consumer.await.unwrap().unwrap();
+    }
+}
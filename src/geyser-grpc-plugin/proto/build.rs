@@ -16,6 +16,12 @@ fn main() {
             "InstructionErrorType",
             "#[cfg_attr(test, derive(enum_iterator::Sequence))]",
         )
+        .type_attribute(
+            "MaybeTransactionUpdate.msg",
+            "#[allow(clippy::large_enum_variant)]",
+        )
+        .type_attribute("MaybeBlock.msg", "#[allow(clippy::large_enum_variant)]")
+        .type_attribute("SinkMessage.msg", "#[allow(clippy::large_enum_variant)]")
         .compile_protos(
             &[
                 "proto/confirmed_block.proto",
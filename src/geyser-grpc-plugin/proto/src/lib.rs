@@ -24,6 +24,8 @@ pub mod solana {
         pub mod confirmed_block {
             tonic::include_proto!("solana.storage.confirmed_block");
         }
+        // as referenced by the geyser protos
+        pub use super::entries;
     }
     pub mod tx_by_addr {
         tonic::include_proto!("solana.storage.transaction_by_addr");
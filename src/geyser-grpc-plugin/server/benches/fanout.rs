@@ -0,0 +1,191 @@
+//! Compares fanning account updates out to 100 subscribers thru the service, from the dispatcher to
+//! each subscription's stream, when the frame encoded once is shared by all of them against each
+//! subscriber's stream encoding the update itself.
+//!
+//! Both run the same service. Subscribers write every streamed message out as tonic would; in the
+//! per-subscriber case, each account update is written out by encoding a copy of it instead of the
+//! shared frame, as tonic does for a decoded message.
+//!
+//! Run with `cargo bench -p geyser-grpc-plugin-server --bench fanout`.
+
+use std::{
+    collections::HashSet,
+    hint::black_box,
+    sync::{atomic::AtomicU64, Arc},
+    time::{Duration, Instant},
+};
+
+use bytes::BytesMut;
+use crossbeam_channel::unbounded;
+use futures_util::StreamExt;
+use geyser_grpc_plugin_server::{
+    metrics::GeyserMetrics,
+    server::{GeyserService, GeyserServiceConfig},
+    service::geyser_server::Geyser,
+};
+use jito_geyser_protos::solana::geyser::{
+    maybe_account_update, AccountUpdate, MaybeAccountUpdate, SubscribeAccountUpdatesRequest,
+    TimestampedAccountUpdate,
+};
+use prost::Message;
+use serde_json::json;
+use tokio::runtime::Runtime;
+use tonic::Request;
+
+const NUM_SUBSCRIBERS: usize = 100;
+const NUM_UPDATES: u64 = 10_000;
+const NUM_ACCOUNTS: u64 = 256;
+const ACCOUNT_DATA_LEN: usize = 165;
+
+fn account_update(seq: u64) -> TimestampedAccountUpdate {
+    TimestampedAccountUpdate {
+        ts: Some(prost_types::Timestamp::default()),
+        account_update: Some(AccountUpdate {
+            slot: seq / 100,
+            pubkey: vec![(seq % NUM_ACCOUNTS) as u8; 32],
+            lamports: 2_039_280,
+            owner: vec![6; 32],
+            data: vec![(seq % 7) as u8; ACCOUNT_DATA_LEN],
+            seq,
+            tx_signature: Some("1".repeat(88)),
+            ..AccountUpdate::default()
+        }),
+        timing: None,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    PerSubscriber,
+    PreEncoded,
+}
+
+/// Streams every update to each subscriber thru a fresh service, returning the time taken from
+/// notifying the first update to the last subscriber having written out the last one.
+fn fan_out(
+    runtime: &Runtime,
+    encoding: Encoding,
+    updates: &Arc<Vec<TimestampedAccountUpdate>>,
+) -> Duration {
+    let service_config: GeyserServiceConfig = serde_json::from_value(json!({
+        "heartbeat_interval_ms": 100,
+        // large enough that nothing's dropped
+        "subscriber_buffer_size": NUM_UPDATES,
+    }))
+    .unwrap();
+    let highest_write_slot = Arc::new(AtomicU64::new(0));
+    let (account_update_tx, account_update_rx) = unbounded();
+    let (_slot_update_tx, slot_update_rx) = unbounded();
+    let (_slot_entry_update_tx, slot_entry_update_rx) = unbounded();
+    let (_block_update_tx, block_update_rx) = unbounded();
+    let (_transaction_update_tx, transaction_update_rx) = unbounded();
+    let (_startup_notification_tx, startup_notification_rx) = unbounded();
+    let mut service = GeyserService::new(
+        service_config,
+        account_update_rx,
+        slot_update_rx,
+        slot_entry_update_rx,
+        block_update_rx,
+        transaction_update_rx,
+        startup_notification_rx,
+        highest_write_slot.clone(),
+        None,
+        None,
+        Arc::new(GeyserMetrics::new(highest_write_slot).unwrap()),
+    );
+    let shutdown = service.shutdown_handle().unwrap();
+
+    let accounts: HashSet<Vec<u8>> = updates
+        .iter()
+        .map(|u| u.account_update.as_ref().unwrap().pubkey.clone())
+        .collect();
+    let subscribers: Vec<_> = (0..NUM_SUBSCRIBERS)
+        .map(|_| {
+            let request = Request::new(SubscribeAccountUpdatesRequest {
+                accounts: accounts.iter().cloned().collect(),
+                ..SubscribeAccountUpdatesRequest::default()
+            });
+            let mut stream = runtime
+                .block_on(service.subscribe_account_updates_v2(request))
+                .unwrap()
+                .into_inner();
+            // only a registered subscription is sent heartbeats
+            runtime.block_on(stream.next()).unwrap().unwrap();
+
+            let updates = updates.clone();
+            async move {
+                let mut buf = BytesMut::with_capacity(1024);
+                let mut num_received = 0;
+                while num_received < updates.len() {
+                    let frame = stream.next().await.unwrap().unwrap();
+                    match encoding {
+                        Encoding::PreEncoded => {
+                            num_received += usize::from(frame.clone().into_timestamped().is_some());
+                            frame.encode(&mut buf).unwrap();
+                        }
+                        Encoding::PerSubscriber => {
+                            if frame.into_timestamped().is_none() {
+                                continue;
+                            }
+                            let msg = MaybeAccountUpdate {
+                                msg: Some(maybe_account_update::Msg::AccountUpdate(
+                                    updates[num_received].clone(),
+                                )),
+                            };
+                            num_received += 1;
+                            msg.encode(&mut buf).unwrap();
+                        }
+                    }
+                    black_box(&buf);
+                    buf.clear();
+                }
+            }
+        })
+        .collect();
+
+    let start = Instant::now();
+    let handles: Vec<_> = subscribers
+        .into_iter()
+        .map(|subscriber| runtime.spawn(subscriber))
+        .collect();
+    for update in updates.iter() {
+        account_update_tx.send(update.clone()).unwrap();
+    }
+    runtime.block_on(async {
+        for handle in handles {
+            handle.await.unwrap();
+        }
+    });
+    let elapsed = start.elapsed();
+
+    shutdown.close_subscriptions("benchmark finished");
+    shutdown.join();
+    elapsed
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let updates_per_sec = NUM_UPDATES as f64 / elapsed.as_secs_f64();
+    let messages_per_sec = updates_per_sec * NUM_SUBSCRIBERS as f64;
+    println!(
+        "{name:<24} {:>10.1}ms {updates_per_sec:>12.0} updates/s {messages_per_sec:>14.0} msgs/s",
+        elapsed.as_secs_f64() * 1_000.0
+    );
+}
+
+fn main() {
+    let runtime = Runtime::new().unwrap();
+    let updates = Arc::new((0..NUM_UPDATES).map(account_update).collect::<Vec<_>>());
+    // warm up allocator and caches
+    fan_out(&runtime, Encoding::PerSubscriber, &updates);
+    fan_out(&runtime, Encoding::PreEncoded, &updates);
+
+    println!("fanning {NUM_UPDATES} account updates out to {NUM_SUBSCRIBERS} subscribers");
+    let baseline = fan_out(&runtime, Encoding::PerSubscriber, &updates);
+    report("per-subscriber encoding", baseline);
+    let shared = fan_out(&runtime, Encoding::PreEncoded, &updates);
+    report("pre-encoded", shared);
+    println!(
+        "speedup: {:.1}x",
+        baseline.as_secs_f64() / shared.as_secs_f64()
+    );
+}
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use tonic_build::configure;
+
+fn main() {
+    const PROTOC_ENVAR: &str = "PROTOC";
+    if std::env::var(PROTOC_ENVAR).is_err() {
+        #[cfg(not(windows))]
+        std::env::set_var(PROTOC_ENVAR, protobuf_src::protoc());
+    }
+
+    // Generates just the Geyser service, reusing the messages from jito-geyser-protos, except for
+    // account and program updates which are streamed pre-encoded. The descriptor set is served by
+    // the reflection service.
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    configure()
+        .build_client(false)
+        .file_descriptor_set_path(out_dir.join("geyser_descriptor.bin"))
+        .extern_path(".solana", "::jito_geyser_protos::solana")
+        .extern_path(
+            ".solana.geyser.MaybeAccountUpdate",
+            "crate::pre_encoded::EncodedMaybeAccountUpdate",
+        )
+        .extern_path(
+            ".solana.geyser.TimestampedAccountUpdate",
+            "crate::pre_encoded::EncodedTimestampedAccountUpdate",
+        )
+        .compile_protos(&["../proto/proto/geyser.proto"], &["../proto/proto"])
+        .unwrap();
+}
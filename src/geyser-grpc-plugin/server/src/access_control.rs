@@ -0,0 +1,333 @@
+//! Per-token access control and subscription quotas.
+//!
+//! Clients are authenticated by the `access-token` header against a table of tokens, each granted a
+//! set of RPC methods and quotas. The grant is attached to the request so the service can enforce it.
+//! The table can be reloaded at any time, with subscriptions counting towards the quotas of
+//! the tokens they were opened with. Clients that authenticated with a TLS certificate are
+//! identified by its subject rather than their token's name.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use serde_derive::Deserialize;
+use tonic::{service::Interceptor, Request, Status};
+
+use crate::tls::peer_subject;
+
+pub const ACCESS_TOKEN_HEADER: &str = "access-token";
+
+/// Name given to the unrestricted grant of the legacy `access_token`.
+const DEFAULT_TOKEN_NAME: &str = "default";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessTokenConfig {
+    /// Identifies the token's holder in logs.
+    pub name: String,
+
+    pub token: String,
+
+    /// RPC methods the token may call, as named in the service definition e.g.
+    /// `SubscribeAccountUpdates`. Defaults to all.
+    pub allowed_methods: Option<Vec<String>>,
+
+    /// Max number of subscriptions open at once across all connections using the token.
+    /// Defaults to unlimited.
+    pub max_concurrent_subscriptions: Option<usize>,
+
+    /// Max number of accounts or programs per account or program subscription.
+    /// Defaults to unlimited.
+    pub max_accounts_per_subscription: Option<usize>,
+
+    /// If false, transaction updates are streamed without the transaction and its status meta.
+    /// Defaults to true.
+    pub full_transaction_data_allowed: Option<bool>,
+}
+
+/// What a client holding a given token may do.
+#[derive(Debug)]
+pub struct AccessGrant {
+    config: AccessTokenConfig,
+    /// Carried over to the token's new grant upon reload.
+    active_subscriptions: Arc<AtomicUsize>,
+}
+
+impl AccessGrant {
+    fn new(config: AccessTokenConfig, active_subscriptions: Arc<AtomicUsize>) -> Self {
+        Self {
+            config,
+            active_subscriptions,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn full_transaction_data_allowed(&self) -> bool {
+        self.config.full_transaction_data_allowed.unwrap_or(true)
+    }
+
+    pub fn check_method(&self, method: &str) -> Result<(), Status> {
+        match &self.config.allowed_methods {
+            Some(allowed_methods) if !allowed_methods.iter().any(|m| m == method) => {
+                Err(Status::permission_denied(format!(
+                    "{} is not allowed to call {method}",
+                    self.config.name
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the number of accounts subscribed to or requested at once is within the token's quota.
+    pub fn check_num_accounts(&self, num_accounts: usize) -> Result<(), Status> {
+        match self.config.max_accounts_per_subscription {
+            Some(max_accounts) if num_accounts > max_accounts => {
+                Err(Status::resource_exhausted(format!(
+                    "{} may subscribe to at most {max_accounts} accounts per subscription",
+                    self.config.name
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the subscription is within the token's quotas, returning a permit that counts
+    /// towards its concurrent subscriptions until dropped.
+    pub fn authorize_subscription(
+        self: &Arc<Self>,
+        method: &str,
+        num_accounts: usize,
+    ) -> Result<SubscriptionPermit, Status> {
+        self.check_method(method)?;
+        self.check_num_accounts(num_accounts)?;
+
+        let max_subscriptions = self
+            .config
+            .max_concurrent_subscriptions
+            .unwrap_or(usize::MAX);
+        self.active_subscriptions
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| {
+                (active < max_subscriptions).then_some(active + 1)
+            })
+            .map_err(|_| {
+                Status::resource_exhausted(format!(
+                    "{} may have at most {max_subscriptions} concurrent subscriptions",
+                    self.config.name
+                ))
+            })?;
+
+        Ok(SubscriptionPermit {
+            grant: self.clone(),
+        })
+    }
+}
+
+/// Held by a subscription's stream, releasing its slot in the token's concurrent subscriptions
+/// once the client goes away.
+#[derive(Debug)]
+pub struct SubscriptionPermit {
+    grant: Arc<AccessGrant>,
+}
+
+impl Drop for SubscriptionPermit {
+    fn drop(&mut self) {
+        self.grant
+            .active_subscriptions
+            .fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Identifies the client in logs by the subject of the certificate it authenticated with,
+/// falling back to the name of its access token.
+pub fn client_identity<T>(request: &Request<T>) -> Option<String> {
+    peer_subject(request).or_else(|| {
+        request
+            .extensions()
+            .get::<Arc<AccessGrant>>()
+            .map(|grant| grant.name().to_string())
+    })
+}
+
+/// Authenticates requests against the token table, attaching the matching [AccessGrant] to them.
+/// Requests go thru unchecked if no tokens are configured.
+#[derive(Clone)]
+pub struct AccessTokenChecker {
+    grants: Arc<RwLock<HashMap<String, Arc<AccessGrant>>>>,
+}
+
+impl AccessTokenChecker {
+    /// The legacy `access_token`, if any, is granted unrestricted access.
+    pub fn new(access_token: Option<String>, access_tokens: &[AccessTokenConfig]) -> Self {
+        let checker = Self {
+            grants: Arc::default(),
+        };
+        checker.reload(access_token, access_tokens);
+        checker
+    }
+
+    /// Replaces the token table, applying to requests from then on.
+    pub fn reload(&self, access_token: Option<String>, access_tokens: &[AccessTokenConfig]) {
+        let legacy_grant = access_token.map(|token| AccessTokenConfig {
+            name: DEFAULT_TOKEN_NAME.to_string(),
+            token,
+            allowed_methods: None,
+            max_concurrent_subscriptions: None,
+            max_accounts_per_subscription: None,
+            full_transaction_data_allowed: None,
+        });
+        let mut grants = self.grants.write().unwrap();
+        let reloaded_grants = legacy_grant
+            .into_iter()
+            .chain(access_tokens.iter().cloned())
+            .map(|config| {
+                let active_subscriptions = grants
+                    .get(&config.token)
+                    .map(|grant| grant.active_subscriptions.clone())
+                    .unwrap_or_default();
+                (
+                    config.token.clone(),
+                    Arc::new(AccessGrant::new(config, active_subscriptions)),
+                )
+            })
+            .collect();
+        *grants = reloaded_grants;
+    }
+}
+
+impl Interceptor for AccessTokenChecker {
+    fn call(&mut self, mut req: Request<()>) -> Result<Request<()>, Status> {
+        let grants = self.grants.read().unwrap();
+        if grants.is_empty() {
+            return Ok(req);
+        }
+        let grant = req
+            .metadata()
+            .get(ACCESS_TOKEN_HEADER)
+            .and_then(|t| t.to_str().ok())
+            .and_then(|t| grants.get(t))
+            .cloned()
+            .ok_or_else(|| Status::unauthenticated("Access token is incorrect"))?;
+        drop(grants);
+        req.extensions_mut().insert(grant);
+        Ok(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    fn checker() -> AccessTokenChecker {
+        AccessTokenChecker::new(
+            Some("legacy".to_string()),
+            &[AccessTokenConfig {
+                name: "limited".to_string(),
+                token: "limited-token".to_string(),
+                allowed_methods: Some(vec!["SubscribeAccountUpdates".to_string()]),
+                max_concurrent_subscriptions: Some(1),
+                max_accounts_per_subscription: Some(2),
+                full_transaction_data_allowed: Some(false),
+            }],
+        )
+    }
+
+    fn grant(checker: &mut AccessTokenChecker, token: &str) -> Result<Arc<AccessGrant>, Status> {
+        let mut req = Request::new(());
+        req.metadata_mut()
+            .insert(ACCESS_TOKEN_HEADER, token.parse().unwrap());
+        let req = checker.call(req)?;
+        Ok(req.extensions().get::<Arc<AccessGrant>>().unwrap().clone())
+    }
+
+    #[test]
+    fn test_enforces_grants() {
+        let mut checker = checker();
+        assert_eq!(
+            grant(&mut checker, "unknown").unwrap_err().code(),
+            Code::Unauthenticated
+        );
+
+        let legacy = grant(&mut checker, "legacy").unwrap();
+        assert!(legacy.full_transaction_data_allowed());
+        assert!(legacy
+            .authorize_subscription("SubscribeTransactionUpdates", 0)
+            .is_ok());
+
+        let limited = grant(&mut checker, "limited-token").unwrap();
+        assert_eq!(limited.name(), "limited");
+        assert!(!limited.full_transaction_data_allowed());
+        assert_eq!(
+            limited
+                .authorize_subscription("SubscribeTransactionUpdates", 0)
+                .unwrap_err()
+                .code(),
+            Code::PermissionDenied
+        );
+        assert_eq!(
+            limited
+                .authorize_subscription("SubscribeAccountUpdates", 3)
+                .unwrap_err()
+                .code(),
+            Code::ResourceExhausted
+        );
+
+        let permit = limited
+            .authorize_subscription("SubscribeAccountUpdates", 2)
+            .unwrap();
+        assert_eq!(
+            limited
+                .authorize_subscription("SubscribeAccountUpdates", 1)
+                .unwrap_err()
+                .code(),
+            Code::ResourceExhausted
+        );
+        drop(permit);
+        let permit = limited
+            .authorize_subscription("SubscribeAccountUpdates", 1)
+            .unwrap();
+
+        // open subscriptions count towards the reloaded quotas
+        checker.reload(
+            None,
+            &[AccessTokenConfig {
+                name: "limited".to_string(),
+                token: "limited-token".to_string(),
+                allowed_methods: None,
+                max_concurrent_subscriptions: Some(2),
+                max_accounts_per_subscription: None,
+                full_transaction_data_allowed: None,
+            }],
+        );
+        assert_eq!(
+            grant(&mut checker, "legacy").unwrap_err().code(),
+            Code::Unauthenticated
+        );
+        let limited = grant(&mut checker, "limited-token").unwrap();
+        let _reloaded_permit = limited
+            .authorize_subscription("SubscribeTransactionUpdates", 0)
+            .unwrap();
+        assert_eq!(
+            limited
+                .authorize_subscription("SubscribeAccountUpdates", 1)
+                .unwrap_err()
+                .code(),
+            Code::ResourceExhausted
+        );
+        drop(permit);
+        assert!(limited
+            .authorize_subscription("SubscribeAccountUpdates", 1)
+            .is_ok());
+
+        checker.reload(None, &[]);
+        let req = checker.call(Request::new(())).unwrap();
+        assert!(req.extensions().get::<Arc<AccessGrant>>().is_none());
+    }
+}
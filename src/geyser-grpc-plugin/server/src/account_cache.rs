@@ -0,0 +1,173 @@
+//! Latest-value cache of accounts seen by the geyser service.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::pre_encoded::SharedAccountUpdate;
+
+/// Keeps the most recent write of every account observed, along with an index of accounts by owner
+/// so that program subscriptions can be seeded with the current state.
+#[derive(Default)]
+pub struct AccountCache {
+    /// Latest update keyed by account pubkey.
+    accounts: HashMap<Vec<u8>, Arc<SharedAccountUpdate>>,
+
+    /// Account pubkeys keyed by their current owner.
+    owner_index: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+
+    /// Only accounts owned by these programs are cached, if set.
+    owners: Option<HashSet<Vec<u8>>>,
+}
+
+impl AccountCache {
+    pub fn new(owners: Option<HashSet<Vec<u8>>>) -> Self {
+        Self {
+            owners,
+            ..Self::default()
+        }
+    }
+
+    /// Stores the update if it's newer than the cached value, as determined by (slot, seq).
+    /// Accounts reassigned to an owner outside of the allow-list are evicted.
+    pub fn insert(&mut self, update: &Arc<SharedAccountUpdate>) {
+        let Some(account_update) = update.account_update.as_ref() else {
+            return;
+        };
+
+        if let Some(cached) = self
+            .accounts
+            .get(&account_update.pubkey)
+            .and_then(|u| u.account_update.as_ref())
+        {
+            if (cached.slot, cached.seq) > (account_update.slot, account_update.seq) {
+                return;
+            }
+
+            if cached.owner != account_update.owner {
+                if let Some(owned) = self.owner_index.get_mut(&cached.owner) {
+                    owned.remove(&account_update.pubkey);
+                    if owned.is_empty() {
+                        self.owner_index.remove(&cached.owner);
+                    }
+                }
+            }
+        }
+
+        if self
+            .owners
+            .as_ref()
+            .is_some_and(|owners| !owners.contains(&account_update.owner))
+        {
+            self.accounts.remove(&account_update.pubkey);
+            return;
+        }
+
+        self.owner_index
+            .entry(account_update.owner.clone())
+            .or_default()
+            .insert(account_update.pubkey.clone());
+        self.accounts
+            .insert(account_update.pubkey.clone(), update.clone());
+    }
+
+    pub fn get(&self, pubkey: &[u8]) -> Option<&Arc<SharedAccountUpdate>> {
+        self.accounts.get(pubkey)
+    }
+
+    /// Returns the cached values of all accounts currently owned by the given program.
+    pub fn get_by_owner<'a>(
+        &'a self,
+        owner: &[u8],
+    ) -> impl Iterator<Item = &'a Arc<SharedAccountUpdate>> + 'a {
+        self.owner_index
+            .get(owner)
+            .into_iter()
+            .flatten()
+            .filter_map(|pubkey| self.accounts.get(pubkey))
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jito_geyser_protos::solana::geyser::{AccountUpdate, TimestampedAccountUpdate};
+
+    use super::*;
+
+    fn update(pubkey: u8, owner: u8, slot: u64, seq: u64) -> Arc<SharedAccountUpdate> {
+        Arc::new(SharedAccountUpdate::new(TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                slot,
+                pubkey: vec![pubkey; 32],
+                owner: vec![owner; 32],
+                seq,
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }))
+    }
+
+    #[test]
+    fn test_keeps_latest_write() {
+        let mut cache = AccountCache::default();
+        cache.insert(&update(1, 9, 10, 5));
+        cache.insert(&update(1, 9, 10, 3));
+        assert_eq!(
+            cache
+                .get(&[1; 32])
+                .unwrap()
+                .account_update
+                .as_ref()
+                .unwrap()
+                .seq,
+            5
+        );
+
+        cache.insert(&update(1, 9, 11, 1));
+        let cached = cache
+            .get(&[1; 32])
+            .unwrap()
+            .account_update
+            .as_ref()
+            .unwrap();
+        assert_eq!((cached.slot, cached.seq), (11, 1));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_owner_index_follows_owner_changes() {
+        let mut cache = AccountCache::default();
+        cache.insert(&update(1, 9, 10, 1));
+        cache.insert(&update(2, 9, 10, 2));
+        assert_eq!(cache.get_by_owner(&[9; 32]).count(), 2);
+
+        cache.insert(&update(1, 8, 11, 3));
+        assert_eq!(cache.get_by_owner(&[9; 32]).count(), 1);
+        assert_eq!(cache.get_by_owner(&[8; 32]).count(), 1);
+        assert_eq!(cache.get_by_owner(&[7; 32]).count(), 0);
+    }
+
+    #[test]
+    fn test_only_caches_allowed_owners() {
+        let mut cache = AccountCache::new(Some(HashSet::from([vec![9; 32]])));
+        cache.insert(&update(1, 9, 10, 1));
+        cache.insert(&update(2, 8, 10, 2));
+        assert!(cache.get(&[1; 32]).is_some());
+        assert!(cache.get(&[2; 32]).is_none());
+
+        // reassigned to an owner outside of the allow-list
+        cache.insert(&update(1, 8, 11, 3));
+        assert!(cache.is_empty());
+        assert_eq!(cache.get_by_owner(&[9; 32]).count(), 0);
+    }
+}
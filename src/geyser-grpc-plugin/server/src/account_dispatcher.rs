@@ -0,0 +1,465 @@
+//! Dispatches account updates to account and program subscriptions on a pool of worker threads.
+//!
+//! Updates are sharded by pubkey so writes to the same account are always dispatched by the same
+//! thread, in the order they were received. Each shard indexes its subscriptions by pubkey and by
+//! owner, so dispatching an update is a couple of map lookups rather than a scan of every
+//! subscription, and payloads are shared between subscribers as `Arc`s.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{bounded, tick, Receiver, Sender};
+use jito_geyser_protos::solana::geyser::{SlotUpdate, SlotUpdateStatus};
+use log::*;
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::{
+    metrics::SubscriptionMetrics,
+    pre_encoded::SharedAccountUpdate,
+    slot_coalescer::SlotCoalescer,
+    subscriber_queue::{AccountUpdateQueueSender, QueueSendError},
+};
+
+/// Max number of events buffered per shard before the event loop blocks on it.
+const SHARD_BUFFER_SIZE: usize = 100_000;
+
+/// Cadence at which coalescing subscriptions are checked for slots held longer than their max delay.
+const COALESCE_EXPIRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What a subscription matches updates on.
+#[derive(Clone, Copy, Debug)]
+pub enum SubscriptionFilter {
+    /// Matches updates whose pubkey is one of the subscription's keys.
+    Pubkey,
+    /// Matches updates whose owner is one of the subscription's keys.
+    Owner,
+}
+
+enum ShardEvent {
+    AccountUpdate(Arc<SharedAccountUpdate>),
+    /// A processed or dead slot, releasing or discarding coalesced writes.
+    SlotUpdate(SlotUpdate),
+    SubscriptionAdded {
+        uuid: Uuid,
+        filter: SubscriptionFilter,
+        keys: Vec<Vec<u8>>,
+        notification_sender: AccountUpdateQueueSender,
+        coalesce_max_delay: Option<Duration>,
+        metrics: Arc<SubscriptionMetrics>,
+    },
+    SubscriptionClosed(Uuid),
+    /// Acknowledged once everything sent to the shard before it has been dispatched.
+    Flush(Sender<()>),
+}
+
+pub struct AccountUpdateDispatcher {
+    shards: Vec<Sender<ShardEvent>>,
+    t_hdls: Vec<JoinHandle<()>>,
+}
+
+impl AccountUpdateDispatcher {
+    pub fn new(num_shards: usize) -> Self {
+        let (shards, t_hdls) = (0..num_shards.max(1))
+            .map(|i| {
+                let (sender, receiver) = bounded(SHARD_BUFFER_SIZE);
+                let t_hdl = Builder::new()
+                    .name(format!("geyser-dispatch-{i}"))
+                    .spawn(move || Shard::default().run(receiver))
+                    .unwrap();
+                (sender, t_hdl)
+            })
+            .unzip();
+        Self { shards, t_hdls }
+    }
+
+    /// Registers the subscription with every shard, each indexing the keys it's responsible for.
+    pub fn add_subscription(
+        &self,
+        uuid: Uuid,
+        filter: SubscriptionFilter,
+        keys: &HashSet<Vec<u8>>,
+        notification_sender: &AccountUpdateQueueSender,
+        coalesce_max_delay: Option<Duration>,
+        metrics: Arc<SubscriptionMetrics>,
+    ) {
+        for (shard_idx, shard) in self.shards.iter().enumerate() {
+            let keys = match filter {
+                // an update's owner says nothing about which shard it lands on
+                SubscriptionFilter::Owner => keys.iter().cloned().collect(),
+                SubscriptionFilter::Pubkey => keys
+                    .iter()
+                    .filter(|pubkey| self.shard_idx(pubkey) == shard_idx)
+                    .cloned()
+                    .collect(),
+            };
+            self.send(
+                shard,
+                ShardEvent::SubscriptionAdded {
+                    uuid,
+                    filter,
+                    keys,
+                    notification_sender: notification_sender.clone(),
+                    coalesce_max_delay,
+                    metrics: metrics.clone(),
+                },
+            );
+        }
+    }
+
+    pub fn remove_subscription(&self, uuid: Uuid) {
+        for shard in &self.shards {
+            self.send(shard, ShardEvent::SubscriptionClosed(uuid));
+        }
+    }
+
+    pub fn dispatch(&self, update: Arc<SharedAccountUpdate>) {
+        let Some(account_update) = update.account_update.as_ref() else {
+            return;
+        };
+        let shard = &self.shards[self.shard_idx(&account_update.pubkey)];
+        self.send(shard, ShardEvent::AccountUpdate(update));
+    }
+
+    /// Forwards processed and dead slots to the shards for coalescing subscriptions.
+    pub fn handle_slot_update(&self, slot_update: &SlotUpdate) {
+        if !matches!(
+            SlotUpdateStatus::try_from(slot_update.status),
+            Ok(SlotUpdateStatus::Processed | SlotUpdateStatus::Dead)
+        ) {
+            return;
+        }
+        for shard in &self.shards {
+            self.send(shard, ShardEvent::SlotUpdate(*slot_update));
+        }
+    }
+
+    /// Waits for the shards to dispatch every update sent thus far. Writes held by coalescing
+    /// subscriptions aren't released.
+    pub fn flush(&self) {
+        let (ack_sender, ack_receiver) = bounded(self.shards.len());
+        for shard in &self.shards {
+            self.send(shard, ShardEvent::Flush(ack_sender.clone()));
+        }
+        drop(ack_sender);
+        while ack_receiver.recv().is_ok() {}
+    }
+
+    /// Stops the shards once they've dispatched everything buffered.
+    pub fn join(self) {
+        drop(self.shards);
+        for t_hdl in self.t_hdls {
+            let _ = t_hdl.join();
+        }
+    }
+
+    fn shard_idx(&self, pubkey: &[u8]) -> usize {
+        // pubkeys are uniformly distributed, so the leading bytes are as good as a hash
+        let mut buf = [0u8; 8];
+        let len = pubkey.len().min(8);
+        buf[..len].copy_from_slice(&pubkey[..len]);
+        (u64::from_le_bytes(buf) % self.shards.len() as u64) as usize
+    }
+
+    fn send(&self, shard: &Sender<ShardEvent>, event: ShardEvent) {
+        if shard.send(event).is_err() {
+            error!("account update dispatch shard disconnected");
+        }
+    }
+}
+
+struct ShardSubscription {
+    notification_sender: AccountUpdateQueueSender,
+    filter: SubscriptionFilter,
+    keys: Vec<Vec<u8>>,
+    /// Set if only the final write of each account per slot should be streamed.
+    coalescer: Option<SlotCoalescer>,
+    metrics: Arc<SubscriptionMetrics>,
+}
+
+impl ShardSubscription {
+    /// Streams the update, applying the subscription's backpressure policy.
+    /// Returns true if the subscription should be dropped.
+    fn stream_update(&self, update: &Arc<SharedAccountUpdate>) -> bool {
+        match self.notification_sender.try_send(update.clone()) {
+            Ok(false) => {
+                self.metrics.record_sent();
+                false
+            }
+            Ok(true) => {
+                self.metrics.record_dropped();
+                false
+            }
+            Err(QueueSendError::Full) => {
+                self.metrics.record_dropped();
+                let _ = self
+                    .notification_sender
+                    .stream_error(Status::resource_exhausted("subscriber is not keeping up"));
+                true
+            }
+            Err(QueueSendError::Closed) => true,
+        }
+    }
+
+    /// Streams the update, or holds it if the subscription is coalescing writes.
+    /// Returns true if the subscription should be dropped.
+    fn dispatch_update(&mut self, update: &Arc<SharedAccountUpdate>, now: Instant) -> bool {
+        match self.coalescer.as_mut() {
+            Some(coalescer) => coalescer
+                .insert(update.clone(), now)
+                .is_some_and(|update| self.stream_update(&update)),
+            None => self.stream_update(update),
+        }
+    }
+
+    /// Streams the writes released by the coalescer, if any.
+    /// Returns true if the subscription should be dropped.
+    fn flush_coalesced(
+        &mut self,
+        release: impl FnOnce(&mut SlotCoalescer) -> Vec<Arc<SharedAccountUpdate>>,
+    ) -> bool {
+        let Some(coalescer) = self.coalescer.as_mut() else {
+            return false;
+        };
+        release(coalescer)
+            .iter()
+            .any(|update| self.stream_update(update))
+    }
+}
+
+#[derive(Default)]
+struct Shard {
+    subscriptions: HashMap<Uuid, ShardSubscription>,
+    by_pubkey: HashMap<Vec<u8>, HashSet<Uuid>>,
+    by_owner: HashMap<Vec<u8>, HashSet<Uuid>>,
+}
+
+impl Shard {
+    fn run(mut self, receiver: Receiver<ShardEvent>) {
+        let coalesce_expiry_tick = tick(COALESCE_EXPIRY_INTERVAL);
+        loop {
+            crossbeam_channel::select! {
+                recv(receiver) -> maybe_event => {
+                    let Ok(event) = maybe_event else {
+                        return;
+                    };
+                    self.handle_event(event);
+                }
+                recv(coalesce_expiry_tick) -> _ => {
+                    let now = Instant::now();
+                    let failed_subscription_ids: Vec<Uuid> = self
+                        .subscriptions
+                        .iter_mut()
+                        .filter_map(|(uuid, sub)| sub.flush_coalesced(|c| c.expire(now)).then_some(*uuid))
+                        .collect();
+                    self.remove_subscriptions(&failed_subscription_ids);
+                }
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: ShardEvent) {
+        match event {
+            ShardEvent::AccountUpdate(update) => self.handle_account_update(update),
+            ShardEvent::SlotUpdate(slot_update) => self.handle_slot_update(slot_update),
+            ShardEvent::SubscriptionAdded {
+                uuid,
+                filter,
+                keys,
+                notification_sender,
+                coalesce_max_delay,
+                metrics,
+            } => {
+                if keys.is_empty() {
+                    return;
+                }
+                let index = match filter {
+                    SubscriptionFilter::Pubkey => &mut self.by_pubkey,
+                    SubscriptionFilter::Owner => &mut self.by_owner,
+                };
+                for key in &keys {
+                    index.entry(key.clone()).or_default().insert(uuid);
+                }
+                self.subscriptions.insert(
+                    uuid,
+                    ShardSubscription {
+                        notification_sender,
+                        filter,
+                        keys,
+                        coalescer: coalesce_max_delay.map(SlotCoalescer::new),
+                        metrics,
+                    },
+                );
+            }
+            ShardEvent::SubscriptionClosed(uuid) => self.remove_subscriptions(&[uuid]),
+            ShardEvent::Flush(ack_sender) => {
+                let _ = ack_sender.send(());
+            }
+        }
+    }
+
+    fn handle_account_update(&mut self, update: Arc<SharedAccountUpdate>) {
+        let Some(account_update) = update.account_update.as_ref() else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut failed_subscription_ids = vec![];
+        let matching_ids = self
+            .by_pubkey
+            .get(&account_update.pubkey)
+            .into_iter()
+            .chain(self.by_owner.get(&account_update.owner))
+            .flatten();
+        for uuid in matching_ids {
+            if let Some(sub) = self.subscriptions.get_mut(uuid) {
+                if sub.dispatch_update(&update, now) {
+                    failed_subscription_ids.push(*uuid);
+                }
+            }
+        }
+        self.remove_subscriptions(&failed_subscription_ids);
+    }
+
+    fn handle_slot_update(&mut self, slot_update: SlotUpdate) {
+        let slot = slot_update.slot;
+        match SlotUpdateStatus::try_from(slot_update.status) {
+            Ok(SlotUpdateStatus::Processed) => {
+                let failed_subscription_ids: Vec<Uuid> = self
+                    .subscriptions
+                    .iter_mut()
+                    .filter_map(|(uuid, sub)| {
+                        sub.flush_coalesced(|c| c.slot_processed(slot))
+                            .then_some(*uuid)
+                    })
+                    .collect();
+                self.remove_subscriptions(&failed_subscription_ids);
+            }
+            Ok(SlotUpdateStatus::Dead) => {
+                for coalescer in self
+                    .subscriptions
+                    .values_mut()
+                    .filter_map(|s| s.coalescer.as_mut())
+                {
+                    coalescer.slot_dead(slot);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Removes subscriptions from the shard. Closing the stream is left to the event loop, which is
+    /// notified once the client goes away.
+    fn remove_subscriptions(&mut self, subscription_ids: &[Uuid]) {
+        for uuid in subscription_ids {
+            let Some(sub) = self.subscriptions.remove(uuid) else {
+                continue;
+            };
+            let index = match sub.filter {
+                SubscriptionFilter::Pubkey => &mut self.by_pubkey,
+                SubscriptionFilter::Owner => &mut self.by_owner,
+            };
+            for key in &sub.keys {
+                if let Some(ids) = index.get_mut(key) {
+                    ids.remove(uuid);
+                    if ids.is_empty() {
+                        index.remove(key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{FutureExt, StreamExt};
+    use jito_geyser_protos::solana::geyser::{
+        maybe_account_update, AccountUpdate, BackpressurePolicy, MaybeAccountUpdate,
+        TimestampedAccountUpdate,
+    };
+
+    use prost::Message;
+
+    use super::*;
+    use crate::{metrics::GeyserMetrics, subscriber_queue::account_update_queue};
+
+    fn update(pubkey: u8, owner: u8, seq: u64) -> Arc<SharedAccountUpdate> {
+        Arc::new(SharedAccountUpdate::new(TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                pubkey: vec![pubkey; 32],
+                owner: vec![owner; 32],
+                seq,
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }))
+    }
+
+    #[test]
+    fn test_dispatches_by_pubkey_and_owner_in_order() {
+        let metrics = Arc::new(GeyserMetrics::new(Arc::default()).unwrap());
+        let dispatcher = AccountUpdateDispatcher::new(4);
+
+        let (account_sender, mut account_receiver) =
+            account_update_queue(100, BackpressurePolicy::DropNewest, 0);
+        let (program_sender, mut program_receiver) =
+            account_update_queue(100, BackpressurePolicy::DropNewest, 0);
+        dispatcher.add_subscription(
+            Uuid::new_v4(),
+            SubscriptionFilter::Pubkey,
+            &HashSet::from([vec![1; 32], vec![2; 32]]),
+            &account_sender,
+            None,
+            Arc::new(metrics.subscription_metrics("account", "a".to_string())),
+        );
+        dispatcher.add_subscription(
+            Uuid::new_v4(),
+            SubscriptionFilter::Owner,
+            &HashSet::from([vec![9; 32]]),
+            &program_sender,
+            None,
+            Arc::new(metrics.subscription_metrics("program", "p".to_string())),
+        );
+        drop(account_sender);
+        drop(program_sender);
+
+        for seq in 0..30 {
+            dispatcher.dispatch(update((seq % 3) as u8 + 1, 9 - (seq % 2) as u8, seq));
+        }
+        dispatcher.join();
+
+        let drain = |receiver: &mut crate::subscriber_queue::AccountUpdateQueueReceiver| {
+            let mut updates = vec![];
+            while let Some(Some(Ok(frame))) = receiver.next().now_or_never() {
+                let msg = MaybeAccountUpdate::decode(frame.bytes().clone()).unwrap();
+                if let Some(maybe_account_update::Msg::AccountUpdate(u)) = msg.msg {
+                    let u = u.account_update.unwrap();
+                    updates.push((u.pubkey[0], u.seq));
+                }
+            }
+            updates
+        };
+
+        let account_updates = drain(&mut account_receiver);
+        assert_eq!(account_updates.len(), 20);
+        for pubkey in [1, 2] {
+            let seqs: Vec<u64> = account_updates
+                .iter()
+                .filter(|(p, _)| *p == pubkey)
+                .map(|(_, seq)| *seq)
+                .collect();
+            assert_eq!(seqs.len(), 10);
+            assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        let program_updates = drain(&mut program_receiver);
+        assert_eq!(program_updates.len(), 15);
+        assert!(program_updates.iter().all(|(_, seq)| seq % 2 == 0));
+    }
+}
@@ -0,0 +1,127 @@
+//! Lets operators see who is subscribed and kill subscriptions over the `GeyserAdmin` service.
+//!
+//! Requests are served by the event loop, which owns the subscriptions. The service is
+//! authenticated with its own token, which isn't accepted by the Geyser service and vice versa.
+
+use std::{collections::HashMap, net::SocketAddr, time::SystemTime};
+
+use crossbeam_channel::Sender;
+use jito_geyser_protos::solana::geyser::{
+    KillSubscriptionRequest, KillSubscriptionResponse, ListSubscriptionsRequest,
+    ListSubscriptionsResponse, SubscriptionInfo,
+};
+use tokio::sync::oneshot;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{access_control::client_identity, service::geyser_admin_server::GeyserAdmin};
+
+pub enum AdminRequest {
+    ListSubscriptions(oneshot::Sender<Vec<SubscriptionInfo>>),
+    /// Answered with whether there was such a subscription.
+    KillSubscription(Uuid, oneshot::Sender<bool>),
+}
+
+/// Who opened a subscription and when.
+#[derive(Clone, Debug)]
+pub struct SubscriptionClient {
+    peer_address: Option<SocketAddr>,
+    identity: Option<String>,
+    connected_at: SystemTime,
+}
+
+impl SubscriptionClient {
+    pub fn new<T>(request: &Request<T>) -> Self {
+        Self {
+            peer_address: request.remote_addr(),
+            identity: client_identity(request),
+            connected_at: SystemTime::now(),
+        }
+    }
+}
+
+/// Subscriptions as listed by the admin service.
+pub trait DescribeSubscription {
+    fn client(&self) -> &SubscriptionClient;
+
+    /// What's streamed to the subscriber, empty if everything is.
+    fn filter_summary(&self) -> String {
+        String::new()
+    }
+
+    fn queued_updates(&self) -> usize;
+
+    fn dropped_updates(&self) -> u64;
+}
+
+pub fn describe_all<'a, S: DescribeSubscription>(
+    subscription_type: &'a str,
+    subscriptions: &'a HashMap<Uuid, S>,
+) -> impl Iterator<Item = SubscriptionInfo> + 'a {
+    subscriptions.iter().map(move |(uuid, subscription)| {
+        let client = subscription.client();
+        SubscriptionInfo {
+            uuid: uuid.to_string(),
+            subscription_type: subscription_type.to_string(),
+            filter_summary: subscription.filter_summary(),
+            peer_address: client
+                .peer_address
+                .map(|addr| addr.to_string())
+                .unwrap_or_default(),
+            client_identity: client.identity.clone().unwrap_or_default(),
+            queued_updates: subscription.queued_updates() as u64,
+            dropped_updates: subscription.dropped_updates(),
+            connected_at: Some(client.connected_at.into()),
+        }
+    })
+}
+
+pub struct GeyserAdminService {
+    admin_request_tx: Sender<AdminRequest>,
+}
+
+impl GeyserAdminService {
+    pub fn new(admin_request_tx: Sender<AdminRequest>) -> Self {
+        Self { admin_request_tx }
+    }
+
+    /// Hands the request to the event loop and waits for its response.
+    async fn request<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<T>) -> AdminRequest,
+    ) -> Result<T, Status> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.admin_request_tx
+            .send(request(response_tx))
+            .map_err(|_| Status::unavailable("event loop has exited"))?;
+        response_rx
+            .await
+            .map_err(|_| Status::unavailable("event loop has exited"))
+    }
+}
+
+#[tonic::async_trait]
+impl GeyserAdmin for GeyserAdminService {
+    async fn list_subscriptions(
+        &self,
+        _request: Request<ListSubscriptionsRequest>,
+    ) -> Result<Response<ListSubscriptionsResponse>, Status> {
+        let subscriptions = self.request(AdminRequest::ListSubscriptions).await?;
+        Ok(Response::new(ListSubscriptionsResponse { subscriptions }))
+    }
+
+    async fn kill_subscription(
+        &self,
+        request: Request<KillSubscriptionRequest>,
+    ) -> Result<Response<KillSubscriptionResponse>, Status> {
+        let uuid = Uuid::parse_str(&request.get_ref().uuid)
+            .map_err(|_| Status::invalid_argument("uuid is malformed"))?;
+        if !self
+            .request(|response_tx| AdminRequest::KillSubscription(uuid, response_tx))
+            .await?
+        {
+            return Err(Status::not_found(format!("no subscription {uuid}")));
+        }
+        Ok(Response::new(KillSubscriptionResponse {}))
+    }
+}
@@ -0,0 +1,312 @@
+//! Assembles full blocks from the transactions, entries and account writes notified for each slot.
+//!
+//! The validator notifies a block's metadata once the slot is frozen, but its transactions and
+//! entries travel on separate channels, so the metadata may well be received before the rest of
+//! the block. A block is therefore only released once the transaction and entry counts in its
+//! metadata have been met, or as incomplete once its slot falls below the root.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use jito_geyser_protos::solana::{
+    geyser::{Block, BlockUpdate, TransactionUpdate},
+    storage::entries::Entry,
+};
+use log::*;
+
+use crate::pre_encoded::SharedAccountUpdate;
+
+/// Transactions notified without their index within the block are told apart by signature.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum TransactionKey {
+    Index(u64),
+    Signature(String),
+}
+
+impl From<&TransactionUpdate> for TransactionKey {
+    fn from(transaction: &TransactionUpdate) -> Self {
+        if transaction.tx_idx == u64::MAX {
+            Self::Signature(transaction.signature.clone())
+        } else {
+            Self::Index(transaction.tx_idx)
+        }
+    }
+}
+
+#[derive(Default)]
+struct PendingBlock {
+    block_update: Option<BlockUpdate>,
+    /// Keyed so that duplicates don't count towards completeness.
+    transactions: HashMap<TransactionKey, TransactionUpdate>,
+    entries: HashMap<u32, Entry>,
+    account_writes: Vec<Arc<SharedAccountUpdate>>,
+}
+
+impl PendingBlock {
+    /// Blocks whose metadata lacks counts are considered complete as soon as it's received.
+    fn is_complete(&self) -> bool {
+        let Some(block_update) = &self.block_update else {
+            return false;
+        };
+        block_update
+            .executed_transaction_count
+            .map_or(true, |count| self.transactions.len() as u64 >= count)
+            && block_update
+                .entry_count
+                .map_or(true, |count| self.entries.len() as u64 >= count)
+    }
+
+    fn into_block(self, slot: u64, is_incomplete: bool) -> Block {
+        let mut transactions: Vec<TransactionUpdate> = self.transactions.into_values().collect();
+        transactions.sort_by(|a, b| {
+            a.tx_idx
+                .cmp(&b.tx_idx)
+                .then_with(|| a.signature.cmp(&b.signature))
+        });
+        let mut entries: Vec<Entry> = self.entries.into_values().collect();
+        entries.sort_by_key(|entry| entry.index);
+        let mut account_writes: Vec<_> = self
+            .account_writes
+            .iter()
+            .filter_map(|u| u.account_update.clone())
+            .collect();
+        account_writes.sort_by_key(|u| u.seq);
+
+        Block {
+            slot,
+            block_update: self.block_update,
+            transactions,
+            entries,
+            account_writes,
+            is_incomplete,
+        }
+    }
+}
+
+pub struct BlockAssembler {
+    /// Account writes are only held onto if set.
+    include_account_writes: bool,
+    pending_blocks: BTreeMap<u64, PendingBlock>,
+}
+
+impl BlockAssembler {
+    pub fn new(include_account_writes: bool) -> Self {
+        Self {
+            include_account_writes,
+            pending_blocks: BTreeMap::new(),
+        }
+    }
+
+    /// Each insert returns the slot's block if it's now complete.
+    pub fn insert_transaction(&mut self, transaction: TransactionUpdate) -> Option<Block> {
+        let slot = transaction.slot;
+        self.pending_blocks
+            .entry(slot)
+            .or_default()
+            .transactions
+            .insert(TransactionKey::from(&transaction), transaction);
+        self.release_if_complete(slot)
+    }
+
+    pub fn insert_entry(&mut self, slot: u64, entry: Entry) -> Option<Block> {
+        self.pending_blocks
+            .entry(slot)
+            .or_default()
+            .entries
+            .insert(entry.index, entry);
+        self.release_if_complete(slot)
+    }
+
+    pub fn insert_block_update(&mut self, block_update: BlockUpdate) -> Option<Block> {
+        let slot = block_update.slot;
+        self.pending_blocks.entry(slot).or_default().block_update = Some(block_update);
+        self.release_if_complete(slot)
+    }
+
+    /// Writes don't count towards completeness; those received after the block was released are
+    /// held until the slot is rooted, then discarded.
+    pub fn insert_account_write(&mut self, update: Arc<SharedAccountUpdate>) {
+        if !self.include_account_writes {
+            return;
+        }
+        if let Some(account_update) = update.account_update.as_ref() {
+            self.pending_blocks
+                .entry(account_update.slot)
+                .or_default()
+                .account_writes
+                .push(update);
+        }
+    }
+
+    /// Discards what was held for a slot that will never be completed.
+    pub fn slot_dead(&mut self, slot: u64) {
+        self.pending_blocks.remove(&slot);
+    }
+
+    /// Returns the blocks for slots below the root, which can no longer be completed, as
+    /// incomplete. What was held for slots without metadata is discarded.
+    pub fn slot_rooted(&mut self, slot: u64) -> Vec<Block> {
+        let pending_blocks = self.pending_blocks.split_off(&slot);
+        let pruned_blocks = std::mem::replace(&mut self.pending_blocks, pending_blocks);
+        pruned_blocks
+            .into_iter()
+            .filter(|(_, pending_block)| pending_block.block_update.is_some())
+            .map(|(slot, pending_block)| {
+                warn!(
+                    "releasing incomplete block for slot {slot} [transactions={}, entries={}]",
+                    pending_block.transactions.len(),
+                    pending_block.entries.len()
+                );
+                pending_block.into_block(slot, true)
+            })
+            .collect()
+    }
+
+    fn release_if_complete(&mut self, slot: u64) -> Option<Block> {
+        if !self.pending_blocks.get(&slot)?.is_complete() {
+            return None;
+        }
+        self.pending_blocks
+            .remove(&slot)
+            .map(|pending_block| pending_block.into_block(slot, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jito_geyser_protos::solana::geyser::{AccountUpdate, TimestampedAccountUpdate};
+
+    use super::*;
+
+    fn transaction(slot: u64, tx_idx: u64) -> TransactionUpdate {
+        TransactionUpdate {
+            slot,
+            tx_idx,
+            ..TransactionUpdate::default()
+        }
+    }
+
+    fn entry(index: u32) -> Entry {
+        Entry {
+            index,
+            hash: vec![index as u8; 32],
+            ..Entry::default()
+        }
+    }
+
+    fn block_update(slot: u64, transactions: u64, entries: u64) -> BlockUpdate {
+        BlockUpdate {
+            slot,
+            executed_transaction_count: Some(transactions),
+            entry_count: Some(entries),
+            ..BlockUpdate::default()
+        }
+    }
+
+    fn account_write(slot: u64, seq: u64) -> Arc<SharedAccountUpdate> {
+        Arc::new(SharedAccountUpdate::new(TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                slot,
+                seq,
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }))
+    }
+
+    #[test]
+    fn test_releases_block_once_complete() {
+        let mut assembler = BlockAssembler::new(true);
+        assert!(assembler.insert_transaction(transaction(10, 1)).is_none());
+        assert!(assembler.insert_entry(10, entry(1)).is_none());
+        assembler.insert_account_write(account_write(10, 7));
+        assembler.insert_account_write(account_write(10, 5));
+
+        // metadata received ahead of the rest of the block
+        assert!(assembler
+            .insert_block_update(block_update(10, 2, 2))
+            .is_none());
+        assert!(assembler.insert_transaction(transaction(10, 0)).is_none());
+        assert!(assembler.insert_transaction(transaction(10, 0)).is_none());
+        let block = assembler.insert_entry(10, entry(0)).unwrap();
+
+        assert_eq!(block.slot, 10);
+        assert_eq!(
+            block
+                .transactions
+                .iter()
+                .map(|tx| tx.tx_idx)
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            block.entries.iter().map(|e| e.index).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            block
+                .account_writes
+                .iter()
+                .map(|u| u.seq)
+                .collect::<Vec<_>>(),
+            vec![5, 7]
+        );
+        assert!(assembler.pending_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_discards_dead_and_unrooted_slots() {
+        let mut assembler = BlockAssembler::new(false);
+        assembler.insert_account_write(account_write(9, 1));
+        assert!(assembler.insert_transaction(transaction(9, 0)).is_none());
+        assert!(assembler.insert_transaction(transaction(10, 0)).is_none());
+        assert!(assembler.insert_transaction(transaction(11, 0)).is_none());
+
+        assembler.slot_dead(11);
+        assert!(assembler.slot_rooted(10).is_empty());
+        assert_eq!(
+            assembler.pending_blocks.keys().copied().collect::<Vec<_>>(),
+            vec![10]
+        );
+
+        let block = assembler
+            .insert_block_update(block_update(10, 1, 0))
+            .unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert!(block.account_writes.is_empty());
+        assert!(!block.is_incomplete);
+    }
+
+    #[test]
+    fn test_releases_incomplete_blocks_below_root() {
+        let mut assembler = BlockAssembler::new(false);
+        // notified without their index, as by validators on the first transaction interface
+        let unindexed = |signature: &str| TransactionUpdate {
+            signature: signature.to_string(),
+            ..transaction(10, u64::MAX)
+        };
+        assert!(assembler.insert_transaction(unindexed("b")).is_none());
+        assert!(assembler.insert_transaction(unindexed("a")).is_none());
+        assert!(assembler.insert_transaction(unindexed("a")).is_none());
+        // the third transaction was dropped
+        assert!(assembler
+            .insert_block_update(block_update(10, 3, 0))
+            .is_none());
+
+        assert!(assembler.slot_rooted(10).is_empty());
+        let [block] = <[Block; 1]>::try_from(assembler.slot_rooted(11)).unwrap();
+        assert_eq!((block.slot, block.is_incomplete), (10, true));
+        assert_eq!(
+            block
+                .transactions
+                .iter()
+                .map(|tx| tx.signature.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert!(assembler.pending_blocks.is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! Applies changes to the plugin's config file while the validator is running.
+//!
+//! TLS certificates, access tokens and the heartbeat interval take effect without dropping
+//! existing client streams. Other settings, e.g. the bind address and buffer sizes, require a
+//! restart and are left as they were.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread::{Builder, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, Result as PluginResult,
+};
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
+use log::*;
+
+use crate::{
+    access_control::AccessTokenChecker, geyser_grpc_plugin::PluginConfig,
+    server::GeyserServiceReloader, tls::ReloadableCertResolver,
+};
+
+pub struct ConfigReloader {
+    config_path: PathBuf,
+
+    /// Config most recently applied.
+    config: Mutex<PluginConfig>,
+
+    /// Set if the server was started with TLS.
+    cert_resolver: Option<Arc<ReloadableCertResolver>>,
+    access_token_checker: AccessTokenChecker,
+    service_reloader: GeyserServiceReloader,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config_path: PathBuf,
+        config: PluginConfig,
+        cert_resolver: Option<Arc<ReloadableCertResolver>>,
+        access_token_checker: AccessTokenChecker,
+        service_reloader: GeyserServiceReloader,
+    ) -> Self {
+        Self {
+            config_path,
+            config: Mutex::new(config),
+            cert_resolver,
+            access_token_checker,
+            service_reloader,
+        }
+    }
+
+    /// Re-reads the config file and applies what can be changed at runtime. Nothing is applied
+    /// if the file or the certificates fail to load.
+    pub fn reload(&self) -> PluginResult<()> {
+        let reloaded_config = PluginConfig::load(&self.config_path)?;
+        let mut config = self.config.lock().unwrap();
+        Self::warn_on_restart_required(&config, &reloaded_config);
+
+        let service_config = &reloaded_config.geyser_service_config;
+        if let (Some(cert_resolver), Some(tls_config)) =
+            (&self.cert_resolver, &service_config.tls_config)
+        {
+            cert_resolver
+                .reload(tls_config)
+                .map_err(|e| GeyserPluginError::Custom(e.into()))?;
+        }
+        self.access_token_checker.reload(
+            service_config.access_token.clone(),
+            service_config.access_tokens.as_deref().unwrap_or_default(),
+        );
+        self.service_reloader.reload(service_config);
+
+        info!("reloaded geyser config: {:?}", reloaded_config);
+        *config = reloaded_config;
+        Ok(())
+    }
+
+    fn warn_on_restart_required(config: &PluginConfig, reloaded_config: &PluginConfig) {
+        let client_ca_path = |config: &PluginConfig| {
+            config
+                .geyser_service_config
+                .tls_config
+                .as_ref()
+                .and_then(|tls_config| tls_config.client_ca_path.clone())
+        };
+        let restart_required = [
+            (
+                "bind_address",
+                config.bind_address != reloaded_config.bind_address,
+            ),
+            (
+                "account_update_buffer_size",
+                config.account_update_buffer_size != reloaded_config.account_update_buffer_size,
+            ),
+            (
+                "slot_update_buffer_size",
+                config.slot_update_buffer_size != reloaded_config.slot_update_buffer_size,
+            ),
+            (
+                "slot_entry_update_buffer_size",
+                config.slot_entry_update_buffer_size
+                    != reloaded_config.slot_entry_update_buffer_size,
+            ),
+            (
+                "block_update_buffer_size",
+                config.block_update_buffer_size != reloaded_config.block_update_buffer_size,
+            ),
+            (
+                "transaction_update_buffer_size",
+                config.transaction_update_buffer_size
+                    != reloaded_config.transaction_update_buffer_size,
+            ),
+            (
+                "account_cache_owners",
+                config.account_cache_owners != reloaded_config.account_cache_owners,
+            ),
+            ("sinks", config.sinks != reloaded_config.sinks),
+            (
+                "startup_account_owners",
+                config.startup_account_owners != reloaded_config.startup_account_owners,
+            ),
+            (
+                "startup_update_buffer_size",
+                config.startup_update_buffer_size != reloaded_config.startup_update_buffer_size,
+            ),
+            (
+                "tls_config",
+                config.geyser_service_config.tls_config.is_some()
+                    != reloaded_config.geyser_service_config.tls_config.is_some(),
+            ),
+            (
+                "admin_access_token",
+                config.geyser_service_config.admin_access_token
+                    != reloaded_config.geyser_service_config.admin_access_token,
+            ),
+            (
+                "tls_config.client_ca_path",
+                client_ca_path(config) != client_ca_path(reloaded_config),
+            ),
+        ];
+        for (setting, _) in restart_required.iter().filter(|(_, changed)| *changed) {
+            warn!("{setting} changed, the validator must be restarted for it to take effect");
+        }
+    }
+}
+
+/// Reloads the config whenever the file is modified, checking its mtime at a fixed interval.
+pub struct ConfigWatcher {
+    exit_sender: Sender<()>,
+    t_hdl: JoinHandle<()>,
+}
+
+impl ConfigWatcher {
+    pub fn new(config_reloader: Arc<ConfigReloader>, watch_interval: Duration) -> Self {
+        let (exit_sender, exit_receiver) = bounded(1);
+        let t_hdl = Builder::new()
+            .name("geyser-config-watcher".to_string())
+            .spawn(move || {
+                let modified = |path: &PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+                let mut last_modified: Option<SystemTime> = modified(&config_reloader.config_path);
+                while let Err(RecvTimeoutError::Timeout) =
+                    exit_receiver.recv_timeout(watch_interval)
+                {
+                    let current_modified = modified(&config_reloader.config_path);
+                    if current_modified == last_modified {
+                        continue;
+                    }
+                    last_modified = current_modified;
+                    info!("config file modified, reloading");
+                    if let Err(e) = config_reloader.reload() {
+                        error!("error reloading config: {}", e);
+                    }
+                }
+            })
+            .unwrap();
+        Self { exit_sender, t_hdl }
+    }
+
+    pub fn join(self) {
+        let _ = self.exit_sender.send(());
+        self.t_hdl.join().unwrap();
+    }
+}
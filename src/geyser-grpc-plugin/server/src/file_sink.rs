@@ -0,0 +1,92 @@
+//! Appends updates to a file, one base64 encoded [SinkMessage] per line.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jito_geyser_protos::solana::geyser::SinkMessage;
+use prost::Message;
+use serde_derive::Deserialize;
+
+use crate::sink::{SinkResult, SinkWriter};
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FileSinkConfig {
+    /// Created if missing, otherwise appended to.
+    pub path: String,
+}
+
+pub struct FileSinkWriter {
+    writer: BufWriter<File>,
+    buf: Vec<u8>,
+}
+
+impl FileSinkWriter {
+    pub fn new(config: &FileSinkConfig) -> SinkResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            buf: vec![],
+        })
+    }
+}
+
+impl SinkWriter for FileSinkWriter {
+    fn write(&mut self, messages: &[SinkMessage]) -> SinkResult<()> {
+        for message in messages {
+            self.buf.clear();
+            message.encode(&mut self.buf).unwrap();
+            self.writer
+                .write_all(STANDARD.encode(&self.buf).as_bytes())?;
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use jito_geyser_protos::solana::geyser::{sink_message, SlotUpdate, TimestampedSlotUpdate};
+
+    use super::*;
+
+    #[test]
+    fn test_writes_delimited_messages() {
+        let path = std::env::temp_dir().join(format!("file-sink-{}", uuid::Uuid::new_v4()));
+        let config = FileSinkConfig {
+            path: path.to_string_lossy().into_owned(),
+        };
+        let messages: Vec<SinkMessage> = (0..3)
+            .map(|slot| SinkMessage {
+                msg: Some(sink_message::Msg::SlotUpdate(TimestampedSlotUpdate {
+                    ts: None,
+                    slot_update: Some(SlotUpdate {
+                        slot,
+                        ..SlotUpdate::default()
+                    }),
+                    timing: None,
+                })),
+            })
+            .collect();
+
+        let mut writer = FileSinkWriter::new(&config).unwrap();
+        writer.write(&messages[..2]).unwrap();
+        writer.write(&messages[2..]).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let decoded: Vec<SinkMessage> = contents
+            .lines()
+            .map(|line| SinkMessage::decode(STANDARD.decode(line).unwrap().as_slice()).unwrap())
+            .collect();
+        assert_eq!(decoded, messages);
+    }
+}
@@ -1,14 +1,17 @@
 //! Implements the geyser plugin interface.
 
 use std::{
-    fs,
+    collections::HashSet,
     fs::File,
     io::Read,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    str::FromStr,
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use agave_geyser_plugin_interface::geyser_plugin_interface::{
@@ -16,12 +19,12 @@ use agave_geyser_plugin_interface::geyser_plugin_interface::{
     ReplicaEntryInfoVersions, ReplicaTransactionInfoVersions, Result as PluginResult, SlotStatus,
 };
 use bs58;
-use crossbeam_channel::{bounded, Sender, TrySendError};
+use crossbeam_channel::bounded;
 use jito_geyser_protos::solana::{
     geyser::{
-        geyser_server::GeyserServer, AccountUpdate, BlockUpdate, SlotUpdate, SlotUpdateStatus,
-        TimestampedAccountUpdate, TimestampedBlockUpdate, TimestampedSlotEntryUpdate,
-        TimestampedSlotUpdate, TimestampedTransactionUpdate, TransactionUpdate,
+        AccountUpdate, BlockUpdate, SlotUpdate, SlotUpdateStatus, TimestampedAccountUpdate,
+        TimestampedBlockUpdate, TimestampedSlotEntryUpdate, TimestampedSlotUpdate,
+        TimestampedTransactionUpdate, TransactionUpdate,
     },
     storage::confirmed_block::ConfirmedTransaction,
 };
@@ -29,36 +32,60 @@ use log::*;
 use serde_derive::Deserialize;
 use serde_json;
 use serde_with::{serde_as, DefaultOnError};
-use tokio::{runtime::Runtime, sync::oneshot};
+use solana_sdk::pubkey::Pubkey;
+use tokio::{runtime::Runtime, sync::oneshot, task::JoinHandle};
 use tonic::{
-    service::{interceptor::InterceptedService, Interceptor},
-    transport::{Identity, Server, ServerTlsConfig},
-    Request, Status,
+    codec::CompressionEncoding, service::interceptor::InterceptedService, transport::Server,
 };
 
 use crate::{
+    access_control::AccessTokenChecker,
     compact_timestamp,
-    server::{GeyserService, GeyserServiceConfig},
+    config_reload::{ConfigReloader, ConfigWatcher},
+    health,
+    journal::{JournalConfig, JournalWriter},
+    metrics::{self, GeyserMetrics, MetricsConfig},
+    server::{GeyserService, GeyserServiceConfig, GeyserServiceShutdown, SlotEntryNotification},
+    service::{
+        geyser_admin_server::GeyserAdminServer,
+        geyser_server::{GeyserServer, SERVICE_NAME},
+        FILE_DESCRIPTOR_SET,
+    },
+    sink::{self, GrpcSink, SinkConfig, SinkUpdate, Sinks},
+    tls::{self, ReloadableCertResolver},
+    update_timing,
 };
 
 pub struct PluginData {
     runtime: Runtime,
     server_exit_sender: oneshot::Sender<()>,
+    server_handle: JoinHandle<Result<(), tonic::transport::Error>>,
+    service_shutdown: GeyserServiceShutdown,
+
+    /// Max time given to subscribers to receive what's buffered for them upon unload.
+    shutdown_drain_timeout: Duration,
+    metrics_server_exit_sender: Option<oneshot::Sender<()>>,
 
-    /// Where updates are piped thru to the grpc service.
-    account_update_sender: Sender<TimestampedAccountUpdate>,
-    slot_update_sender: Sender<TimestampedSlotUpdate>,
-    slot_entry_update_sender: Sender<TimestampedSlotEntryUpdate>,
-    block_update_sender: Sender<TimestampedBlockUpdate>,
-    transaction_update_sender: Sender<TimestampedTransactionUpdate>,
+    /// Where updates are piped thru to, the grpc service first.
+    sinks: Sinks,
+
+    /// Persists account updates so they can be replayed to reconnecting clients.
+    journal_writer: Option<JournalWriter>,
+    metrics: Arc<GeyserMetrics>,
 
     /// Highest slot that an account write has been processed for thus far.
     highest_write_slot: Arc<AtomicU64>,
 
+    /// Applies config changes upon plugin reload or config file modification.
+    config_reloader: Arc<ConfigReloader>,
+    config_watcher: Option<ConfigWatcher>,
+
     /// Only set to true if account_data_notifications_enabled is true
     /// Otherwise, will always be false
     is_startup_completed: AtomicBool,
     ignore_startup_updates: bool,
+    /// Owners of the accounts streamed at startup, all are streamed if unset.
+    startup_account_owners: Option<HashSet<Vec<u8>>>,
     account_data_notifications_enabled: bool,
 }
 
@@ -102,11 +129,82 @@ pub struct PluginConfig {
     pub block_update_buffer_size: usize,
     pub transaction_update_buffer_size: usize,
     pub skip_startup_stream: Option<bool>,
+    /// Base58 encoded programs whose accounts are streamed at startup, unless skipped.
+    /// All accounts are streamed if unset.
+    pub startup_account_owners: Option<Vec<String>>,
+    /// Buffers startup account updates separately from live ones if set, blocking the validator
+    /// while the buffer is full rather than dropping them.
+    pub startup_update_buffer_size: Option<usize>,
     pub account_data_notifications_enabled: Option<bool>,
+    /// Enables the on-disk account update journal used to replay updates from a given slot.
+    pub journal_config: Option<JournalConfig>,
+    /// Serves prometheus metrics over HTTP if set.
+    pub metrics_config: Option<MetricsConfig>,
+    /// Checks the config file for changes at this interval, reloading it when modified.
+    /// Not watched if unset.
+    pub config_watch_interval_ms: Option<u64>,
+    /// Base58 encoded programs whose accounts are held in the account cache. Required if the cache
+    /// is enabled, as caching every account would exhaust the validator's memory.
+    pub account_cache_owners: Option<Vec<String>>,
+    /// Destinations updates are written to in addition to the grpc service.
+    pub sinks: Option<Vec<SinkConfig>>,
 }
 
 impl PluginConfig {
     const DEFAULT_SLOT_ENTRY_UPDATE_BUFFER_SIZE: usize = 1_000_000;
+
+    fn account_cache_owners(&self) -> PluginResult<Option<HashSet<Vec<u8>>>> {
+        Self::parse_owners("account_cache_owners", &self.account_cache_owners)
+    }
+
+    fn startup_account_owners(&self) -> PluginResult<Option<HashSet<Vec<u8>>>> {
+        Self::parse_owners("startup_account_owners", &self.startup_account_owners)
+    }
+
+    fn parse_owners(
+        setting: &str,
+        owners: &Option<Vec<String>>,
+    ) -> PluginResult<Option<HashSet<Vec<u8>>>> {
+        owners
+            .as_ref()
+            .map(|owners| {
+                owners
+                    .iter()
+                    .map(|owner| {
+                        Pubkey::from_str(owner)
+                            .map(|owner| owner.to_bytes().to_vec())
+                            .map_err(|err| GeyserPluginError::ConfigFileReadError {
+                                msg: format!("Error parsing {setting} {err:?}"),
+                            })
+                    })
+                    .collect()
+            })
+            .transpose()
+    }
+
+    pub fn load(config_path: &Path) -> PluginResult<Self> {
+        let mut file = File::open(config_path)?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)?;
+
+        let config: Self =
+            serde_json::from_str(&buf).map_err(|err| GeyserPluginError::ConfigFileReadError {
+                msg: format!("Error deserializing PluginConfig: {err:?}"),
+            })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the settings that depend on one another.
+    fn validate(&self) -> PluginResult<()> {
+        if self.geyser_service_config.account_cache_enabled() && self.account_cache_owners.is_none()
+        {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "account_cache_enabled requires account_cache_owners".to_string(),
+            });
+        }
+        Ok(())
+    }
 }
 
 // Can add default values for other fields here
@@ -119,7 +217,10 @@ impl GeyserPlugin for GeyserGrpcPlugin {
         "geyser-grpc-plugin"
     }
 
-    fn on_load(&mut self, config_path: &str, _is_reload: bool) -> PluginResult<()> {
+    /// Upon reload, an already loaded plugin applies what it can of the config in place, keeping
+    /// client streams open. Validators that reload plugins by unloading them first get a fresh
+    /// instance, which is loaded from scratch.
+    fn on_load(&mut self, config_path: &str, is_reload: bool) -> PluginResult<()> {
         solana_logger::setup_with_default("info");
         info!(
             "Loading plugin {:?} from config_path {:?}",
@@ -127,14 +228,11 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             config_path
         );
 
-        let mut file = File::open(config_path)?;
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)?;
+        if let (true, Some(data)) = (is_reload, &self.data) {
+            return data.config_reloader.reload();
+        }
 
-        let config: PluginConfig =
-            serde_json::from_str(&buf).map_err(|err| GeyserPluginError::ConfigFileReadError {
-                msg: format!("Error deserializing PluginConfig: {err:?}"),
-            })?;
+        let config = PluginConfig::load(Path::new(config_path))?;
 
         info!("loaded geyser config: {:?}", config);
 
@@ -147,6 +245,10 @@ impl GeyserPlugin for GeyserGrpcPlugin {
                 })?;
 
         let highest_write_slot = Arc::new(AtomicU64::new(0));
+        let metrics = Arc::new(
+            GeyserMetrics::new(highest_write_slot.clone())
+                .map_err(|e| GeyserPluginError::Custom(e.into()))?,
+        );
         let (account_update_sender, account_update_rx) = bounded(config.account_update_buffer_size);
         let (slot_update_sender, slot_update_rx) = bounded(config.slot_update_buffer_size);
         let (slot_entry_update_sender, slot_entry_update_rx) =
@@ -155,53 +257,169 @@ impl GeyserPlugin for GeyserGrpcPlugin {
         let (block_update_sender, block_update_receiver) = bounded(config.block_update_buffer_size);
         let (transaction_update_sender, transaction_update_receiver) =
             bounded(config.transaction_update_buffer_size);
+        // only holds the end of startup unless startup updates are buffered
+        let (startup_notification_sender, startup_notification_rx) =
+            bounded(config.startup_update_buffer_size.unwrap_or(1).max(1));
+
+        let (journal_writer, journal_reader) = match &config.journal_config {
+            Some(journal_config) => {
+                let (writer, reader) = JournalWriter::new(journal_config)
+                    .map_err(|e| GeyserPluginError::Custom(e.into()))?;
+                (Some(writer), Some(reader))
+            }
+            None => (None, None),
+        };
 
-        let svc = GeyserService::new(
+        let grpc_sink = GrpcSink {
+            account_update_sender,
+            slot_update_sender,
+            slot_entry_update_sender,
+            block_update_sender,
+            transaction_update_sender,
+            startup_notification_sender,
+            buffer_startup_updates: config.startup_update_buffer_size.is_some(),
+            metrics: metrics.clone(),
+        };
+        let sinks = config
+            .sinks
+            .iter()
+            .flatten()
+            .map(|sink_config| {
+                sink::build_sink(sink_config, metrics.clone())
+                    .map_err(|e| GeyserPluginError::Custom(e.into()))
+            })
+            .collect::<PluginResult<Vec<_>>>()?;
+        let sinks = Sinks::new(grpc_sink, sinks, metrics.clone());
+
+        let mut svc = GeyserService::new(
             config.geyser_service_config.clone(),
             account_update_rx,
             slot_update_rx,
             slot_entry_update_rx,
             block_update_receiver,
             transaction_update_receiver,
+            startup_notification_rx,
             highest_write_slot.clone(),
+            journal_reader,
+            config.account_cache_owners()?,
+            metrics.clone(),
         );
-        let svc = GeyserServer::new(svc);
+        let service_reloader = svc.reloader();
+        let event_loop_liveness = svc.event_loop_liveness();
+        let admin_service = svc.admin_service();
+        let service_shutdown = svc
+            .shutdown_handle()
+            .expect("shutdown handle is only taken once");
+        let mut svc = GeyserServer::new(svc);
+        if config.geyser_service_config.compression_enabled() {
+            // responses are compressed with whichever the client lists first in grpc-accept-encoding
+            for encoding in [CompressionEncoding::Zstd, CompressionEncoding::Gzip] {
+                svc = svc.send_compressed(encoding).accept_compressed(encoding);
+            }
+        }
 
         let runtime = Runtime::new().unwrap();
         let (server_exit_tx, server_exit_rx) = oneshot::channel();
-        let mut server_builder = Server::builder();
-        let tls_config = config.geyser_service_config.tls_config.clone();
-        let access_token = config.geyser_service_config.access_token.clone();
-        if let Some(tls_config) = tls_config {
-            let cert = fs::read(&tls_config.cert_path)?;
-            let key = fs::read(&tls_config.key_path)?;
-            server_builder = server_builder
-                .tls_config(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
-                .map_err(|e| GeyserPluginError::Custom(e.into()))?;
-        }
-        let s;
-        if let Some(access_token) = access_token {
-            let svc = InterceptedService::new(svc, AccessTokenChecker::new(access_token));
-            s = server_builder.add_service(svc);
-        } else {
-            s = server_builder.add_service(svc);
-        }
-        runtime.spawn(s.serve_with_shutdown(addr, async move {
+        let server_exit = async move {
             let _ = server_exit_rx.await;
-        }));
+        };
+        // always installed so that tokens can be added upon reload
+        let access_token_checker = AccessTokenChecker::new(
+            config.geyser_service_config.access_token.clone(),
+            config
+                .geyser_service_config
+                .access_tokens
+                .as_deref()
+                .unwrap_or_default(),
+        );
+        let (health_reporter, health_service) = tonic_health::server::health_reporter();
+        health::spawn_health_reporter(&runtime, event_loop_liveness, health_reporter);
+        let reflection_service = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+            .with_service_name(SERVICE_NAME)
+            .build_v1()
+            .map_err(|e| GeyserPluginError::Custom(e.into()))?;
+        let service_config = &config.geyser_service_config;
+        // the admin token is checked on its own so that geyser tokens don't grant admin access
+        let admin_service = service_config
+            .admin_access_token
+            .clone()
+            .map(|admin_access_token| {
+                InterceptedService::new(
+                    GeyserAdminServer::new(admin_service),
+                    AccessTokenChecker::new(Some(admin_access_token), &[]),
+                )
+            });
+        // health checks and reflection are open to load balancers and tooling without a token
+        let s = Server::builder()
+            .http2_keepalive_interval(Some(service_config.http2_keepalive_interval()))
+            .http2_keepalive_timeout(Some(service_config.http2_keepalive_timeout()))
+            .max_concurrent_streams(service_config.max_concurrent_streams)
+            .add_service(InterceptedService::new(svc, access_token_checker.clone()))
+            .add_optional_service(admin_service)
+            .add_service(health_service)
+            .add_service(reflection_service);
+        let (cert_resolver, server_handle) = match &config.geyser_service_config.tls_config {
+            Some(tls_config) => {
+                let cert_resolver = Arc::new(
+                    ReloadableCertResolver::new(tls_config)
+                        .map_err(|e| GeyserPluginError::Custom(e.into()))?,
+                );
+                let _guard = runtime.enter();
+                let incoming =
+                    tls::incoming(TcpListener::bind(addr)?, cert_resolver.clone(), tls_config)
+                        .map_err(|e| GeyserPluginError::Custom(e.into()))?;
+                let server_handle =
+                    runtime.spawn(s.serve_with_incoming_shutdown(incoming, server_exit));
+                (Some(cert_resolver), server_handle)
+            }
+            None => (
+                None,
+                runtime.spawn(s.serve_with_shutdown(addr, server_exit)),
+            ),
+        };
+
+        let config_reloader = Arc::new(ConfigReloader::new(
+            PathBuf::from(config_path),
+            config.clone(),
+            cert_resolver,
+            access_token_checker,
+            service_reloader,
+        ));
+        let config_watcher = config.config_watch_interval_ms.map(|watch_interval_ms| {
+            ConfigWatcher::new(
+                config_reloader.clone(),
+                Duration::from_millis(watch_interval_ms),
+            )
+        });
+
+        let metrics_server_exit_sender = match &config.metrics_config {
+            Some(metrics_config) => {
+                let (exit_tx, exit_rx) = oneshot::channel();
+                metrics::serve(metrics_config, metrics.clone(), &runtime, exit_rx)
+                    .map_err(|e| GeyserPluginError::Custom(e.into()))?;
+                Some(exit_tx)
+            }
+            None => None,
+        };
 
         self.data = Some(PluginData {
             runtime,
             server_exit_sender: server_exit_tx,
-            account_update_sender,
-            slot_update_sender,
-            slot_entry_update_sender,
-            block_update_sender,
-            transaction_update_sender,
+            server_handle,
+            service_shutdown,
+            shutdown_drain_timeout: config.geyser_service_config.shutdown_drain_timeout(),
+            metrics_server_exit_sender,
+            sinks,
+            journal_writer,
+            metrics,
             highest_write_slot,
+            config_reloader,
+            config_watcher,
             is_startup_completed: AtomicBool::new(false),
             // don't skip startup to keep backwards compatability
             ignore_startup_updates: config.skip_startup_stream.unwrap_or(false),
+            startup_account_owners: config.startup_account_owners()?,
             account_data_notifications_enabled: config
                 .account_data_notifications_enabled
                 .unwrap_or(true),
@@ -215,21 +433,45 @@ impl GeyserPlugin for GeyserGrpcPlugin {
         info!("Unloading plugin: {:?}", self.name());
 
         let data = self.data.take().expect("plugin not initialized");
+        if let Some(config_watcher) = data.config_watcher {
+            config_watcher.join();
+        }
+        data.service_shutdown
+            .close_subscriptions("validator is shutting down");
         data.server_exit_sender
             .send(())
             .expect("sending grpc server termination should succeed");
+        // the server exits once every stream has ended, or is left to be cut off by the runtime
+        let drain_timeout = data.shutdown_drain_timeout;
+        let server_handle = data.server_handle;
+        if data
+            .runtime
+            .block_on(async { tokio::time::timeout(drain_timeout, server_handle).await })
+            .is_err()
+        {
+            warn!("timed out draining subscriptions");
+        }
+        data.service_shutdown.join();
+        if let Some(metrics_server_exit_sender) = data.metrics_server_exit_sender {
+            let _ = metrics_server_exit_sender.send(());
+        }
         data.runtime.shutdown_background();
+        data.sinks.shutdown();
+        if let Some(journal_writer) = data.journal_writer {
+            journal_writer.join();
+        }
     }
 
     /// Note: this is called only if account_data_notifications_enabled is set to true.
     /// Do not use it for anything except for account updates
     fn notify_end_of_startup(&self) -> PluginResult<()> {
-        self.data
-            .as_ref()
-            .unwrap()
-            .is_startup_completed
-            .store(true, Ordering::Relaxed);
-        Ok(())
+        let data = self.data.as_ref().unwrap();
+        data.is_startup_completed.store(true, Ordering::Relaxed);
+        data.sinks.send(SinkUpdate::StartupComplete).map_err(|_| {
+            GeyserPluginError::AccountsUpdateError {
+                msg: "startup channel disconnected, exiting".to_string(),
+            }
+        })
     }
 
     fn update_account(
@@ -239,14 +481,26 @@ impl GeyserPlugin for GeyserGrpcPlugin {
         is_startup: bool,
     ) -> PluginResult<()> {
         let data = self.data.as_ref().expect("plugin must be initialized");
+        let timing = update_timing::received();
 
         if data.ignore_startup_updates && !data.is_startup_completed.load(Ordering::Relaxed) {
             return Ok(());
         }
+        if let (true, Some(startup_account_owners)) = (is_startup, &data.startup_account_owners) {
+            let owner = match &account {
+                ReplicaAccountInfoVersions::V0_0_1(account) => account.owner,
+                ReplicaAccountInfoVersions::V0_0_2(account) => account.owner,
+                ReplicaAccountInfoVersions::V0_0_3(account) => account.owner,
+            };
+            if !startup_account_owners.contains(owner) {
+                return Ok(());
+            }
+        }
 
         let account_update = match account {
             ReplicaAccountInfoVersions::V0_0_1(account) => TimestampedAccountUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 account_update: Some(AccountUpdate {
                     slot,
                     pubkey: account.pubkey.to_vec(),
@@ -259,12 +513,15 @@ impl GeyserPlugin for GeyserGrpcPlugin {
                     is_startup,
                     tx_signature: None,
                     replica_version: 1,
+                    is_snapshot: false,
+                    tx_context: None,
                 }),
             },
             ReplicaAccountInfoVersions::V0_0_2(account) => {
                 let tx_signature = account.txn_signature.map(|sig| sig.to_string());
                 TimestampedAccountUpdate {
                     ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                    timing,
                     account_update: Some(AccountUpdate {
                         slot,
                         pubkey: account.pubkey.to_vec(),
@@ -277,11 +534,14 @@ impl GeyserPlugin for GeyserGrpcPlugin {
                         is_startup,
                         tx_signature,
                         replica_version: 2,
+                        is_snapshot: false,
+                        tx_context: None,
                     }),
                 }
             }
             ReplicaAccountInfoVersions::V0_0_3(account) => TimestampedAccountUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 account_update: Some(AccountUpdate {
                     slot,
                     pubkey: account.pubkey.to_vec(),
@@ -294,6 +554,8 @@ impl GeyserPlugin for GeyserGrpcPlugin {
                     is_startup,
                     tx_signature: account.txn.map(|tx| tx.signature().to_string()),
                     replica_version: 2,
+                    is_snapshot: false,
+                    tx_context: None,
                 }),
             },
         };
@@ -326,19 +588,17 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             slot,
         );
 
-        match data.account_update_sender.try_send(account_update) {
-            Ok(_) => Ok(()),
-            Err(TrySendError::Full(_)) => {
-                warn!("account_update channel full, skipping");
-                Ok(())
-            }
-            Err(TrySendError::Disconnected(_)) => {
-                error!("account send error");
-                Err(GeyserPluginError::AccountsUpdateError {
-                    msg: "account_update channel disconnected, exiting".to_string(),
-                })
+        if let Some(journal_writer) = &data.journal_writer {
+            if !journal_writer.send(account_update.clone()) {
+                data.metrics.record_sink_drop("journal");
             }
         }
+
+        data.sinks
+            .send(SinkUpdate::Account(account_update))
+            .map_err(|_| GeyserPluginError::AccountsUpdateError {
+                msg: "account_update channel disconnected, exiting".to_string(),
+            })
     }
 
     fn update_slot_status(
@@ -348,6 +608,7 @@ impl GeyserPlugin for GeyserGrpcPlugin {
         status: &SlotStatus,
     ) -> PluginResult<()> {
         let data = self.data.as_ref().expect("plugin must be initialized");
+        let timing = update_timing::received();
 
         debug!("Updating slot {:?} at with status {:?}", slot, status);
 
@@ -361,26 +622,19 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             SlotStatus::Dead(_) => SlotUpdateStatus::Dead,
         };
 
-        match data.slot_update_sender.try_send(TimestampedSlotUpdate {
-            ts: Some(prost_types::Timestamp::from(SystemTime::now())),
-            slot_update: Some(SlotUpdate {
-                slot,
-                parent_slot,
-                status: status as i32,
-            }),
-        }) {
-            Ok(_) => Ok(()),
-            Err(TrySendError::Full(_)) => {
-                warn!("slot_update channel full, skipping");
-                Ok(())
-            }
-            Err(TrySendError::Disconnected(_)) => {
-                error!("slot send error");
-                Err(GeyserPluginError::SlotStatusUpdateError {
-                    msg: "slot_update channel disconnected, exiting".to_string(),
-                })
-            }
-        }
+        data.sinks
+            .send(SinkUpdate::Slot(TimestampedSlotUpdate {
+                ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
+                slot_update: Some(SlotUpdate {
+                    slot,
+                    parent_slot,
+                    status: status as i32,
+                }),
+            }))
+            .map_err(|_| GeyserPluginError::SlotStatusUpdateError {
+                msg: "slot_update channel disconnected, exiting".to_string(),
+            })
     }
 
     fn notify_transaction(
@@ -389,10 +643,12 @@ impl GeyserPlugin for GeyserGrpcPlugin {
         slot: u64,
     ) -> PluginResult<()> {
         let data = self.data.as_ref().expect("plugin must be initialized");
+        let timing = update_timing::received();
 
         let transaction_update = match transaction {
             ReplicaTransactionInfoVersions::V0_0_1(tx) => TimestampedTransactionUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 transaction: Some(TransactionUpdate {
                     slot,
                     signature: tx.signature.to_string(),
@@ -406,6 +662,7 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             },
             ReplicaTransactionInfoVersions::V0_0_2(tx) => TimestampedTransactionUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 transaction: Some(TransactionUpdate {
                     slot,
                     signature: tx.signature.to_string(),
@@ -419,27 +676,21 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             },
         };
 
-        match data.transaction_update_sender.try_send(transaction_update) {
-            Ok(_) => Ok(()),
-            Err(TrySendError::Full(_)) => {
-                warn!("transaction_update_sender full");
-                Ok(())
-            }
-            Err(TrySendError::Disconnected(_)) => {
-                error!("transaction_update_sender disconnected");
-                Err(GeyserPluginError::TransactionUpdateError {
-                    msg: "transaction_update_sender channel disconnected, exiting".to_string(),
-                })
-            }
-        }
+        data.sinks
+            .send(SinkUpdate::Transaction(transaction_update))
+            .map_err(|_| GeyserPluginError::TransactionUpdateError {
+                msg: "transaction_update_sender channel disconnected, exiting".to_string(),
+            })
     }
 
     fn notify_block_metadata(&self, block_info: ReplicaBlockInfoVersions) -> PluginResult<()> {
         let data = self.data.as_ref().expect("plugin must be initialized");
+        let timing = update_timing::received();
 
         let block = match block_info {
             ReplicaBlockInfoVersions::V0_0_1(block) => TimestampedBlockUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 block_update: Some(BlockUpdate {
                     slot: block.slot,
                     blockhash: block.blockhash.to_string(),
@@ -455,6 +706,7 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             },
             ReplicaBlockInfoVersions::V0_0_2(block) => TimestampedBlockUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 block_update: Some(BlockUpdate {
                     slot: block.slot,
                     blockhash: block.blockhash.to_string(),
@@ -470,6 +722,7 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             },
             ReplicaBlockInfoVersions::V0_0_3(block) => TimestampedBlockUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 block_update: Some(BlockUpdate {
                     slot: block.slot,
                     blockhash: block.blockhash.to_string(),
@@ -485,6 +738,7 @@ impl GeyserPlugin for GeyserGrpcPlugin {
             },
             ReplicaBlockInfoVersions::V0_0_4(block) => TimestampedBlockUpdate {
                 ts: Some(prost_types::Timestamp::from(SystemTime::now())),
+                timing,
                 block_update: Some(BlockUpdate {
                     slot: block.slot,
                     blockhash: block.blockhash.to_string(),
@@ -504,19 +758,9 @@ impl GeyserPlugin for GeyserGrpcPlugin {
                 }),
             },
         };
-        match data.block_update_sender.try_send(block) {
-            Ok(_) => Ok(()),
-            Err(TrySendError::Full(_)) => {
-                warn!("block update sender full");
-                Ok(())
-            }
-            Err(TrySendError::Disconnected(_)) => {
-                error!("block update send disconnected");
-                Err(GeyserPluginError::Custom(
-                    "block_update_sender channel disconnected, exiting".into(),
-                ))
-            }
-        }
+        data.sinks.send(SinkUpdate::Block(block)).map_err(|_| {
+            GeyserPluginError::Custom("block_update_sender channel disconnected, exiting".into())
+        })
     }
 
     fn account_data_notifications_enabled(&self) -> bool {
@@ -536,40 +780,36 @@ impl GeyserPlugin for GeyserGrpcPlugin {
 
     fn notify_entry(&self, entry: ReplicaEntryInfoVersions) -> PluginResult<()> {
         let data = self.data.as_ref().expect("plugin must be initialized");
+        let timing = update_timing::received();
 
-        let slot_entry = utils::get_slot_and_index_from_replica_entry_info_versions(&entry);
+        let slot_entry = utils::get_slot_entry_update_from_replica_entry_info_versions(&entry);
+        let entry = utils::get_entry_from_replica_entry_info_versions(&entry);
 
         debug!(
             "Updating slot entry {} at index {}",
             slot_entry.slot, slot_entry.index
         );
 
-        match data
-            .slot_entry_update_sender
-            .try_send(TimestampedSlotEntryUpdate {
-                ts: compact_timestamp::get_current_time_us_u32(),
-                entry_update: Some(slot_entry),
-            }) {
-            Ok(_) => Ok(()),
-            Err(TrySendError::Full(_)) => {
-                warn!("slot_entry_update channel full, skipping");
-                Ok(())
-            }
-            Err(TrySendError::Disconnected(_)) => {
-                error!("slot entry info send error");
-                Err(GeyserPluginError::SlotStatusUpdateError {
-                    msg: "slot_entry_update channel disconnected, exiting".to_string(),
-                })
-            }
-        }
+        data.sinks
+            .send(SinkUpdate::Entry(SlotEntryNotification {
+                entry_update: TimestampedSlotEntryUpdate {
+                    ts: compact_timestamp::get_current_time_us_u32(),
+                    timing,
+                    entry_update: Some(slot_entry),
+                },
+                entry,
+            }))
+            .map_err(|_| GeyserPluginError::SlotStatusUpdateError {
+                msg: "slot_entry_update channel disconnected, exiting".to_string(),
+            })
     }
 }
 
 mod utils {
     use agave_geyser_plugin_interface::geyser_plugin_interface::ReplicaEntryInfoVersions;
-    use jito_geyser_protos::solana::geyser::SlotEntryUpdate;
+    use jito_geyser_protos::solana::{geyser::SlotEntryUpdate, storage::entries::Entry};
 
-    pub fn get_slot_and_index_from_replica_entry_info_versions(
+    pub fn get_slot_entry_update_from_replica_entry_info_versions(
         entry: &ReplicaEntryInfoVersions,
     ) -> SlotEntryUpdate {
         match entry {
@@ -577,11 +817,37 @@ mod utils {
                 slot: entry_info.slot,
                 index: entry_info.index as u64,
                 executed_transaction_count: entry_info.executed_transaction_count,
+                num_hashes: entry_info.num_hashes,
+                hash: entry_info.hash.to_vec(),
+                starting_transaction_index: None,
             },
             ReplicaEntryInfoVersions::V0_0_2(entry_info) => SlotEntryUpdate {
                 slot: entry_info.slot,
                 index: entry_info.index as u64,
                 executed_transaction_count: entry_info.executed_transaction_count,
+                num_hashes: entry_info.num_hashes,
+                hash: entry_info.hash.to_vec(),
+                starting_transaction_index: Some(entry_info.starting_transaction_index as u64),
+            },
+        }
+    }
+
+    /// The starting transaction index is only known from V0_0_2 onwards.
+    pub fn get_entry_from_replica_entry_info_versions(entry: &ReplicaEntryInfoVersions) -> Entry {
+        match entry {
+            ReplicaEntryInfoVersions::V0_0_1(entry_info) => Entry {
+                index: entry_info.index as u32,
+                num_hashes: entry_info.num_hashes,
+                hash: entry_info.hash.to_vec(),
+                num_transactions: entry_info.executed_transaction_count,
+                starting_transaction_index: 0,
+            },
+            ReplicaEntryInfoVersions::V0_0_2(entry_info) => Entry {
+                index: entry_info.index as u32,
+                num_hashes: entry_info.num_hashes,
+                hash: entry_info.hash.to_vec(),
+                num_transactions: entry_info.executed_transaction_count,
+                starting_transaction_index: entry_info.starting_transaction_index as u32,
             },
         }
     }
@@ -598,26 +864,6 @@ pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
     Box::into_raw(plugin)
 }
 
-#[derive(Clone)]
-struct AccessTokenChecker {
-    access_token: String,
-}
-
-impl AccessTokenChecker {
-    fn new(access_token: String) -> Self {
-        Self { access_token }
-    }
-}
-
-impl Interceptor for AccessTokenChecker {
-    fn call(&mut self, req: Request<()>) -> Result<Request<()>, Status> {
-        match req.metadata().get("access-token") {
-            Some(t) if &self.access_token == t => Ok(req),
-            _ => Err(Status::unauthenticated("Access token is incorrect")),
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -687,6 +933,32 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_plugin_config_account_cache_requires_owners() {
+        let mut config = serde_json::json!({
+            "bind_address": "0.0.0.0:10000",
+            "account_update_buffer_size": 100000,
+            "slot_update_buffer_size": 100000,
+            "block_update_buffer_size": 100000,
+            "transaction_update_buffer_size": 100000,
+            "geyser_service_config": {
+                "heartbeat_interval_ms": 1000,
+                "subscriber_buffer_size": 1000000,
+                "account_cache_enabled": true
+            }
+        });
+        let result = serde_json::from_value::<PluginConfig>(config.clone())
+            .unwrap()
+            .validate();
+        assert!(
+            matches!(result, Err(GeyserPluginError::ConfigFileReadError { msg }) if msg.contains("account_cache_owners"))
+        );
+
+        config["account_cache_owners"] = serde_json::json!([Pubkey::new_unique().to_string()]);
+        let config: PluginConfig = serde_json::from_value(config).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
     // We currently have default value for slot_entry_update_buffer_size, so this test will always pass
     #[test]
     fn test_plugin_config_no_slot_entry_update_buffer_size() {
@@ -0,0 +1,123 @@
+//! Reports the health of the Geyser service over the standard `grpc.health.v1` service, so that
+//! load balancers can probe it. The service is serving for as long as its event loop is alive.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::*;
+use tokio::runtime::Runtime;
+use tonic_health::{server::HealthReporter, ServingStatus};
+
+use crate::service::geyser_server::SERVICE_NAME;
+
+/// How often the event loop's liveness is checked.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of ticks the event loop may miss before it's considered stalled.
+const MAX_MISSED_TICKS: u64 = 3;
+
+/// Tracks whether the event loop is alive from the ticks it records.
+#[derive(Clone)]
+pub struct EventLoopLiveness {
+    /// Time of the last tick in ms since the epoch, or zero once the event loop has exited.
+    last_tick_ms: Arc<AtomicU64>,
+
+    /// Cadence of the event loop's ticks, which may change when reloaded.
+    tick_interval_ms: Arc<AtomicU64>,
+}
+
+impl EventLoopLiveness {
+    pub fn new(tick_interval_ms: Arc<AtomicU64>) -> Self {
+        Self {
+            last_tick_ms: Arc::new(AtomicU64::new(now_ms())),
+            tick_interval_ms,
+        }
+    }
+
+    pub fn record_tick(&self) {
+        self.last_tick_ms.store(now_ms(), Ordering::Relaxed);
+    }
+
+    pub fn record_exit(&self) {
+        self.last_tick_ms.store(0, Ordering::Relaxed);
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.is_alive_at(now_ms())
+    }
+
+    fn is_alive_at(&self, now_ms: u64) -> bool {
+        let last_tick_ms = self.last_tick_ms.load(Ordering::Relaxed);
+        let max_tick_age_ms = self
+            .tick_interval_ms
+            .load(Ordering::Relaxed)
+            .saturating_mul(MAX_MISSED_TICKS)
+            .max(HEALTH_CHECK_INTERVAL.as_millis() as u64);
+        last_tick_ms != 0 && now_ms.saturating_sub(last_tick_ms) <= max_tick_age_ms
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Keeps the status of the server and the Geyser service in line with the event loop's liveness.
+pub fn spawn_health_reporter(
+    runtime: &Runtime,
+    liveness: EventLoopLiveness,
+    mut reporter: HealthReporter,
+) {
+    runtime.spawn(async move {
+        let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        let mut reported_status = None;
+        loop {
+            interval.tick().await;
+            let status = if liveness.is_alive() {
+                ServingStatus::Serving
+            } else {
+                ServingStatus::NotServing
+            };
+            if reported_status == Some(status) {
+                continue;
+            }
+            if reported_status.is_some() {
+                warn!("geyser service health changed to {status:?}");
+            }
+            // the empty service name stands for the server as a whole
+            for service_name in ["", SERVICE_NAME] {
+                reporter.set_service_status(service_name, status).await;
+            }
+            reported_status = Some(status);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_or_exited_event_loop_not_alive() {
+        let tick_interval_ms = Arc::new(AtomicU64::new(1_000));
+        let liveness = EventLoopLiveness::new(tick_interval_ms.clone());
+        let last_tick_ms = liveness.last_tick_ms.load(Ordering::Relaxed);
+        assert!(liveness.is_alive_at(last_tick_ms + 3_000));
+        assert!(!liveness.is_alive_at(last_tick_ms + 3_001));
+
+        // a shorter interval is still given the time between checks
+        tick_interval_ms.store(10, Ordering::Relaxed);
+        assert!(liveness.is_alive_at(last_tick_ms + 1_000));
+        assert!(!liveness.is_alive_at(last_tick_ms + 1_001));
+
+        liveness.record_exit();
+        assert!(!liveness.is_alive_at(last_tick_ms));
+    }
+}
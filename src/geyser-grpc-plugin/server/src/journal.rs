@@ -0,0 +1,679 @@
+//! Append-only on-disk journal of account updates used to replay updates to clients that reconnect.
+//!
+//! Updates are written to segment files named after the writer's run and the first slot they cover.
+//! The run is incremented every time the writer starts, since seqs restart along with the validator.
+//! A new segment is started once an update's slot is at least `slots_per_segment` past the current
+//! segment's first slot, and the oldest segments are deleted once more than `max_segments` exist.
+//!
+//! Each record is a little-endian u32 length followed by a protobuf encoded [TimestampedAccountUpdate].
+//! Updates dropped because the writer fell behind are recorded as a gap, so that replays crossing it
+//! fail rather than silently skip them: a length with [GAP_RECORD_FLAG] set, followed by the lowest
+//! and highest seq and the highest slot dropped as little-endian u64s.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use jito_geyser_protos::solana::geyser::TimestampedAccountUpdate;
+use log::*;
+use prost::Message;
+use serde_derive::Deserialize;
+use thiserror::Error;
+
+const SEGMENT_FILE_EXTENSION: &str = "journal";
+
+/// How often buffered records are flushed to disk when updates are continuously streaming in.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set in the length of gap records.
+const GAP_RECORD_FLAG: u32 = 1 << 31;
+const GAP_RECORD_LEN: u32 = 3 * 8;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JournalConfig {
+    /// Directory the segment files are written to.
+    pub dir: String,
+
+    /// Number of slots covered by each segment file.
+    #[serde(default = "default_slots_per_segment")]
+    pub slots_per_segment: u64,
+
+    /// Maximum number of segment files retained, the oldest are deleted first.
+    #[serde(default = "default_max_segments")]
+    pub max_segments: usize,
+
+    /// Size of the channel buffering updates before they're written.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+}
+
+impl JournalConfig {
+    const DEFAULT_SLOTS_PER_SEGMENT: u64 = 1_000;
+    const DEFAULT_MAX_SEGMENTS: usize = 10;
+    const DEFAULT_BUFFER_SIZE: usize = 100_000;
+}
+
+fn default_slots_per_segment() -> u64 {
+    JournalConfig::DEFAULT_SLOTS_PER_SEGMENT
+}
+
+fn default_max_segments() -> usize {
+    JournalConfig::DEFAULT_MAX_SEGMENTS
+}
+
+fn default_buffer_size() -> usize {
+    JournalConfig::DEFAULT_BUFFER_SIZE
+}
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("IoError {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("DecodeError {0}")]
+    DecodeError(#[from] prost::DecodeError),
+
+    #[error("slot {from_slot} has been pruned, the oldest journaled slot is {oldest_slot}")]
+    SlotPruned { from_slot: u64, oldest_slot: u64 },
+
+    #[error("updates with seqs {lowest_seq} to {highest_seq} were dropped from the journal")]
+    Gap { lowest_seq: u64, highest_seq: u64 },
+
+    #[error("journal has not caught up to seq {seq}")]
+    NotCaughtUp { seq: u64 },
+}
+
+pub type JournalResult<T> = Result<T, JournalError>;
+
+enum JournalRecord {
+    Update(TimestampedAccountUpdate),
+    Gap(JournalGap),
+}
+
+/// Updates dropped rather than journaled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct JournalGap {
+    lowest_seq: u64,
+    highest_seq: u64,
+    highest_slot: u64,
+}
+
+impl JournalGap {
+    fn add(&mut self, seq: u64, slot: u64) {
+        self.lowest_seq = self.lowest_seq.min(seq);
+        self.highest_seq = self.highest_seq.max(seq);
+        self.highest_slot = self.highest_slot.max(slot);
+    }
+}
+
+/// Writes account updates to the journal on a dedicated thread.
+pub struct JournalWriter {
+    record_sender: Sender<JournalRecord>,
+    /// Updates dropped since the last gap was queued, journaled ahead of any further updates.
+    pending_gap: Mutex<Option<JournalGap>>,
+    t_hdl: JoinHandle<()>,
+}
+
+impl JournalWriter {
+    /// Spawns the writer thread, returning the writer alongside a reader over the same directory.
+    pub fn new(config: &JournalConfig) -> JournalResult<(Self, JournalReader)> {
+        let dir = PathBuf::from(&config.dir);
+        fs::create_dir_all(&dir)?;
+        let run = list_segments(&dir)?
+            .iter()
+            .map(|segment| segment.run + 1)
+            .max()
+            .unwrap_or_default();
+
+        let highest_written_seq = Arc::new(AtomicU64::new(0));
+        let (record_sender, record_receiver) = bounded(config.buffer_size);
+
+        let t_hdl = {
+            let dir = dir.clone();
+            let highest_written_seq = highest_written_seq.clone();
+            let slots_per_segment = config.slots_per_segment.max(1);
+            let max_segments = config.max_segments.max(1);
+            Builder::new()
+                .name("geyser-journal-writer".to_string())
+                .spawn(move || {
+                    if let Err(e) = Self::write_loop(
+                        dir,
+                        run,
+                        slots_per_segment,
+                        max_segments,
+                        record_receiver,
+                        highest_written_seq,
+                    ) {
+                        error!("journal writer exited with error: {}", e);
+                    }
+                })?
+        };
+
+        Ok((
+            Self {
+                record_sender,
+                pending_gap: Mutex::new(None),
+                t_hdl,
+            },
+            JournalReader {
+                dir,
+                run,
+                highest_written_seq,
+            },
+        ))
+    }
+
+    /// Queues the update to be journaled without blocking. Returns false if the writer has fallen
+    /// behind, in which case the update is dropped and journaled as part of a gap.
+    pub fn send(&self, update: TimestampedAccountUpdate) -> bool {
+        let Some((seq, slot)) = update.account_update.as_ref().map(|u| (u.seq, u.slot)) else {
+            return true;
+        };
+        let mut pending_gap = self.pending_gap.lock().unwrap();
+        if let Some(gap) = *pending_gap {
+            if self.record_sender.try_send(JournalRecord::Gap(gap)).is_ok() {
+                *pending_gap = None;
+            }
+        }
+        if pending_gap.is_none()
+            && self
+                .record_sender
+                .try_send(JournalRecord::Update(update))
+                .is_ok()
+        {
+            return true;
+        }
+        pending_gap
+            .get_or_insert(JournalGap {
+                lowest_seq: seq,
+                highest_seq: seq,
+                highest_slot: slot,
+            })
+            .add(seq, slot);
+        false
+    }
+
+    /// Flushes any remaining updates and waits for the writer thread to exit.
+    pub fn join(self) {
+        if let Some(gap) = self.pending_gap.into_inner().unwrap() {
+            let _ = self.record_sender.send(JournalRecord::Gap(gap));
+        }
+        drop(self.record_sender);
+        self.t_hdl.join().unwrap();
+    }
+
+    fn write_loop(
+        dir: PathBuf,
+        run: u64,
+        slots_per_segment: u64,
+        max_segments: usize,
+        record_receiver: Receiver<JournalRecord>,
+        highest_written_seq: Arc<AtomicU64>,
+    ) -> JournalResult<()> {
+        let mut segment: Option<(u64, BufWriter<File>)> = None;
+        let mut pending_seq = 0;
+        let mut is_dirty = false;
+        let mut last_flush = Instant::now();
+
+        loop {
+            let record = match record_receiver.recv_timeout(FLUSH_INTERVAL) {
+                Ok(record) => Some(record),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            if let Some(record) = record {
+                let slot = match &record {
+                    JournalRecord::Update(update) => {
+                        let Some(account_update) = update.account_update.as_ref() else {
+                            continue;
+                        };
+                        pending_seq = pending_seq.max(account_update.seq);
+                        account_update.slot
+                    }
+                    JournalRecord::Gap(gap) => {
+                        warn!(
+                            "journaling a gap of dropped updates with seqs {} to {}",
+                            gap.lowest_seq, gap.highest_seq
+                        );
+                        pending_seq = pending_seq.max(gap.highest_seq);
+                        gap.highest_slot
+                    }
+                };
+
+                let needs_rotation = match &segment {
+                    None => true,
+                    Some((start_slot, _)) => slot >= start_slot + slots_per_segment,
+                };
+                if needs_rotation {
+                    if let Some((_, mut writer)) = segment.take() {
+                        writer.flush()?;
+                    }
+                    let start_slot = slot - slot % slots_per_segment;
+                    let file = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(segment_path(&dir, run, start_slot))?;
+                    segment = Some((start_slot, BufWriter::new(file)));
+                    prune_segments(&dir, max_segments)?;
+                }
+
+                let (_, writer) = segment.as_mut().unwrap();
+                write_record(writer, &record)?;
+                is_dirty = true;
+            }
+
+            if is_dirty && (record_receiver.is_empty() || last_flush.elapsed() >= FLUSH_INTERVAL) {
+                if let Some((_, writer)) = segment.as_mut() {
+                    writer.flush()?;
+                }
+                highest_written_seq.store(pending_seq, Ordering::Release);
+                is_dirty = false;
+                last_flush = Instant::now();
+            }
+        }
+
+        if let Some((_, mut writer)) = segment.take() {
+            writer.flush()?;
+        }
+        highest_written_seq.store(pending_seq, Ordering::Release);
+
+        Ok(())
+    }
+}
+
+/// Reads journaled updates back out of the segment files.
+#[derive(Clone)]
+pub struct JournalReader {
+    dir: PathBuf,
+
+    /// Run of the writer, whose updates are the only ones bounded by seq when replaying.
+    run: u64,
+
+    /// Highest update seq flushed to disk thus far.
+    highest_written_seq: Arc<AtomicU64>,
+}
+
+impl JournalReader {
+    /// Blocks until every update up to and including `seq` has been flushed or the timeout elapses.
+    pub fn wait_for_seq(&self, seq: u64, timeout: Duration) -> JournalResult<()> {
+        let start = Instant::now();
+        while self.highest_written_seq.load(Ordering::Acquire) < seq {
+            if start.elapsed() >= timeout {
+                return Err(JournalError::NotCaughtUp { seq });
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        Ok(())
+    }
+
+    /// Returns an error if updates for `from_slot` may have been pruned from the journal.
+    pub fn check_retained(&self, from_slot: u64) -> JournalResult<()> {
+        match list_segments(&self.dir)?.first() {
+            Some(oldest) if from_slot < oldest.start_slot => Err(JournalError::SlotPruned {
+                from_slot,
+                oldest_slot: oldest.start_slot,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Invokes `f` on every journaled update with a slot >= `from_slot` in the order they were
+    /// written, up until the update with seq `max_seq` for those written by the current run.
+    /// Stops early if `f` returns false, and fails if updates in that range were dropped.
+    pub fn replay<F>(&self, from_slot: u64, max_seq: u64, mut f: F) -> JournalResult<()>
+    where
+        F: FnMut(TimestampedAccountUpdate) -> bool,
+    {
+        let segments = list_segments(&self.dir)?;
+        let Some(oldest) = segments.first() else {
+            return Ok(());
+        };
+        if from_slot < oldest.start_slot {
+            return Err(JournalError::SlotPruned {
+                from_slot,
+                oldest_slot: oldest.start_slot,
+            });
+        }
+
+        for (i, segment) in segments.iter().enumerate() {
+            // Updates are only ever appended to their run's current segment, which never starts
+            // past the update's slot, so a segment followed by one from the same run starting at or
+            // before from_slot can't hold anything >= from_slot.
+            if segments
+                .get(i + 1)
+                .is_some_and(|next| next.run == segment.run && next.start_slot <= from_slot)
+            {
+                continue;
+            }
+            // previous runs ended before the subscription was registered
+            let is_current_run = segment.run == self.run;
+
+            let file = match File::open(&segment.path) {
+                Ok(file) => file,
+                // pruned while replaying
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut reader = BufReader::new(file);
+            while let Some(record) = read_record(&mut reader)? {
+                match record {
+                    JournalRecord::Update(update) => {
+                        let Some(account_update) = update.account_update.as_ref() else {
+                            continue;
+                        };
+                        if account_update.slot < from_slot
+                            || (is_current_run && account_update.seq > max_seq)
+                        {
+                            continue;
+                        }
+                        if !f(update) {
+                            return Ok(());
+                        }
+                    }
+                    JournalRecord::Gap(gap) => {
+                        if gap.highest_slot < from_slot
+                            || (is_current_run && gap.lowest_seq > max_seq)
+                        {
+                            continue;
+                        }
+                        return Err(JournalError::Gap {
+                            lowest_seq: gap.lowest_seq,
+                            highest_seq: gap.highest_seq,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads every update out of a journal in the order they were written, skipping over gaps. The
+/// path may be either a journal directory or a single segment file copied out of one.
+pub fn read_journal(path: &Path) -> JournalResult<Vec<TimestampedAccountUpdate>> {
+    let segment_paths = if path.is_dir() {
+        list_segments(path)?
+            .into_iter()
+            .map(|segment| segment.path)
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut updates = vec![];
+    for segment_path in segment_paths {
+        let mut reader = BufReader::new(File::open(segment_path)?);
+        while let Some(record) = read_record(&mut reader)? {
+            match record {
+                JournalRecord::Update(update) => updates.push(update),
+                JournalRecord::Gap(gap) => warn!(
+                    "journal is missing updates with seqs {} to {}",
+                    gap.lowest_seq, gap.highest_seq
+                ),
+            }
+        }
+    }
+    Ok(updates)
+}
+
+struct Segment {
+    run: u64,
+    start_slot: u64,
+    path: PathBuf,
+}
+
+fn segment_path(dir: &Path, run: u64, start_slot: u64) -> PathBuf {
+    dir.join(format!(
+        "{run:010}-{start_slot:020}.{SEGMENT_FILE_EXTENSION}"
+    ))
+}
+
+/// Returns the segments in the directory in the order they were written.
+fn list_segments(dir: &Path) -> io::Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_FILE_EXTENSION) {
+            continue;
+        }
+        let Some((run, start_slot)) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.split_once('-'))
+        else {
+            continue;
+        };
+        if let (Ok(run), Ok(start_slot)) = (run.parse(), start_slot.parse()) {
+            segments.push(Segment {
+                run,
+                start_slot,
+                path,
+            });
+        }
+    }
+    segments.sort_unstable_by_key(|segment| (segment.run, segment.start_slot));
+    Ok(segments)
+}
+
+fn prune_segments(dir: &Path, max_segments: usize) -> io::Result<()> {
+    let segments = list_segments(dir)?;
+    if segments.len() > max_segments {
+        for segment in &segments[..segments.len() - max_segments] {
+            info!(
+                "pruning journal segment of run {} starting at slot {}",
+                segment.run, segment.start_slot
+            );
+            fs::remove_file(&segment.path)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &JournalRecord) -> io::Result<()> {
+    match record {
+        JournalRecord::Update(update) => {
+            let encoded = update.encode_to_vec();
+            writer.write_all(&(encoded.len() as u32).to_le_bytes())?;
+            writer.write_all(&encoded)
+        }
+        JournalRecord::Gap(gap) => {
+            writer.write_all(&(GAP_RECORD_FLAG | GAP_RECORD_LEN).to_le_bytes())?;
+            for value in [gap.lowest_seq, gap.highest_seq, gap.highest_slot] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reads the next record, returning None at the end of the file or on a partially written record.
+fn read_record<R: Read>(reader: &mut R) -> JournalResult<Option<JournalRecord>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len);
+    let mut buf = vec![0u8; (len & !GAP_RECORD_FLAG) as usize];
+    match reader.read_exact(&mut buf) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    if len & GAP_RECORD_FLAG == 0 {
+        return Ok(Some(JournalRecord::Update(
+            TimestampedAccountUpdate::decode(buf.as_slice())?,
+        )));
+    }
+    if buf.len() != GAP_RECORD_LEN as usize {
+        return Err(io::Error::new(ErrorKind::InvalidData, "malformed gap record").into());
+    }
+    let value = |i: usize| u64::from_le_bytes(buf[i * 8..(i + 1) * 8].try_into().unwrap());
+    Ok(Some(JournalRecord::Gap(JournalGap {
+        lowest_seq: value(0),
+        highest_seq: value(1),
+        highest_slot: value(2),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use jito_geyser_protos::solana::geyser::AccountUpdate;
+
+    use super::*;
+
+    fn update(slot: u64, seq: u64) -> TimestampedAccountUpdate {
+        TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                slot,
+                seq,
+                pubkey: vec![1; 32],
+                owner: vec![2; 32],
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }
+    }
+
+    fn test_config(name: &str, max_segments: usize) -> JournalConfig {
+        let dir =
+            std::env::temp_dir().join(format!("geyser-journal-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        JournalConfig {
+            dir: dir.to_str().unwrap().to_string(),
+            slots_per_segment: 10,
+            max_segments,
+            buffer_size: 100,
+        }
+    }
+
+    fn replay_seqs(
+        reader: &JournalReader,
+        from_slot: u64,
+        max_seq: u64,
+    ) -> JournalResult<Vec<u64>> {
+        let mut seqs = vec![];
+        reader.replay(from_slot, max_seq, |u| {
+            seqs.push(u.account_update.unwrap().seq);
+            true
+        })?;
+        Ok(seqs)
+    }
+
+    #[test]
+    fn test_replay_from_slot() {
+        let config = test_config("replay", 10);
+        let (writer, reader) = JournalWriter::new(&config).unwrap();
+        for (seq, slot) in (0..35).enumerate() {
+            assert!(writer.send(update(slot, seq as u64 + 1)));
+        }
+        writer.join();
+
+        assert_eq!(list_segments(Path::new(&config.dir)).unwrap().len(), 4);
+        assert_eq!(
+            replay_seqs(&reader, 28, u64::MAX).unwrap(),
+            (29..=35).collect::<Vec<_>>()
+        );
+        assert_eq!(replay_seqs(&reader, 28, 30).unwrap(), vec![29, 30]);
+        reader.wait_for_seq(35, Duration::ZERO).unwrap();
+
+        let journaled = read_journal(Path::new(&config.dir)).unwrap();
+        assert_eq!(journaled.len(), 35);
+        let last_segment = segment_path(Path::new(&config.dir), 0, 30);
+        assert_eq!(read_journal(&last_segment).unwrap(), journaled[30..]);
+        let _ = fs::remove_dir_all(&config.dir);
+    }
+
+    #[test]
+    fn test_pruned_slot() {
+        let config = test_config("pruned", 2);
+        let (writer, reader) = JournalWriter::new(&config).unwrap();
+        for (seq, slot) in (0..35).enumerate() {
+            assert!(writer.send(update(slot, seq as u64 + 1)));
+        }
+        writer.join();
+
+        assert!(matches!(
+            replay_seqs(&reader, 5, u64::MAX),
+            Err(JournalError::SlotPruned {
+                from_slot: 5,
+                oldest_slot: 20
+            })
+        ));
+        assert_eq!(replay_seqs(&reader, 33, u64::MAX).unwrap(), vec![34, 35]);
+        let _ = fs::remove_dir_all(&config.dir);
+    }
+
+    #[test]
+    fn test_replay_spans_runs() {
+        let config = test_config("runs", 10);
+        let (writer, _) = JournalWriter::new(&config).unwrap();
+        for seq in 1..=10 {
+            assert!(writer.send(update(5, seq)));
+        }
+        writer.join();
+        // seqs restart along with the validator
+        let (writer, reader) = JournalWriter::new(&config).unwrap();
+        for seq in 1..=5 {
+            assert!(writer.send(update(15, seq)));
+        }
+        writer.join();
+
+        let mut expected: Vec<u64> = (1..=10).collect();
+        expected.extend(1..=3);
+        assert_eq!(replay_seqs(&reader, 0, 3).unwrap(), expected);
+        assert_eq!(replay_seqs(&reader, 10, 3).unwrap(), vec![1, 2, 3]);
+        let _ = fs::remove_dir_all(&config.dir);
+    }
+
+    #[test]
+    fn test_replay_fails_across_gap() {
+        let config = test_config("gap", 10);
+        let dir = PathBuf::from(&config.dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut segment = File::create(segment_path(&dir, 0, 0)).unwrap();
+        let gap = JournalGap {
+            lowest_seq: 2,
+            highest_seq: 3,
+            highest_slot: 6,
+        };
+        for record in [
+            JournalRecord::Update(update(5, 1)),
+            JournalRecord::Gap(gap),
+            JournalRecord::Update(update(7, 4)),
+        ] {
+            write_record(&mut segment, &record).unwrap();
+        }
+        let reader = JournalReader {
+            dir: dir.clone(),
+            run: 0,
+            highest_written_seq: Arc::new(AtomicU64::new(4)),
+        };
+
+        assert!(matches!(
+            replay_seqs(&reader, 0, 4),
+            Err(JournalError::Gap {
+                lowest_seq: 2,
+                highest_seq: 3
+            })
+        ));
+        // neither the dropped seqs nor slots are replayed
+        assert_eq!(replay_seqs(&reader, 0, 1).unwrap(), vec![1]);
+        assert_eq!(replay_seqs(&reader, 7, 4).unwrap(), vec![4]);
+        assert_eq!(read_journal(&dir).unwrap().len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
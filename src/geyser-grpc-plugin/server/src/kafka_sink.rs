@@ -0,0 +1,691 @@
+//! Produces updates to a single Kafka topic partition, one protobuf encoded [SinkMessage] per
+//! record. Account updates are keyed by pubkey.
+//!
+//! Speaks just enough of the Kafka protocol (Produce v3 with v2 record batches and Metadata v1)
+//! to write to the partition leader. The configured broker is assumed to lead the partition until
+//! it answers otherwise, upon which the leader is looked up thru it and the batch retried once.
+//! Batches are sent uncompressed and without idempotence, so a batch may be written twice if the
+//! broker's response is lost.
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jito_geyser_protos::solana::geyser::{sink_message, SinkMessage};
+use log::*;
+use prost::Message;
+use serde_derive::Deserialize;
+
+use crate::sink::{SinkError, SinkResult, SinkWriter};
+
+const PRODUCE_API_KEY: i16 = 0;
+const PRODUCE_API_VERSION: i16 = 3;
+const METADATA_API_KEY: i16 = 3;
+const METADATA_API_VERSION: i16 = 1;
+const NOT_LEADER_OR_FOLLOWER: i16 = 6;
+const CLIENT_ID: &str = "geyser-grpc-plugin";
+const RECORD_BATCH_MAGIC: i8 = 2;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct KafkaSinkConfig {
+    /// Address of a broker of the cluster, i.e. `host:port`, thru which the partition leader is
+    /// looked up.
+    pub broker: String,
+    pub topic: String,
+    #[serde(default)]
+    pub partition: i32,
+
+    /// Acknowledgements required from the broker, either 0, 1 or -1 for all in-sync replicas.
+    #[serde(default = "default_acks")]
+    pub acks: i16,
+
+    /// Bounds connecting to the broker, writing a batch and waiting for its acknowledgement.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_acks() -> i16 {
+    1
+}
+
+fn default_timeout_ms() -> u64 {
+    5_000
+}
+
+pub struct KafkaSinkWriter {
+    config: KafkaSinkConfig,
+    stream: Option<TcpStream>,
+    correlation_id: i32,
+
+    /// Address of the partition leader, once looked up. Batches go to the configured broker until
+    /// then.
+    leader: Option<String>,
+}
+
+impl KafkaSinkWriter {
+    pub fn new(config: &KafkaSinkConfig) -> Self {
+        Self {
+            config: config.clone(),
+            stream: None,
+            correlation_id: 0,
+            leader: None,
+        }
+    }
+
+    fn connect(&self, broker: &str) -> SinkResult<TcpStream> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let addr = broker
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| SinkError::InvalidConfig(format!("unresolved broker {broker}")))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+        info!("connected to kafka broker {broker}");
+        Ok(stream)
+    }
+
+    fn produce(&mut self, messages: &[SinkMessage]) -> SinkResult<()> {
+        if self.stream.is_none() {
+            let leader = self.leader.as_deref().unwrap_or(&self.config.broker);
+            self.stream = Some(self.connect(leader)?);
+        }
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+        let request = encode_produce_request(&self.config, self.correlation_id, messages);
+
+        let stream = self.stream.as_mut().unwrap();
+        stream.write_all(&request)?;
+        if self.config.acks == 0 {
+            // the broker doesn't respond
+            return Ok(());
+        }
+        let response = read_response(stream)?;
+        check_produce_response(&response, self.correlation_id)
+    }
+
+    /// Looks up the address of the partition leader thru the configured broker.
+    fn find_leader(&mut self) -> SinkResult<String> {
+        let mut stream = self.connect(&self.config.broker)?;
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+        stream.write_all(&encode_metadata_request(
+            &self.config.topic,
+            self.correlation_id,
+        ))?;
+        let response = read_response(&mut stream)?;
+        find_partition_leader(&response, self.correlation_id, self.config.partition)
+    }
+}
+
+impl SinkWriter for KafkaSinkWriter {
+    fn write(&mut self, messages: &[SinkMessage]) -> SinkResult<()> {
+        let mut result = self.produce(messages);
+        if matches!(result, Err(SinkError::KafkaNotLeader(_))) {
+            // leadership moved to another broker, e.g. as the leader restarted
+            self.stream = None;
+            result = self.find_leader().and_then(|leader| {
+                info!("kafka partition leader moved to {leader}");
+                self.leader = Some(leader);
+                self.produce(messages)
+            });
+        }
+        if result.is_err() {
+            // reconnect for the next batch, the connection may be out of sync
+            self.stream = None;
+        }
+        result
+    }
+}
+
+fn encode_produce_request(
+    config: &KafkaSinkConfig,
+    correlation_id: i32,
+    messages: &[SinkMessage],
+) -> Vec<u8> {
+    let record_batch = encode_record_batch(messages);
+
+    let mut buf = request_header(PRODUCE_API_KEY, PRODUCE_API_VERSION, correlation_id);
+    // null transactional_id
+    buf.extend_from_slice(&(-1i16).to_be_bytes());
+    buf.extend_from_slice(&config.acks.to_be_bytes());
+    buf.extend_from_slice(&(config.timeout_ms.min(i32::MAX as u64) as i32).to_be_bytes());
+    // a single topic with a single partition
+    buf.extend_from_slice(&1i32.to_be_bytes());
+    put_string(&mut buf, &config.topic);
+    buf.extend_from_slice(&1i32.to_be_bytes());
+    buf.extend_from_slice(&config.partition.to_be_bytes());
+    buf.extend_from_slice(&(record_batch.len() as i32).to_be_bytes());
+    buf.extend_from_slice(&record_batch);
+    put_request_size(&mut buf);
+    buf
+}
+
+fn encode_metadata_request(topic: &str, correlation_id: i32) -> Vec<u8> {
+    let mut buf = request_header(METADATA_API_KEY, METADATA_API_VERSION, correlation_id);
+    buf.extend_from_slice(&1i32.to_be_bytes());
+    put_string(&mut buf, topic);
+    put_request_size(&mut buf);
+    buf
+}
+
+/// Leaves room for the request size, which is filled in by [put_request_size] once the request
+/// has been written out.
+fn request_header(api_key: i16, api_version: i16, correlation_id: i32) -> Vec<u8> {
+    let mut buf = vec![0; 4];
+    buf.extend_from_slice(&api_key.to_be_bytes());
+    buf.extend_from_slice(&api_version.to_be_bytes());
+    buf.extend_from_slice(&correlation_id.to_be_bytes());
+    put_string(&mut buf, CLIENT_ID);
+    buf
+}
+
+fn put_request_size(buf: &mut [u8]) {
+    let size = (buf.len() - 4) as i32;
+    buf[..4].copy_from_slice(&size.to_be_bytes());
+}
+
+fn encode_record_batch(messages: &[SinkMessage]) -> Vec<u8> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut value = vec![];
+    for (offset_delta, message) in messages.iter().enumerate() {
+        value.clear();
+        message.encode(&mut value).unwrap();
+        let key = match &message.msg {
+            Some(sink_message::Msg::AccountUpdate(u)) => {
+                u.account_update.as_ref().map(|u| u.pubkey.as_slice())
+            }
+            _ => None,
+        };
+
+        record.clear();
+        // attributes, then timestamp delta
+        record.push(0);
+        put_varint(&mut record, 0);
+        put_varint(&mut record, offset_delta as i64);
+        match key {
+            Some(key) => {
+                put_varint(&mut record, key.len() as i64);
+                record.extend_from_slice(key);
+            }
+            None => put_varint(&mut record, -1),
+        }
+        put_varint(&mut record, value.len() as i64);
+        record.extend_from_slice(&value);
+        // no headers
+        put_varint(&mut record, 0);
+
+        put_varint(&mut records, record.len() as i64);
+        records.extend_from_slice(&record);
+    }
+
+    // the part of the batch covered by the crc
+    let mut body = vec![];
+    body.extend_from_slice(&0i16.to_be_bytes());
+    body.extend_from_slice(&(messages.len().saturating_sub(1) as i32).to_be_bytes());
+    body.extend_from_slice(&timestamp.to_be_bytes());
+    body.extend_from_slice(&timestamp.to_be_bytes());
+    // no producer id, epoch or sequence
+    body.extend_from_slice(&(-1i64).to_be_bytes());
+    body.extend_from_slice(&(-1i16).to_be_bytes());
+    body.extend_from_slice(&(-1i32).to_be_bytes());
+    body.extend_from_slice(&(messages.len() as i32).to_be_bytes());
+    body.extend_from_slice(&records);
+
+    let mut batch = vec![];
+    batch.extend_from_slice(&0i64.to_be_bytes());
+    // batch length counts everything after itself
+    batch.extend_from_slice(&((4 + 1 + 4 + body.len()) as i32).to_be_bytes());
+    // partition leader epoch, set by the broker
+    batch.extend_from_slice(&(-1i32).to_be_bytes());
+    batch.push(RECORD_BATCH_MAGIC as u8);
+    batch.extend_from_slice(&crc32c(&body).to_be_bytes());
+    batch.extend_from_slice(&body);
+    batch
+}
+
+fn read_response(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut size = [0; 4];
+    stream.read_exact(&mut size)?;
+    let size = i32::from_be_bytes(size);
+    if size < 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("negative response size {size}"),
+        ));
+    }
+    let mut response = vec![0; size as usize];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+fn check_correlation_id(reader: &mut ResponseReader, correlation_id: i32) -> SinkResult<()> {
+    let response_correlation_id = reader.i32()?;
+    if response_correlation_id != correlation_id {
+        return Err(SinkError::KafkaError(format!(
+            "expected correlation id {correlation_id}, got {response_correlation_id}"
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the first error code reported by the broker, if any.
+fn check_produce_response(response: &[u8], correlation_id: i32) -> SinkResult<()> {
+    let mut reader = ResponseReader(response);
+    check_correlation_id(&mut reader, correlation_id)?;
+    for _ in 0..reader.i32()? {
+        let topic_len = reader.i16()?;
+        reader.skip(topic_len.max(0) as usize)?;
+        for _ in 0..reader.i32()? {
+            let partition = reader.i32()?;
+            let error_code = reader.i16()?;
+            // base offset and log append time
+            reader.skip(16)?;
+            match error_code {
+                0 => {}
+                NOT_LEADER_OR_FOLLOWER => return Err(SinkError::KafkaNotLeader(partition)),
+                _ => {
+                    return Err(SinkError::KafkaError(format!(
+                        "partition {partition} error code {error_code}"
+                    )))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the `host:port` of the broker leading the partition, as of a metadata response.
+fn find_partition_leader(
+    response: &[u8],
+    correlation_id: i32,
+    partition: i32,
+) -> SinkResult<String> {
+    let mut reader = ResponseReader(response);
+    check_correlation_id(&mut reader, correlation_id)?;
+    let brokers = (0..reader.i32()?)
+        .map(|_| {
+            let node_id = reader.i32()?;
+            let host = reader.string()?;
+            let port = reader.i32()?;
+            // rack
+            reader.string()?;
+            Ok((node_id, format!("{host}:{port}")))
+        })
+        .collect::<SinkResult<Vec<_>>>()?;
+    // controller id
+    reader.skip(4)?;
+
+    let mut leader_id = None;
+    for _ in 0..reader.i32()? {
+        let topic_error_code = reader.i16()?;
+        reader.string()?;
+        // is_internal
+        reader.skip(1)?;
+        if topic_error_code != 0 {
+            return Err(SinkError::KafkaError(format!(
+                "topic metadata error code {topic_error_code}"
+            )));
+        }
+        for _ in 0..reader.i32()? {
+            // error code, then partition
+            reader.skip(2)?;
+            let partition_id = reader.i32()?;
+            let leader = reader.i32()?;
+            // replicas and in-sync replicas
+            for _ in 0..2 {
+                let replicas = reader.i32()?;
+                reader.skip(4 * replicas.max(0) as usize)?;
+            }
+            if partition_id == partition {
+                leader_id = Some(leader);
+            }
+        }
+    }
+    let leader_id = leader_id
+        .ok_or_else(|| SinkError::KafkaError(format!("no leader for partition {partition}")))?;
+    brokers
+        .into_iter()
+        .find_map(|(node_id, address)| (node_id == leader_id).then_some(address))
+        .ok_or_else(|| SinkError::KafkaError(format!("unknown leader {leader_id}")))
+}
+
+struct ResponseReader<'a>(&'a [u8]);
+
+impl<'a> ResponseReader<'a> {
+    fn bytes(&mut self, n: usize) -> SinkResult<&'a [u8]> {
+        let bytes = self
+            .0
+            .get(..n)
+            .ok_or_else(|| SinkError::KafkaError("truncated response".to_string()))?;
+        self.0 = &self.0[n..];
+        Ok(bytes)
+    }
+
+    fn take<const N: usize>(&mut self) -> SinkResult<[u8; N]> {
+        self.bytes(N).map(|bytes| bytes.try_into().unwrap())
+    }
+
+    fn i16(&mut self) -> SinkResult<i16> {
+        self.take().map(i16::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> SinkResult<i32> {
+        self.take().map(i32::from_be_bytes)
+    }
+
+    fn string(&mut self) -> SinkResult<String> {
+        let len = self.i16()?.max(0) as usize;
+        self.bytes(len)
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn skip(&mut self, n: usize) -> SinkResult<()> {
+        self.bytes(n).map(|_| ())
+    }
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as i16).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Zigzag encoded, as used for the fields of records.
+fn put_varint(buf: &mut Vec<u8>, value: i64) {
+    let mut v = ((value << 1) ^ (value >> 63)) as u64;
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+/// CRC-32C (Castagnoli), as used to checksum record batches.
+fn crc32c(bytes: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut j = 0;
+            while j < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0x82f6_3b78
+                } else {
+                    crc >> 1
+                };
+                j += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    !bytes.iter().fold(!0u32, |crc, b| {
+        TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{SocketAddr, TcpListener},
+        thread,
+    };
+
+    use jito_geyser_protos::solana::geyser::{
+        AccountUpdate, SlotUpdate, TimestampedAccountUpdate, TimestampedSlotUpdate,
+    };
+
+    use super::*;
+
+    struct RequestReader<'a>(&'a [u8]);
+
+    impl RequestReader<'_> {
+        fn bytes(&mut self, n: usize) -> &[u8] {
+            let (bytes, rest) = self.0.split_at(n);
+            self.0 = rest;
+            bytes
+        }
+
+        fn i16(&mut self) -> i16 {
+            i16::from_be_bytes(self.bytes(2).try_into().unwrap())
+        }
+
+        fn i32(&mut self) -> i32 {
+            i32::from_be_bytes(self.bytes(4).try_into().unwrap())
+        }
+
+        fn string(&mut self) -> String {
+            let len = self.i16() as usize;
+            String::from_utf8(self.bytes(len).to_vec()).unwrap()
+        }
+
+        fn varint(&mut self) -> i64 {
+            let (mut v, mut shift) = (0u64, 0);
+            loop {
+                let b = self.bytes(1)[0];
+                v |= ((b & 0x7f) as u64) << shift;
+                shift += 7;
+                if b & 0x80 == 0 {
+                    return (v >> 1) as i64 ^ -((v & 1) as i64);
+                }
+            }
+        }
+
+        fn varbytes(&mut self) -> Option<Vec<u8>> {
+            let len = self.varint();
+            (len >= 0).then(|| self.bytes(len as usize).to_vec())
+        }
+    }
+
+    /// Topic and keyed records of a produce request.
+    type Produced = (String, Vec<(Option<Vec<u8>>, SinkMessage)>);
+
+    /// Stands in for a broker, decoding a single produce request.
+    fn broker_stand_in(listener: TcpListener, error_code: i16) -> thread::JoinHandle<Produced> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_response(&mut stream).unwrap();
+            let mut reader = RequestReader(&request);
+            assert_eq!(reader.i16(), PRODUCE_API_KEY);
+            assert_eq!(reader.i16(), PRODUCE_API_VERSION);
+            let correlation_id = reader.i32();
+            assert_eq!(reader.string(), CLIENT_ID);
+            assert_eq!(reader.i16(), -1);
+            assert_eq!(reader.i16(), 1);
+            reader.i32();
+            assert_eq!(reader.i32(), 1);
+            let topic = reader.string();
+            assert_eq!(reader.i32(), 1);
+            let partition = reader.i32();
+            let batch_len = reader.i32() as usize;
+            assert_eq!(reader.0.len(), batch_len);
+
+            // record batch header
+            reader.bytes(8);
+            assert_eq!(reader.i32() as usize, batch_len - 12);
+            reader.i32();
+            assert_eq!(reader.bytes(1)[0] as i8, RECORD_BATCH_MAGIC);
+            let crc = reader.i32() as u32;
+            assert_eq!(crc, crc32c(reader.0));
+            reader.bytes(2 + 4 + 8 + 8 + 8 + 2 + 4);
+            let records = (0..reader.i32())
+                .map(|_| {
+                    reader.varint();
+                    reader.bytes(1);
+                    reader.varint();
+                    reader.varint();
+                    let key = reader.varbytes();
+                    let value = reader.varbytes().unwrap();
+                    assert_eq!(reader.varint(), 0);
+                    (key, SinkMessage::decode(value.as_slice()).unwrap())
+                })
+                .collect();
+
+            let mut response = vec![];
+            response.extend_from_slice(&correlation_id.to_be_bytes());
+            response.extend_from_slice(&1i32.to_be_bytes());
+            put_string(&mut response, &topic);
+            response.extend_from_slice(&1i32.to_be_bytes());
+            response.extend_from_slice(&partition.to_be_bytes());
+            response.extend_from_slice(&error_code.to_be_bytes());
+            response.extend_from_slice(&[0; 16]);
+            response.extend_from_slice(&0i32.to_be_bytes());
+            stream
+                .write_all(&(response.len() as i32).to_be_bytes())
+                .unwrap();
+            stream.write_all(&response).unwrap();
+            (topic, records)
+        })
+    }
+
+    /// Stands in for a broker, answering a single metadata request with `leader` leading every
+    /// partition of the requested topic.
+    fn metadata_stand_in(listener: TcpListener, leader: SocketAddr) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_response(&mut stream).unwrap();
+            let mut reader = RequestReader(&request);
+            assert_eq!(reader.i16(), METADATA_API_KEY);
+            assert_eq!(reader.i16(), METADATA_API_VERSION);
+            let correlation_id = reader.i32();
+            assert_eq!(reader.string(), CLIENT_ID);
+            assert_eq!(reader.i32(), 1);
+            let topic = reader.string();
+
+            let mut response = vec![];
+            response.extend_from_slice(&correlation_id.to_be_bytes());
+            // a single broker, without a rack, which is also the controller
+            response.extend_from_slice(&1i32.to_be_bytes());
+            response.extend_from_slice(&1i32.to_be_bytes());
+            put_string(&mut response, &leader.ip().to_string());
+            response.extend_from_slice(&(leader.port() as i32).to_be_bytes());
+            response.extend_from_slice(&(-1i16).to_be_bytes());
+            response.extend_from_slice(&1i32.to_be_bytes());
+            // a single topic with a single partition
+            response.extend_from_slice(&1i32.to_be_bytes());
+            response.extend_from_slice(&0i16.to_be_bytes());
+            put_string(&mut response, &topic);
+            response.push(0);
+            response.extend_from_slice(&1i32.to_be_bytes());
+            response.extend_from_slice(&0i16.to_be_bytes());
+            response.extend_from_slice(&0i32.to_be_bytes());
+            response.extend_from_slice(&1i32.to_be_bytes());
+            for _ in 0..2 {
+                response.extend_from_slice(&1i32.to_be_bytes());
+                response.extend_from_slice(&1i32.to_be_bytes());
+            }
+            stream
+                .write_all(&(response.len() as i32).to_be_bytes())
+                .unwrap();
+            stream.write_all(&response).unwrap();
+        })
+    }
+
+    fn config(listener: &TcpListener) -> KafkaSinkConfig {
+        KafkaSinkConfig {
+            broker: listener.local_addr().unwrap().to_string(),
+            topic: "geyser".to_string(),
+            partition: 0,
+            acks: default_acks(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn test_produces_to_broker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut writer = KafkaSinkWriter::new(&config(&listener));
+        let broker = broker_stand_in(listener, 0);
+
+        let pubkey = vec![7; 32];
+        let messages = vec![
+            SinkMessage {
+                msg: Some(sink_message::Msg::AccountUpdate(TimestampedAccountUpdate {
+                    ts: None,
+                    account_update: Some(AccountUpdate {
+                        pubkey: pubkey.clone(),
+                        ..AccountUpdate::default()
+                    }),
+                    timing: None,
+                })),
+            },
+            SinkMessage {
+                msg: Some(sink_message::Msg::SlotUpdate(TimestampedSlotUpdate {
+                    ts: None,
+                    slot_update: Some(SlotUpdate {
+                        slot: 5,
+                        ..SlotUpdate::default()
+                    }),
+                    timing: None,
+                })),
+            },
+        ];
+        writer.write(&messages).unwrap();
+
+        let (topic, records) = broker.join().unwrap();
+        assert_eq!(topic, "geyser");
+        assert_eq!(
+            records,
+            vec![
+                (Some(pubkey), messages[0].clone()),
+                (None, messages[1].clone())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reconnects_after_broker_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut writer = KafkaSinkWriter::new(&config(&listener));
+        let listener_clone = listener.try_clone().unwrap();
+
+        let broker = broker_stand_in(listener, 7);
+        assert!(matches!(
+            writer.write(&[SinkMessage::default()]),
+            Err(SinkError::KafkaError(_))
+        ));
+        broker.join().unwrap();
+        assert!(writer.stream.is_none());
+
+        let broker = broker_stand_in(listener_clone, 0);
+        writer.write(&[SinkMessage::default()]).unwrap();
+        assert_eq!(broker.join().unwrap().1.len(), 1);
+    }
+
+    #[test]
+    fn test_retries_batch_on_partition_leader() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leader_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leader = leader_listener.local_addr().unwrap();
+        let mut writer = KafkaSinkWriter::new(&config(&listener));
+
+        // the configured broker no longer leads the partition
+        let broker = thread::spawn(move || {
+            let listener_clone = listener.try_clone().unwrap();
+            broker_stand_in(listener, NOT_LEADER_OR_FOLLOWER)
+                .join()
+                .unwrap();
+            metadata_stand_in(listener_clone, leader).join().unwrap();
+        });
+        let leader_broker = broker_stand_in(leader_listener, 0);
+        writer.write(&[SinkMessage::default()]).unwrap();
+        broker.join().unwrap();
+        assert_eq!(leader_broker.join().unwrap().1.len(), 1);
+        assert_eq!(writer.leader, Some(leader.to_string()));
+    }
+}
@@ -1,4 +1,35 @@
+pub mod access_control;
+pub mod account_cache;
+pub(crate) mod account_dispatcher;
+pub mod admin;
+pub mod block_assembler;
 pub mod compact_timestamp;
+pub mod config_reload;
+pub mod file_sink;
 pub mod geyser_grpc_plugin;
+pub mod health;
+pub mod journal;
+pub mod kafka_sink;
+pub mod metrics;
+#[cfg(test)]
+mod plugin_tests;
+pub mod pre_encoded;
 pub mod server;
+pub mod sink;
+pub mod slot_coalescer;
+pub mod slot_tracker;
+pub(crate) mod subscriber_channel;
+pub(crate) mod subscriber_queue;
 pub(crate) mod subscription_stream;
+pub mod tls;
+pub mod transaction_linker;
+pub mod update_timing;
+
+/// The Geyser service as implemented by the server, streaming account and program updates
+/// pre-encoded. Messages are otherwise those of [jito_geyser_protos].
+pub mod service {
+    tonic::include_proto!("solana.geyser");
+
+    /// Describes the Geyser service and its messages to clients of the reflection service.
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("geyser_descriptor");
+}
@@ -0,0 +1,380 @@
+//! Prometheus metrics for the plugin and geyser service, optionally served over HTTP.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use axum::{extract::State, http::header::CONTENT_TYPE, routing::get, Router};
+use log::*;
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramTimer, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use serde_derive::Deserialize;
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MetricsConfig {
+    /// Address the `/metrics` HTTP endpoint is served on.
+    pub bind_address: String,
+}
+
+#[derive(Error, Debug)]
+pub enum MetricsError {
+    #[error("PrometheusError {0}")]
+    PrometheusError(#[from] prometheus::Error),
+
+    #[error("IoError {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("AddrParseError {0}")]
+    AddrParseError(#[from] std::net::AddrParseError),
+}
+
+/// The channels updates are piped thru from the plugin to the geyser service.
+#[derive(Clone, Copy, Debug)]
+pub enum Channel {
+    AccountUpdate,
+    SlotUpdate,
+    SlotEntryUpdate,
+    BlockUpdate,
+    TransactionUpdate,
+}
+
+impl Channel {
+    const ALL: [Channel; 5] = [
+        Channel::AccountUpdate,
+        Channel::SlotUpdate,
+        Channel::SlotEntryUpdate,
+        Channel::BlockUpdate,
+        Channel::TransactionUpdate,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Channel::AccountUpdate => "account_update",
+            Channel::SlotUpdate => "slot_update",
+            Channel::SlotEntryUpdate => "slot_entry_update",
+            Channel::BlockUpdate => "block_update",
+            Channel::TransactionUpdate => "transaction_update",
+        }
+    }
+}
+
+/// Events handled by the geyser service's event loop.
+#[derive(Clone, Copy, Debug)]
+pub enum EventLoopEvent {
+    AccountUpdate,
+    SlotUpdate,
+    SlotEntryUpdate,
+    BlockUpdate,
+    TransactionUpdate,
+    SubscriptionAdded,
+    SubscriptionClosed,
+    Heartbeat,
+}
+
+impl EventLoopEvent {
+    const ALL: [EventLoopEvent; 8] = [
+        EventLoopEvent::AccountUpdate,
+        EventLoopEvent::SlotUpdate,
+        EventLoopEvent::SlotEntryUpdate,
+        EventLoopEvent::BlockUpdate,
+        EventLoopEvent::TransactionUpdate,
+        EventLoopEvent::SubscriptionAdded,
+        EventLoopEvent::SubscriptionClosed,
+        EventLoopEvent::Heartbeat,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            EventLoopEvent::AccountUpdate => "account_update",
+            EventLoopEvent::SlotUpdate => "slot_update",
+            EventLoopEvent::SlotEntryUpdate => "slot_entry_update",
+            EventLoopEvent::BlockUpdate => "block_update",
+            EventLoopEvent::TransactionUpdate => "transaction_update",
+            EventLoopEvent::SubscriptionAdded => "subscription_added",
+            EventLoopEvent::SubscriptionClosed => "subscription_closed",
+            EventLoopEvent::Heartbeat => "heartbeat",
+        }
+    }
+}
+
+pub struct GeyserMetrics {
+    registry: Registry,
+
+    channel_queue_depth: Vec<IntGauge>,
+    channel_dropped: Vec<IntCounter>,
+    sink_dropped: IntCounterVec,
+
+    subscription_sent: IntCounterVec,
+    subscription_dropped: IntCounterVec,
+    active_subscriptions: IntGaugeVec,
+
+    event_loop_latency: Vec<Histogram>,
+
+    highest_write_slot: IntGauge,
+    /// Sampled into `highest_write_slot` upon every scrape.
+    highest_write_slot_source: Arc<AtomicU64>,
+}
+
+impl GeyserMetrics {
+    pub fn new(highest_write_slot_source: Arc<AtomicU64>) -> Result<Self, MetricsError> {
+        let registry = Registry::new_custom(Some("geyser".to_string()), None)?;
+
+        let channel_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "channel_queue_depth",
+                "Number of updates queued between the plugin and the geyser service",
+            ),
+            &["channel"],
+        )?;
+        let channel_dropped = IntCounterVec::new(
+            Opts::new(
+                "channel_dropped_total",
+                "Number of updates dropped by the plugin because the channel was full",
+            ),
+            &["channel"],
+        )?;
+        let sink_dropped = IntCounterVec::new(
+            Opts::new(
+                "sink_dropped_total",
+                "Number of updates dropped by the plugin because a sink's buffer was full or writing them failed",
+            ),
+            &["sink"],
+        )?;
+        let subscription_sent = IntCounterVec::new(
+            Opts::new(
+                "subscription_sent_total",
+                "Number of updates queued for a subscriber",
+            ),
+            &["subscription_type", "subscription_id"],
+        )?;
+        let subscription_dropped = IntCounterVec::new(
+            Opts::new(
+                "subscription_dropped_total",
+                "Number of updates dropped for a subscriber that isn't keeping up",
+            ),
+            &["subscription_type", "subscription_id"],
+        )?;
+        let active_subscriptions = IntGaugeVec::new(
+            Opts::new("active_subscriptions", "Number of active subscriptions"),
+            &["subscription_type"],
+        )?;
+        let event_loop_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "event_loop_latency_seconds",
+                "Time taken by the event loop to handle an event",
+            )
+            .buckets(exponential_buckets(1e-6, 4.0, 10)?),
+            &["event"],
+        )?;
+        let highest_write_slot = IntGauge::new(
+            "highest_write_slot",
+            "Highest slot an account write has been observed for",
+        )?;
+
+        registry.register(Box::new(channel_queue_depth.clone()))?;
+        registry.register(Box::new(channel_dropped.clone()))?;
+        registry.register(Box::new(sink_dropped.clone()))?;
+        registry.register(Box::new(subscription_sent.clone()))?;
+        registry.register(Box::new(subscription_dropped.clone()))?;
+        registry.register(Box::new(active_subscriptions.clone()))?;
+        registry.register(Box::new(event_loop_latency.clone()))?;
+        registry.register(Box::new(highest_write_slot.clone()))?;
+
+        Ok(Self {
+            registry,
+            channel_queue_depth: Channel::ALL
+                .iter()
+                .map(|c| channel_queue_depth.with_label_values(&[c.as_str()]))
+                .collect(),
+            channel_dropped: Channel::ALL
+                .iter()
+                .map(|c| channel_dropped.with_label_values(&[c.as_str()]))
+                .collect(),
+            sink_dropped,
+            subscription_sent,
+            subscription_dropped,
+            active_subscriptions,
+            event_loop_latency: EventLoopEvent::ALL
+                .iter()
+                .map(|e| event_loop_latency.with_label_values(&[e.as_str()]))
+                .collect(),
+            highest_write_slot,
+            highest_write_slot_source,
+        })
+    }
+
+    pub fn record_channel_drop(&self, channel: Channel) {
+        self.channel_dropped[channel as usize].inc();
+    }
+
+    pub fn channel_drops(&self, channel: Channel) -> u64 {
+        self.channel_dropped[channel as usize].get()
+    }
+
+    pub fn record_sink_drop(&self, sink: &str) {
+        self.record_sink_drops(sink, 1);
+    }
+
+    pub fn record_sink_drops(&self, sink: &str, updates: usize) {
+        self.sink_dropped
+            .with_label_values(&[sink])
+            .inc_by(updates as u64);
+    }
+
+    pub fn sink_drops(&self, sink: &str) -> u64 {
+        self.sink_dropped.with_label_values(&[sink]).get()
+    }
+
+    pub fn set_channel_queue_depth(&self, channel: Channel, depth: usize) {
+        self.channel_queue_depth[channel as usize].set(depth as i64);
+    }
+
+    pub fn set_active_subscriptions(&self, subscription_type: &str, count: usize) {
+        self.active_subscriptions
+            .with_label_values(&[subscription_type])
+            .set(count as i64);
+    }
+
+    /// Observes the time until the returned timer is dropped.
+    pub fn start_event_timer(&self, event: EventLoopEvent) -> HistogramTimer {
+        self.event_loop_latency[event as usize].start_timer()
+    }
+
+    pub fn subscription_metrics(
+        self: &Arc<Self>,
+        subscription_type: &'static str,
+        subscription_id: String,
+    ) -> SubscriptionMetrics {
+        let labels = [subscription_type, subscription_id.as_str()];
+        SubscriptionMetrics {
+            sent: self.subscription_sent.with_label_values(&labels),
+            dropped: self.subscription_dropped.with_label_values(&labels),
+            subscription_type,
+            subscription_id,
+            metrics: self.clone(),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.highest_write_slot
+            .set(self.highest_write_slot_source.load(Ordering::Relaxed) as i64);
+
+        let mut buf = vec![];
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buf) {
+            error!("error encoding metrics: {}", e);
+        }
+        buf
+    }
+}
+
+/// Send and drop counters of a single subscription, removed from the registry once dropped.
+pub struct SubscriptionMetrics {
+    sent: IntCounter,
+    dropped: IntCounter,
+    subscription_type: &'static str,
+    subscription_id: String,
+    metrics: Arc<GeyserMetrics>,
+}
+
+impl SubscriptionMetrics {
+    pub fn record_sent(&self) {
+        self.sent.inc();
+    }
+
+    pub fn record_dropped(&self) {
+        self.dropped.inc();
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+}
+
+impl Drop for SubscriptionMetrics {
+    fn drop(&mut self) {
+        let labels = [self.subscription_type, self.subscription_id.as_str()];
+        let _ = self.metrics.subscription_sent.remove_label_values(&labels);
+        let _ = self
+            .metrics
+            .subscription_dropped
+            .remove_label_values(&labels);
+    }
+}
+
+/// Serves the metrics in the prometheus text format at `/metrics` until `exit` fires.
+pub fn serve(
+    config: &MetricsConfig,
+    metrics: Arc<GeyserMetrics>,
+    runtime: &tokio::runtime::Runtime,
+    exit: oneshot::Receiver<()>,
+) -> Result<(), MetricsError> {
+    let addr: SocketAddr = config.bind_address.parse()?;
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    info!("serving metrics on {}", addr);
+
+    let app = Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(metrics);
+    runtime.spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("error creating metrics listener: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = exit.await;
+            })
+            .await
+        {
+            error!("metrics server error: {}", e);
+        }
+    });
+    Ok(())
+}
+
+async fn get_metrics(
+    State(metrics): State<Arc<GeyserMetrics>>,
+) -> ([(axum::http::HeaderName, &'static str); 1], Vec<u8>) {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.encode(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_metrics_removed_on_drop() {
+        let metrics = Arc::new(GeyserMetrics::new(Arc::new(AtomicU64::new(42))).unwrap());
+        metrics.record_channel_drop(Channel::SlotUpdate);
+        let subscription_metrics = metrics.subscription_metrics("account", "abc".to_string());
+        subscription_metrics.record_sent();
+        subscription_metrics.record_dropped();
+
+        let encoded = String::from_utf8(metrics.encode()).unwrap();
+        assert!(encoded.contains("geyser_highest_write_slot 42"));
+        assert!(encoded.contains("geyser_channel_dropped_total{channel=\"slot_update\"} 1"));
+        assert!(encoded.contains(
+            "geyser_subscription_sent_total{subscription_id=\"abc\",subscription_type=\"account\"} 1"
+        ));
+
+        drop(subscription_metrics);
+        let encoded = String::from_utf8(metrics.encode()).unwrap();
+        assert!(!encoded.contains("subscription_id=\"abc\""));
+        assert_eq!(metrics.channel_drops(Channel::SlotUpdate), 1);
+    }
+}
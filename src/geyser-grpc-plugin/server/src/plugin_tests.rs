@@ -0,0 +1,1002 @@
+//! Loads the plugin in-process, drives it thru the [GeyserPlugin] callbacks as a validator would
+//! and checks what a client receives over gRPC.
+
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    time::Duration,
+};
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPlugin, ReplicaAccountInfoV3, ReplicaAccountInfoVersions, ReplicaBlockInfoV3,
+    ReplicaBlockInfoVersions, ReplicaEntryInfoV2, ReplicaEntryInfoVersions,
+    ReplicaTransactionInfoV2, ReplicaTransactionInfoVersions, SlotStatus,
+};
+use jito_geyser_protos::solana::geyser::{
+    geyser_admin_client::GeyserAdminClient, geyser_client::GeyserClient, maybe_account_update,
+    maybe_block_update, maybe_slot_entry_update, maybe_slot_update, maybe_transaction_update,
+    AccountUpdate, BackpressurePolicy, KillSubscriptionRequest, ListSubscriptionsRequest,
+    SlotUpdateStatus, SubscribeAccountUpdatesRequest, SubscribeBlockUpdatesRequest,
+    SubscribeProgramsUpdatesRequest, SubscribeSlotEntryUpdateRequest, SubscribeSlotUpdateRequest,
+    SubscribeTransactionUpdatesRequest,
+};
+use serde_json::json;
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, system_transaction,
+    transaction::SanitizedTransaction,
+};
+use solana_transaction_status::TransactionStatusMeta;
+use tokio::runtime::Runtime;
+use tonic::{
+    transport::{Channel, Endpoint},
+    Code, Streaming,
+};
+use tonic_health::pb::{
+    health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
+use tonic_reflection::pb::v1::{
+    server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse, ServerReflectionRequest,
+};
+
+use crate::{
+    access_control::ACCESS_TOKEN_HEADER, geyser_grpc_plugin::GeyserGrpcPlugin,
+    service::geyser_server::SERVICE_NAME,
+};
+
+/// Bounds every wait on the plugin, so that a missing update fails the test instead of hanging.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn unused_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// A loaded plugin and a client connected to it. The plugin is unloaded on drop.
+struct Harness {
+    plugin: GeyserGrpcPlugin,
+    config_path: PathBuf,
+    metrics_port: u16,
+    runtime: Runtime,
+    channel: Channel,
+    client: GeyserClient<Channel>,
+    is_unloaded: bool,
+}
+
+impl Harness {
+    /// Loads the plugin with the test config, overridden by the given fields.
+    fn load(overrides: serde_json::Value) -> Self {
+        let port = unused_port();
+        let metrics_port = unused_port();
+        let mut config = json!({
+            "bind_address": format!("127.0.0.1:{port}"),
+            "account_update_buffer_size": 1000,
+            "slot_update_buffer_size": 1000,
+            "block_update_buffer_size": 1000,
+            "transaction_update_buffer_size": 1000,
+            "metrics_config": {"bind_address": format!("127.0.0.1:{metrics_port}")},
+            "geyser_service_config": {
+                // heartbeats are what tell the tests a subscription has been registered
+                "heartbeat_interval_ms": 10,
+                "subscriber_buffer_size": 1000
+            }
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            config[key] = value.clone();
+        }
+        let config_path =
+            std::env::temp_dir().join(format!("geyser-plugin-test-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&config_path, config.to_string()).unwrap();
+
+        let mut plugin = GeyserGrpcPlugin::default();
+        plugin
+            .on_load(config_path.to_str().unwrap(), false)
+            .unwrap();
+
+        let runtime = Runtime::new().unwrap();
+        let channel = runtime.block_on(async {
+            let endpoint = Endpoint::from_shared(format!("http://127.0.0.1:{port}")).unwrap();
+            let start = std::time::Instant::now();
+            loop {
+                match endpoint.connect().await {
+                    Ok(channel) => return channel,
+                    Err(e) if start.elapsed() > TIMEOUT => panic!("plugin not serving: {e}"),
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        });
+
+        Self {
+            plugin,
+            config_path,
+            metrics_port,
+            runtime,
+            client: GeyserClient::new(channel.clone()),
+            channel,
+            is_unloaded: false,
+        }
+    }
+
+    /// Subscribes with `subscribe`, returning once the service has registered the subscription.
+    fn subscribe<T, F, Fut>(&self, subscribe: F) -> Streaming<T>
+    where
+        F: FnOnce(GeyserClient<Channel>) -> Fut,
+        Fut: std::future::Future<Output = Result<tonic::Response<Streaming<T>>, tonic::Status>>,
+    {
+        let mut stream = self
+            .runtime
+            .block_on(subscribe(self.client.clone()))
+            .unwrap()
+            .into_inner();
+        // only a registered subscription is sent heartbeats
+        self.next(&mut stream);
+        stream
+    }
+
+    fn next<T>(&self, stream: &mut Streaming<T>) -> T {
+        self.next_update(stream, Some)
+    }
+
+    /// Returns the first message `f` maps to an update, skipping heartbeats and the like.
+    fn next_update<T, U>(&self, stream: &mut Streaming<T>, f: impl Fn(T) -> Option<U>) -> U {
+        self.runtime
+            .block_on(async {
+                tokio::time::timeout(TIMEOUT, async {
+                    loop {
+                        let msg = stream.message().await.unwrap().expect("stream closed");
+                        if let Some(update) = f(msg) {
+                            return update;
+                        }
+                    }
+                })
+                .await
+            })
+            .expect("timed out waiting for an update")
+    }
+
+    /// Returns the status the stream ends with, skipping what's streamed ahead of it.
+    fn next_status<T>(&self, stream: &mut Streaming<T>) -> tonic::Status {
+        self.runtime
+            .block_on(async {
+                tokio::time::timeout(TIMEOUT, async {
+                    loop {
+                        match stream.message().await {
+                            Ok(Some(_)) => {}
+                            Ok(None) => panic!("stream ended without a status"),
+                            Err(status) => return status,
+                        }
+                    }
+                })
+                .await
+            })
+            .expect("timed out waiting for a status")
+    }
+
+    fn update_account(&self, pubkey: &Pubkey, owner: &Pubkey, write_version: u64) {
+        self.notify_account(pubkey, owner, write_version, false);
+    }
+
+    fn notify_account(
+        &self,
+        pubkey: &Pubkey,
+        owner: &Pubkey,
+        write_version: u64,
+        is_startup: bool,
+    ) {
+        self.plugin
+            .update_account(
+                ReplicaAccountInfoVersions::V0_0_3(&ReplicaAccountInfoV3 {
+                    pubkey: pubkey.as_ref(),
+                    lamports: 1,
+                    owner: owner.as_ref(),
+                    executable: false,
+                    rent_epoch: 0,
+                    data: &[1, 2, 3],
+                    write_version,
+                    txn: None,
+                }),
+                10,
+                is_startup,
+            )
+            .unwrap();
+    }
+
+    /// Notifies a write to `pubkey` by the transaction, ahead of the transaction itself.
+    fn update_account_in_tx(&self, pubkey: &Pubkey, tx: &SanitizedTransaction, write_version: u64) {
+        self.plugin
+            .update_account(
+                ReplicaAccountInfoVersions::V0_0_3(&ReplicaAccountInfoV3 {
+                    pubkey: pubkey.as_ref(),
+                    lamports: 1,
+                    owner: system_program::id().as_ref(),
+                    executable: false,
+                    rent_epoch: 0,
+                    data: &[],
+                    write_version,
+                    txn: Some(tx),
+                }),
+                10,
+                false,
+            )
+            .unwrap();
+    }
+
+    fn notify_transaction(&self, tx: &SanitizedTransaction, index: usize) {
+        self.plugin
+            .notify_transaction(
+                ReplicaTransactionInfoVersions::V0_0_2(&ReplicaTransactionInfoV2 {
+                    signature: tx.signature(),
+                    is_vote: false,
+                    transaction: tx,
+                    transaction_status_meta: &TransactionStatusMeta::default(),
+                    index,
+                }),
+                10,
+            )
+            .unwrap();
+    }
+
+    fn unload(&mut self) {
+        self.is_unloaded = true;
+        self.plugin.on_unload();
+    }
+
+    fn scrape_metrics(&self) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.metrics_port)).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    /// Sums the counter over subscriptions of the given type.
+    fn subscription_counter(&self, name: &str, subscription_type: &str) -> u64 {
+        self.scrape_metrics()
+            .lines()
+            .filter_map(|line| line.strip_prefix(name)?.strip_prefix('{'))
+            .filter(|line| line.contains(&format!("subscription_type=\"{subscription_type}\"")))
+            .filter_map(|line| line.split(' ').last()?.parse::<u64>().ok())
+            .sum()
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        if !self.is_unloaded {
+            self.plugin.on_unload();
+        }
+        let _ = fs::remove_file(&self.config_path);
+    }
+}
+
+fn account_update(
+    msg: jito_geyser_protos::solana::geyser::MaybeAccountUpdate,
+) -> Option<AccountUpdate> {
+    match msg.msg {
+        Some(maybe_account_update::Msg::AccountUpdate(update)) => update.account_update,
+        _ => None,
+    }
+}
+
+#[test]
+fn test_streams_slots_transactions_blocks_and_entries() {
+    let harness = Harness::load(json!({}));
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+    let mut transactions = harness.subscribe(|mut c| async move {
+        c.subscribe_transaction_updates_v2(SubscribeTransactionUpdatesRequest {})
+            .await
+    });
+    let mut blocks = harness.subscribe(|mut c| async move {
+        c.subscribe_block_updates_v2(SubscribeBlockUpdatesRequest {})
+            .await
+    });
+    let mut entries = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_entry_updates_v2(SubscribeSlotEntryUpdateRequest {})
+            .await
+    });
+
+    harness
+        .plugin
+        .update_slot_status(10, Some(9), &SlotStatus::Processed)
+        .unwrap();
+    let slot_update = harness.next_update(&mut slots, |msg| match msg.msg {
+        Some(maybe_slot_update::Msg::SlotUpdate(update)) => update.slot_update,
+        _ => None,
+    });
+    assert_eq!(
+        (
+            slot_update.slot,
+            slot_update.parent_slot,
+            slot_update.status
+        ),
+        (10, Some(9), SlotUpdateStatus::Processed as i32)
+    );
+
+    let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+        &Keypair::new(),
+        &Pubkey::new_unique(),
+        1,
+        Hash::default(),
+    ));
+    harness
+        .plugin
+        .notify_transaction(
+            ReplicaTransactionInfoVersions::V0_0_2(&ReplicaTransactionInfoV2 {
+                signature: tx.signature(),
+                is_vote: false,
+                transaction: &tx,
+                transaction_status_meta: &TransactionStatusMeta::default(),
+                index: 3,
+            }),
+            10,
+        )
+        .unwrap();
+    let transaction = harness.next_update(&mut transactions, |msg| match msg.msg {
+        Some(maybe_transaction_update::Msg::TransactionUpdate(update)) => update.transaction,
+        _ => None,
+    });
+    assert_eq!(
+        (transaction.slot, transaction.signature, transaction.tx_idx),
+        (10, tx.signature().to_string(), 3)
+    );
+
+    let blockhash = Hash::new_unique().to_string();
+    harness
+        .plugin
+        .notify_block_metadata(ReplicaBlockInfoVersions::V0_0_3(&ReplicaBlockInfoV3 {
+            parent_slot: 9,
+            parent_blockhash: &Hash::default().to_string(),
+            slot: 10,
+            blockhash: &blockhash,
+            rewards: &[],
+            block_time: Some(1_700_000_000),
+            block_height: Some(8),
+            executed_transaction_count: 4,
+            entry_count: 2,
+        }))
+        .unwrap();
+    let block = harness.next_update(&mut blocks, |msg| match msg.msg {
+        Some(maybe_block_update::Msg::BlockUpdate(update)) => update.block_update,
+        _ => None,
+    });
+    assert_eq!(
+        (
+            block.slot,
+            block.blockhash,
+            block.executed_transaction_count,
+            block.entry_count
+        ),
+        (10, blockhash, Some(4), Some(2))
+    );
+
+    let hash = Hash::new_unique();
+    harness
+        .plugin
+        .notify_entry(ReplicaEntryInfoVersions::V0_0_2(&ReplicaEntryInfoV2 {
+            slot: 10,
+            index: 1,
+            num_hashes: 12,
+            hash: hash.as_ref(),
+            executed_transaction_count: 2,
+            starting_transaction_index: 3,
+        }))
+        .unwrap();
+    let entry = harness.next_update(&mut entries, |msg| match msg.msg {
+        Some(maybe_slot_entry_update::Msg::EntryUpdate(update)) => update.entry_update,
+        _ => None,
+    });
+    assert_eq!(
+        (
+            entry.slot,
+            entry.index,
+            entry.num_hashes,
+            entry.hash,
+            entry.starting_transaction_index
+        ),
+        (10, 1, 12, hash.to_bytes().to_vec(), Some(3))
+    );
+}
+
+#[test]
+fn test_account_and_program_filters() {
+    let harness = Harness::load(json!({}));
+    let (account, program, other) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+    let mut programs = harness.subscribe(|mut c| async move {
+        c.subscribe_program_updates_v2(SubscribeProgramsUpdatesRequest {
+            programs: vec![program.to_bytes().to_vec()],
+            ..SubscribeProgramsUpdatesRequest::default()
+        })
+        .await
+    });
+
+    harness.update_account(&other, &other, 1);
+    harness.update_account(&account, &other, 2);
+    harness.update_account(&other, &program, 3);
+    harness.update_account(&account, &program, 4);
+
+    // updates are streamed in order, so anything filtered out would've shown up in between
+    let [first, update] = [(); 2].map(|_| harness.next_update(&mut accounts, account_update));
+    assert_eq!((first.seq, update.seq), (2, 4));
+    let seqs = [(); 2].map(|_| harness.next_update(&mut programs, account_update).seq);
+    assert_eq!(seqs, [3, 4]);
+
+    assert_eq!(update.pubkey, account.to_bytes());
+    assert_eq!(update.owner, program.to_bytes());
+    assert_eq!(update.data, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_stamps_update_timing() {
+    let harness = Harness::load(json!({}));
+    let account = Pubkey::new_unique();
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+
+    harness.update_account(&account, &account, 1);
+    harness
+        .plugin
+        .update_slot_status(10, None, &SlotStatus::Processed)
+        .unwrap();
+
+    let account_timing = harness.next_update(&mut accounts, |msg| match msg.msg {
+        Some(maybe_account_update::Msg::AccountUpdate(update)) => update.timing,
+        _ => None,
+    });
+    let slot_timing = harness.next_update(&mut slots, |msg| match msg.msg {
+        Some(maybe_slot_update::Msg::SlotUpdate(update)) => update.timing,
+        _ => None,
+    });
+    for timing in [account_timing, slot_timing] {
+        assert!(
+            timing.plugin_receive_us != 0
+                && timing.enqueue_us != 0
+                && timing.dispatch_us != 0
+                && timing.send_us != 0,
+            "{timing:?}"
+        );
+    }
+}
+
+#[test]
+fn test_serves_health_and_reflection() {
+    let harness = Harness::load(json!({}));
+
+    let mut health = HealthClient::new(harness.channel.clone());
+    let mut statuses = harness
+        .runtime
+        .block_on(health.watch(HealthCheckRequest {
+            service: SERVICE_NAME.to_string(),
+        }))
+        .unwrap()
+        .into_inner();
+    // unknown until the first liveness check
+    harness.next_update(&mut statuses, |response| {
+        (response.status == ServingStatus::Serving as i32).then_some(())
+    });
+
+    let mut reflection = ServerReflectionClient::new(harness.channel.clone());
+    let mut responses = harness
+        .runtime
+        .block_on(
+            reflection.server_reflection_info(tokio_stream::once(ServerReflectionRequest {
+                host: String::new(),
+                message_request: Some(MessageRequest::ListServices(String::new())),
+            })),
+        )
+        .unwrap()
+        .into_inner();
+    let services =
+        harness.next_update(&mut responses, |response| match response.message_response {
+            Some(MessageResponse::ListServicesResponse(response)) => Some(response.service),
+            _ => None,
+        });
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].name, SERVICE_NAME);
+}
+
+#[test]
+fn test_unload_streams_unavailable_to_subscribers() {
+    let mut harness = Harness::load(json!({}));
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+
+    harness.unload();
+    let status = harness.next_status(&mut slots);
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "validator is shutting down");
+}
+
+#[test]
+fn test_unload_streams_unavailable_to_lagging_subscribers() {
+    let mut harness = Harness::load(json!({
+        "slot_update_buffer_size": 100_000,
+        "geyser_service_config": {
+            "heartbeat_interval_ms": 10,
+            "subscriber_buffer_size": 1,
+            "shutdown_drain_timeout_ms": 5_000
+        }
+    }));
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+    let account = Pubkey::new_unique();
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            backpressure_policy: BackpressurePolicy::DropOldest as i32,
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+    // fills the subscribers' buffers
+    for slot in 1..=10_000 {
+        harness
+            .plugin
+            .update_slot_status(slot, None, &SlotStatus::Processed)
+            .unwrap();
+    }
+    // writes large enough that the transport stops taking them, leaving the latest one queued
+    let data = vec![0; 100_000];
+    for write_version in 1..=500 {
+        harness
+            .plugin
+            .update_account(
+                ReplicaAccountInfoVersions::V0_0_3(&ReplicaAccountInfoV3 {
+                    pubkey: account.as_ref(),
+                    lamports: 1,
+                    owner: account.as_ref(),
+                    executable: false,
+                    rent_epoch: 0,
+                    data: &data,
+                    write_version,
+                    txn: None,
+                }),
+                10,
+                false,
+            )
+            .unwrap();
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    let start = std::time::Instant::now();
+    while harness.subscription_counter("geyser_subscription_sent_total", "account")
+        + harness.subscription_counter("geyser_subscription_dropped_total", "account")
+        < 500
+    {
+        assert!(start.elapsed() < TIMEOUT, "account updates not dispatched");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // read once the subscriptions have been closed, while the plugin waits for them to drain
+    let status = harness.runtime.spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        loop {
+            match slots.message().await {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("stream ended without a status"),
+                Err(status) => return status,
+            }
+        }
+    });
+    let accounts = harness.runtime.spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let mut last_seq = None;
+        loop {
+            match accounts.message().await {
+                Ok(Some(msg)) => last_seq = account_update(msg).map(|u| u.seq).or(last_seq),
+                Ok(None) => panic!("stream ended without a status"),
+                Err(status) => return (last_seq, status),
+            }
+        }
+    });
+    harness.unload();
+    let status = harness.runtime.block_on(status).unwrap();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "validator is shutting down");
+    // the buffered update is streamed ahead of the status
+    let (last_seq, status) = harness.runtime.block_on(accounts).unwrap();
+    assert_eq!(last_seq, Some(500));
+    assert_eq!(status.code(), Code::Unavailable);
+}
+
+#[test]
+fn test_lists_and_kills_subscriptions() {
+    let harness = Harness::load(json!({
+        "geyser_service_config": {
+            "heartbeat_interval_ms": 10,
+            "subscriber_buffer_size": 1000,
+            "admin_access_token": "admin-token"
+        }
+    }));
+    let account = Pubkey::new_unique();
+    let _accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+
+    let mut unauthenticated_admin = GeyserAdminClient::new(harness.channel.clone());
+    let status = harness
+        .runtime
+        .block_on(unauthenticated_admin.list_subscriptions(ListSubscriptionsRequest {}))
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let mut admin = GeyserAdminClient::with_interceptor(
+        harness.channel.clone(),
+        |mut request: tonic::Request<()>| {
+            request
+                .metadata_mut()
+                .insert(ACCESS_TOKEN_HEADER, "admin-token".parse().unwrap());
+            Ok(request)
+        },
+    );
+    let mut subscriptions = harness
+        .runtime
+        .block_on(admin.list_subscriptions(ListSubscriptionsRequest {}))
+        .unwrap()
+        .into_inner()
+        .subscriptions;
+    subscriptions.sort_by(|a, b| a.subscription_type.cmp(&b.subscription_type));
+    assert_eq!(subscriptions.len(), 2);
+    assert_eq!(subscriptions[0].subscription_type, "account");
+    assert_eq!(subscriptions[0].filter_summary, "accounts: 1");
+    assert!(subscriptions[0].peer_address.starts_with("127.0.0.1:"));
+    assert!(subscriptions[0].connected_at.is_some());
+    assert_eq!(subscriptions[1].subscription_type, "slot");
+
+    let kill_slots = KillSubscriptionRequest {
+        uuid: subscriptions[1].uuid.clone(),
+    };
+    harness
+        .runtime
+        .block_on(admin.kill_subscription(kill_slots.clone()))
+        .unwrap();
+    assert_eq!(harness.next_status(&mut slots).code(), Code::Cancelled);
+    let status = harness
+        .runtime
+        .block_on(admin.kill_subscription(kill_slots))
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[test]
+fn test_skips_startup_updates() {
+    let harness = Harness::load(json!({"skip_startup_stream": true}));
+    let account = Pubkey::new_unique();
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+
+    harness.update_account(&account, &account, 1);
+    harness.plugin.notify_end_of_startup().unwrap();
+    harness.update_account(&account, &account, 2);
+
+    assert_eq!(harness.next_update(&mut accounts, account_update).seq, 2);
+}
+
+#[test]
+fn test_links_account_writes_to_transactions() {
+    let harness = Harness::load(json!({
+        "geyser_service_config": {
+            "heartbeat_interval_ms": 10,
+            "subscriber_buffer_size": 1000,
+            "transaction_linkage_enabled": true
+        }
+    }));
+    let payer = Keypair::new();
+    let account = payer.pubkey();
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+
+    let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+        &payer,
+        &Pubkey::new_unique(),
+        1,
+        Hash::default(),
+    ));
+    harness.update_account_in_tx(&account, &tx, 1);
+    harness.notify_transaction(&tx, 3);
+
+    let update = harness.next_update(&mut accounts, account_update);
+    assert_eq!(update.tx_signature, Some(tx.signature().to_string()));
+    let tx_context = update.tx_context.unwrap();
+    assert_eq!(tx_context.tx_idx, 3);
+    assert_eq!(
+        tx_context.program_ids,
+        vec![system_program::id().to_bytes().to_vec()]
+    );
+    assert!(tx_context.is_success);
+}
+
+#[test]
+fn test_replay_leaves_writes_awaiting_their_transaction_to_live_updates() {
+    let journal_dir =
+        std::env::temp_dir().join(format!("geyser-plugin-test-{}", uuid::Uuid::new_v4()));
+    let harness = Harness::load(json!({
+        "journal_config": {"dir": journal_dir.to_str().unwrap()},
+        "geyser_service_config": {
+            "heartbeat_interval_ms": 10,
+            "subscriber_buffer_size": 1000,
+            "transaction_linkage_enabled": true
+        }
+    }));
+    let payer = Keypair::new();
+    let (linked, unlinked) = (payer.pubkey(), Pubkey::new_unique());
+    let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+        &payer,
+        &Pubkey::new_unique(),
+        1,
+        Hash::default(),
+    ));
+    let mut live = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![unlinked.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+    // the write to `linked` is held for its transaction while the later one to `unlinked` isn't
+    harness.update_account_in_tx(&linked, &tx, 1);
+    harness.update_account(&unlinked, &unlinked, 2);
+    assert_eq!(harness.next_update(&mut live, account_update).seq, 2);
+
+    let mut accounts = harness
+        .runtime
+        .block_on(harness.client.clone().subscribe_account_updates_v2(
+            SubscribeAccountUpdatesRequest {
+                accounts: vec![linked.to_bytes().to_vec(), unlinked.to_bytes().to_vec()],
+                from_slot: Some(10),
+                ..SubscribeAccountUpdatesRequest::default()
+            },
+        ))
+        .unwrap()
+        .into_inner();
+    // replayed once the subscription has been registered
+    assert_eq!(harness.next_update(&mut accounts, account_update).seq, 2);
+
+    harness.notify_transaction(&tx, 3);
+    let update = harness.next_update(&mut accounts, account_update);
+    assert_eq!(update.seq, 1);
+    assert_eq!(update.tx_context.unwrap().tx_idx, 3);
+
+    drop(harness);
+    let _ = fs::remove_dir_all(journal_dir);
+}
+
+#[test]
+fn test_buffers_and_filters_startup_updates() {
+    let (owner, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let harness = Harness::load(json!({
+        "account_update_buffer_size": 1,
+        "startup_update_buffer_size": 1,
+        "startup_account_owners": [owner.to_string()],
+    }));
+    let mut programs = harness.subscribe(|mut c| async move {
+        c.subscribe_program_updates_v2(SubscribeProgramsUpdatesRequest {
+            programs: vec![owner.to_bytes().to_vec(), other.to_bytes().to_vec()],
+            ..SubscribeProgramsUpdatesRequest::default()
+        })
+        .await
+    });
+
+    // startup updates block rather than being dropped once the buffer is full
+    for write_version in 1..=500 {
+        harness.notify_account(&Pubkey::new_unique(), &owner, write_version, true);
+        harness.notify_account(&Pubkey::new_unique(), &other, write_version + 500, true);
+    }
+    harness.plugin.notify_end_of_startup().unwrap();
+    harness.update_account(&Pubkey::new_unique(), &other, 1001);
+
+    let mut seqs = vec![];
+    while let Some(seq) = harness.next_update(&mut programs, |msg| match msg.msg {
+        Some(maybe_account_update::Msg::AccountUpdate(update)) => {
+            Some(Some(update.account_update.unwrap().seq))
+        }
+        Some(maybe_account_update::Msg::StartupComplete(_)) => Some(None),
+        _ => None,
+    }) {
+        seqs.push(seq);
+    }
+    assert_eq!(seqs, (1..=500).collect::<Vec<_>>());
+    assert_eq!(harness.next_update(&mut programs, account_update).seq, 1001);
+}
+
+#[test]
+fn test_full_channel_drops_updates() {
+    let harness = Harness::load(json!({"account_update_buffer_size": 1}));
+    let (account, later) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![later.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+
+    // the plugin must never block or fail the validator, it drops updates instead
+    for write_version in 1..=10_000 {
+        harness.update_account(&account, &account, write_version);
+    }
+    let metrics = harness.scrape_metrics();
+    let drops: u64 = metrics
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("geyser_channel_dropped_total{channel=\"account_update\"} ")
+        })
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(drops > 0);
+
+    // and keeps streaming once the channel drains
+    std::thread::sleep(Duration::from_millis(100));
+    harness.update_account(&later, &later, 10_001);
+    assert_eq!(
+        harness.next_update(&mut accounts, account_update).seq,
+        10_001
+    );
+}
+
+#[test]
+fn test_legacy_streams_carry_only_updates() {
+    let harness = Harness::load(json!({}));
+    let account = Pubkey::new_unique();
+    let mut legacy_accounts = harness
+        .runtime
+        .block_on(harness.client.clone().subscribe_account_updates(
+            SubscribeAccountUpdatesRequest {
+                accounts: vec![account.to_bytes().to_vec()],
+                ..SubscribeAccountUpdatesRequest::default()
+            },
+        ))
+        .unwrap()
+        .into_inner();
+    let mut legacy_slots = harness
+        .runtime
+        .block_on(
+            harness
+                .client
+                .clone()
+                .subscribe_slot_updates(SubscribeSlotUpdateRequest {}),
+        )
+        .unwrap()
+        .into_inner();
+    // subscriptions are registered in order, so the legacy ones are once these are
+    let mut accounts = harness.subscribe(|mut c| async move {
+        c.subscribe_account_updates_v2(SubscribeAccountUpdatesRequest {
+            accounts: vec![account.to_bytes().to_vec()],
+            ..SubscribeAccountUpdatesRequest::default()
+        })
+        .await
+    });
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+
+    // heartbeats would've been streamed in the meantime
+    std::thread::sleep(Duration::from_millis(50));
+    harness.update_account(&account, &account, 1);
+    let update = harness.next(&mut legacy_accounts);
+    assert_eq!(update.account_update.unwrap().seq, 1);
+    assert_ne!(update.timing.unwrap().send_us, 0);
+    assert_eq!(harness.next_update(&mut accounts, account_update).seq, 1);
+
+    harness
+        .plugin
+        .update_slot_status(10, Some(9), &SlotStatus::Processed)
+        .unwrap();
+    let update = harness.next(&mut legacy_slots);
+    assert_eq!(update.slot_update.unwrap().slot, 10);
+    assert_ne!(update.timing.unwrap().send_us, 0);
+    let slot_update = harness.next_update(&mut slots, |msg| match msg.msg {
+        Some(maybe_slot_update::Msg::SlotUpdate(update)) => update.slot_update,
+        _ => None,
+    });
+    assert_eq!(slot_update.slot, 10);
+}
+
+#[test]
+fn test_full_slot_stream_drops_newest_updates() {
+    let harness = Harness::load(json!({
+        "slot_update_buffer_size": 100_000,
+        "geyser_service_config": {
+            "heartbeat_interval_ms": 200,
+            "subscriber_buffer_size": 1
+        }
+    }));
+    let mut slots = harness.subscribe(|mut c| async move {
+        c.subscribe_slot_updates_v2(SubscribeSlotUpdateRequest {})
+            .await
+    });
+
+    for slot in 1..=10_000 {
+        harness
+            .plugin
+            .update_slot_status(slot, None, &SlotStatus::Processed)
+            .unwrap();
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    let metrics = harness.scrape_metrics();
+    let drops: u64 = metrics
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("geyser_subscription_dropped_total{")
+                .filter(|line| line.contains("subscription_type=\"slot\""))
+                .and_then(|line| line.split(' ').last())
+        })
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(drops > 0);
+
+    // without a lag report or closing the stream, which streams the update once drained
+    harness.runtime.block_on(async {
+        while let Ok(msg) = tokio::time::timeout(Duration::from_millis(50), slots.message()).await {
+            assert!(msg.unwrap().is_some());
+        }
+    });
+    harness
+        .plugin
+        .update_slot_status(10_001, None, &SlotStatus::Processed)
+        .unwrap();
+    let slot = harness.next_update(&mut slots, |msg| match msg.msg {
+        Some(maybe_slot_update::Msg::SlotUpdate(update)) => update
+            .slot_update
+            .filter(|update| update.slot == 10_001)
+            .map(|update| update.slot),
+        _ => None,
+    });
+    assert_eq!(slot, 10_001);
+}
@@ -0,0 +1,337 @@
+//! Account updates encoded once and streamed to every matching subscriber as pre-encoded frames.
+//!
+//! tonic encodes each streamed message independently, so fanning an update out to N subscribers
+//! would otherwise serialize it N times. Instead, the server's account and program update streams
+//! are generated with [EncodedMaybeAccountUpdate] in place of `MaybeAccountUpdate`; it's encoded
+//! identically on the wire, so clients are none the wiser. The legacy streams, which carry bare
+//! `TimestampedAccountUpdate`s, are likewise generated with [EncodedTimestampedAccountUpdate].
+//!
+//! The send time of an account update differs per subscriber, so it's written out after the shared
+//! bytes as a second `MaybeAccountUpdate` holding just the timing; decoders merge the two.
+
+use std::{ops::Deref, sync::OnceLock};
+
+use bytes::{Buf, BufMut, Bytes};
+use jito_geyser_protos::solana::geyser::{
+    maybe_account_update, MaybeAccountUpdate, TimestampedAccountUpdate, UpdateTiming,
+};
+use prost::{
+    encoding::{decode_key, decode_varint, skip_field, DecodeContext, WireType},
+    DecodeError, Message,
+};
+
+/// An account update shared by every subscription it's dispatched to. The frame streamed to them
+/// is encoded upon first use and shared from then on.
+#[derive(Debug)]
+pub struct SharedAccountUpdate {
+    update: TimestampedAccountUpdate,
+    frame: OnceLock<EncodedMaybeAccountUpdate>,
+}
+
+impl SharedAccountUpdate {
+    pub fn new(update: TimestampedAccountUpdate) -> Self {
+        Self {
+            update,
+            frame: OnceLock::new(),
+        }
+    }
+
+    /// Returns the update encoded as a `MaybeAccountUpdate`, encoding it if this is the first call.
+    pub fn frame(&self) -> EncodedMaybeAccountUpdate {
+        self.frame
+            .get_or_init(|| {
+                EncodedMaybeAccountUpdate::from(MaybeAccountUpdate {
+                    msg: Some(maybe_account_update::Msg::AccountUpdate(
+                        self.update.clone(),
+                    )),
+                })
+            })
+            .clone()
+    }
+}
+
+impl Deref for SharedAccountUpdate {
+    type Target = TimestampedAccountUpdate;
+
+    fn deref(&self) -> &Self::Target {
+        &self.update
+    }
+}
+
+impl From<TimestampedAccountUpdate> for SharedAccountUpdate {
+    fn from(update: TimestampedAccountUpdate) -> Self {
+        Self::new(update)
+    }
+}
+
+/// An encoded `MaybeAccountUpdate`, written out verbatim when encoded. Cloning is cheap as the
+/// underlying buffer is reference counted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodedMaybeAccountUpdate {
+    bytes: Bytes,
+
+    /// Whether it holds an account update, which alone can be stamped with a send time.
+    is_account_update: bool,
+    send_us: Option<u32>,
+}
+
+impl EncodedMaybeAccountUpdate {
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    pub fn set_send_time(&mut self, send_us: u32) {
+        if self.is_account_update {
+            self.send_us = Some(send_us);
+        }
+    }
+
+    /// Returns the account update held, sharing its bytes, or `None` if it's a control message.
+    pub fn into_timestamped(self) -> Option<EncodedTimestampedAccountUpdate> {
+        if !self.is_account_update {
+            return None;
+        }
+        // the bytes are just the `account_update` field: its key, length and then the update
+        let mut buf = &self.bytes[..];
+        decode_key(&mut buf).ok()?;
+        let len = decode_varint(&mut buf).ok()? as usize;
+        let start = self.bytes.len() - buf.len();
+        Some(EncodedTimestampedAccountUpdate {
+            bytes: self.bytes.slice(start..start + len),
+            send_us: self.send_us,
+        })
+    }
+
+    fn send_time_suffix(&self) -> Option<MaybeAccountUpdate> {
+        let send_us = self.send_us?;
+        Some(MaybeAccountUpdate {
+            msg: Some(maybe_account_update::Msg::AccountUpdate(send_time_only(
+                send_us,
+            ))),
+        })
+    }
+}
+
+/// An encoded `TimestampedAccountUpdate`, streamed to legacy account and program subscriptions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodedTimestampedAccountUpdate {
+    bytes: Bytes,
+    send_us: Option<u32>,
+}
+
+impl EncodedTimestampedAccountUpdate {
+    pub fn set_send_time(&mut self, send_us: u32) {
+        self.send_us = Some(send_us);
+    }
+}
+
+impl Message for EncodedTimestampedAccountUpdate {
+    fn encode_raw(&self, buf: &mut impl BufMut) {
+        buf.put_slice(&self.bytes);
+        if let Some(send_us) = self.send_us {
+            send_time_only(send_us).encode_raw(buf);
+        }
+    }
+
+    /// The server only ever encodes these, so decoding discards the fields.
+    fn merge_field(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut impl Buf,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError> {
+        skip_field(wire_type, tag, buf, ctx)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.bytes.len()
+            + self
+                .send_us
+                .map_or(0, |send_us| send_time_only(send_us).encoded_len())
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// An update holding nothing but the send time, which decoders merge into the preceding one.
+fn send_time_only(send_us: u32) -> TimestampedAccountUpdate {
+    TimestampedAccountUpdate {
+        timing: Some(UpdateTiming {
+            send_us,
+            ..UpdateTiming::default()
+        }),
+        ..TimestampedAccountUpdate::default()
+    }
+}
+
+impl From<MaybeAccountUpdate> for EncodedMaybeAccountUpdate {
+    fn from(msg: MaybeAccountUpdate) -> Self {
+        Self {
+            bytes: msg.encode_to_vec().into(),
+            is_account_update: matches!(msg.msg, Some(maybe_account_update::Msg::AccountUpdate(_))),
+            send_us: None,
+        }
+    }
+}
+
+impl Message for EncodedMaybeAccountUpdate {
+    fn encode_raw(&self, buf: &mut impl BufMut) {
+        buf.put_slice(&self.bytes);
+        if let Some(suffix) = self.send_time_suffix() {
+            suffix.encode_raw(buf);
+        }
+    }
+
+    /// The server only ever encodes these, so decoding discards the fields.
+    fn merge_field(
+        &mut self,
+        tag: u32,
+        wire_type: WireType,
+        buf: &mut impl Buf,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError> {
+        skip_field(wire_type, tag, buf, ctx)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.bytes.len()
+            + self
+                .send_time_suffix()
+                .map_or(0, |suffix| suffix.encoded_len())
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jito_geyser_protos::solana::geyser::{AccountUpdate, LagReport};
+
+    use super::*;
+
+    #[test]
+    fn test_wire_compatible_with_maybe_account_update() {
+        let update = TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                pubkey: vec![1; 32],
+                data: vec![2; 100],
+                seq: 3,
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        };
+        let shared = SharedAccountUpdate::new(update.clone());
+        let frame = shared.frame();
+        assert_eq!(frame.bytes().as_ptr(), shared.frame().bytes().as_ptr());
+
+        // as encoded by tonic, i.e. as the body of a length-delimited frame
+        let decoded = MaybeAccountUpdate::decode(frame.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(
+            decoded.msg,
+            Some(maybe_account_update::Msg::AccountUpdate(update))
+        );
+
+        let lag_report = MaybeAccountUpdate {
+            msg: Some(maybe_account_update::Msg::LagReport(LagReport {
+                dropped_updates: 1,
+                ..LagReport::default()
+            })),
+        };
+        let frame = EncodedMaybeAccountUpdate::from(lag_report.clone());
+        assert_eq!(frame.encoded_len(), lag_report.encoded_len());
+        assert_eq!(
+            MaybeAccountUpdate::decode(frame.encode_to_vec().as_slice()).unwrap(),
+            lag_report
+        );
+    }
+
+    #[test]
+    fn test_send_time_merged_into_update_timing() {
+        let update = TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                seq: 1,
+                ..AccountUpdate::default()
+            }),
+            timing: Some(UpdateTiming {
+                plugin_receive_us: 1,
+                enqueue_us: 2,
+                dispatch_us: 3,
+                send_us: 0,
+            }),
+        };
+        let mut frame = SharedAccountUpdate::new(update.clone()).frame();
+        frame.set_send_time(4);
+        let decoded = MaybeAccountUpdate::decode(frame.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(frame.encoded_len(), frame.encode_to_vec().len());
+        let Some(maybe_account_update::Msg::AccountUpdate(decoded)) = decoded.msg else {
+            panic!("expected an account update");
+        };
+        assert_eq!(decoded.account_update, update.account_update);
+        assert_eq!(
+            decoded.timing,
+            Some(UpdateTiming {
+                plugin_receive_us: 1,
+                enqueue_us: 2,
+                dispatch_us: 3,
+                send_us: 4,
+            })
+        );
+
+        // only account updates carry timing
+        let mut frame = EncodedMaybeAccountUpdate::from(MaybeAccountUpdate {
+            msg: Some(maybe_account_update::Msg::LagReport(LagReport::default())),
+        });
+        let unstamped = frame.clone();
+        frame.set_send_time(4);
+        assert_eq!(frame, unstamped);
+        assert_eq!(frame.into_timestamped(), None);
+    }
+
+    #[test]
+    fn test_timestamped_shares_the_account_update() {
+        let update = TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                pubkey: vec![1; 32],
+                data: vec![2; 200],
+                seq: 3,
+                ..AccountUpdate::default()
+            }),
+            timing: Some(UpdateTiming {
+                plugin_receive_us: 1,
+                ..UpdateTiming::default()
+            }),
+        };
+        let frame = SharedAccountUpdate::new(update.clone()).frame();
+        let mut timestamped = frame.clone().into_timestamped().unwrap();
+        assert!(frame
+            .bytes()
+            .as_ptr_range()
+            .contains(&timestamped.bytes.as_ptr()));
+        assert_eq!(
+            TimestampedAccountUpdate::decode(timestamped.encode_to_vec().as_slice()).unwrap(),
+            update
+        );
+
+        timestamped.set_send_time(4);
+        assert_eq!(timestamped.encoded_len(), timestamped.encode_to_vec().len());
+        let decoded =
+            TimestampedAccountUpdate::decode(timestamped.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded.account_update, update.account_update);
+        assert_eq!(
+            decoded.timing,
+            Some(UpdateTiming {
+                plugin_receive_us: 1,
+                send_us: 4,
+                ..UpdateTiming::default()
+            })
+        );
+    }
+}
@@ -1,38 +1,111 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt::{Debug, Display, Formatter},
+    pin::Pin,
     sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, RwLock,
     },
     thread::{Builder, JoinHandle},
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
-use crossbeam_channel::{tick, unbounded, Receiver, RecvError, Sender};
-use jito_geyser_protos::solana::geyser::{
-    geyser_server::Geyser, maybe_partial_account_update, EmptyRequest,
-    GetHeartbeatIntervalResponse, Heartbeat, MaybePartialAccountUpdate, PartialAccountUpdate,
-    SubscribeAccountUpdatesRequest, SubscribeBlockUpdatesRequest,
-    SubscribePartialAccountUpdatesRequest, SubscribeProgramsUpdatesRequest,
-    SubscribeSlotEntryUpdateRequest, SubscribeSlotUpdateRequest,
-    SubscribeTransactionUpdatesRequest, TimestampedAccountUpdate, TimestampedBlockUpdate,
-    TimestampedSlotEntryUpdate, TimestampedSlotUpdate, TimestampedTransactionUpdate,
+use crossbeam_channel::{never, tick, unbounded, Receiver, RecvError, Sender};
+use jito_geyser_protos::solana::{
+    geyser::{
+        maybe_account_update, maybe_block, maybe_block_update, maybe_partial_account_update,
+        maybe_slot_entry_update, maybe_slot_tip_update, maybe_slot_update,
+        maybe_transaction_update, AccountUpdate, BackpressurePolicy, Block, CachedAccount,
+        CoalesceOptions, CommitmentLevel, EmptyRequest, GetAccountRequest, GetAccountResponse,
+        GetHeartbeatIntervalResponse, GetMultipleAccountsRequest, GetMultipleAccountsResponse,
+        GetSlotStateResponse, Heartbeat, MaybeAccountUpdate, MaybeBlock, MaybeBlockUpdate,
+        MaybePartialAccountUpdate, MaybeSlotEntryUpdate, MaybeSlotTipUpdate, MaybeSlotUpdate,
+        MaybeTransactionUpdate, PartialAccountUpdate, SlotTipUpdate, SlotUpdateStatus,
+        StartupComplete, SubscribeAccountUpdatesRequest, SubscribeBlockUpdatesRequest,
+        SubscribeFullBlocksRequest, SubscribePartialAccountUpdatesRequest,
+        SubscribeProgramsUpdatesRequest, SubscribeSlotEntryUpdateRequest, SubscribeSlotTipsRequest,
+        SubscribeSlotUpdateRequest, SubscribeTransactionUpdatesRequest, SubscriptionInfo,
+        TimestampedAccountUpdate, TimestampedBlock, TimestampedBlockUpdate,
+        TimestampedSlotEntryUpdate, TimestampedSlotTipUpdate, TimestampedSlotUpdate,
+        TimestampedTransactionUpdate,
+    },
+    storage::entries::Entry,
 };
 use log::*;
 use once_cell::sync::OnceCell;
 use serde_derive::Deserialize;
 use thiserror::Error;
-use tokio::sync::mpsc::{channel, error::TrySendError as TokioTrySendError, Sender as TokioSender};
+use tokio::sync::{
+    mpsc::{channel, error::TrySendError as TokioTrySendError, Sender as TokioSender},
+    oneshot,
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tonic::{metadata::MetadataValue, Request, Response, Status};
 use uuid::Uuid;
 
-use crate::subscription_stream::{StreamClosedSender, SubscriptionStream};
+use crate::{
+    access_control::{client_identity, AccessGrant, AccessTokenConfig, SubscriptionPermit},
+    account_cache::AccountCache,
+    account_dispatcher::{AccountUpdateDispatcher, SubscriptionFilter},
+    admin::{self, AdminRequest, DescribeSubscription, GeyserAdminService, SubscriptionClient},
+    block_assembler::BlockAssembler,
+    health::EventLoopLiveness,
+    journal::{JournalError, JournalReader},
+    metrics::{Channel, EventLoopEvent, GeyserMetrics, SubscriptionMetrics},
+    pre_encoded::{
+        EncodedMaybeAccountUpdate, EncodedTimestampedAccountUpdate, SharedAccountUpdate,
+    },
+    service::geyser_server::Geyser,
+    slot_tracker::SlotTracker,
+    subscriber_channel::{subscriber_channel, SubscriberSender},
+    subscriber_queue::{
+        account_update_queue, AccountUpdateQueueReceiver, AccountUpdateQueueSender, QueueSendError,
+    },
+    subscription_stream::{StreamClosedSender, SubscriptionStream},
+    transaction_linker::TransactionLinker,
+    update_timing,
+};
 
 static VOTE_PROGRAM_ID: OnceCell<Vec<u8>> = OnceCell::new();
 
 pub const HIGHEST_WRITE_SLOT_HEADER: &str = "highest-write-slot";
 
+/// Max time to wait for the journal writer to flush updates a replaying subscription depends on.
+const JOURNAL_CATCH_UP_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DEFAULT_COALESCE_MAX_DELAY_MS: u64 = 1_000;
+
+const DEFAULT_DISPATCH_THREADS: usize = 4;
+
+const DEFAULT_TRANSACTION_LINKAGE_MAX_DELAY_MS: u64 = 2_000;
+
+/// Keeps idle connections from being dropped by NATs and load balancers along the way.
+const DEFAULT_HTTP2_KEEPALIVE_INTERVAL_MS: u64 = 10_000;
+
+const DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_MS: u64 = 20_000;
+
+const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS: u64 = 5_000;
+
+/// Cadence at which writes held for longer than the transaction linkage max delay are released.
+const TRANSACTION_LINKAGE_EXPIRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Max number of accounts returned by `GetMultipleAccounts`.
+const MAX_GET_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Subscription types as labelled in metrics.
+const ACCOUNT_SUBSCRIPTION: &str = "account";
+const PROGRAM_SUBSCRIPTION: &str = "program";
+const PARTIAL_ACCOUNT_SUBSCRIPTION: &str = "partial_account";
+const SLOT_SUBSCRIPTION: &str = "slot";
+const SLOT_ENTRY_SUBSCRIPTION: &str = "slot_entry";
+const TRANSACTION_SUBSCRIPTION: &str = "transaction";
+const BLOCK_SUBSCRIPTION: &str = "block";
+const FULL_BLOCK_SUBSCRIPTION: &str = "full_block";
+const SLOT_TIP_SUBSCRIPTION: &str = "slot_tip";
+
+/// Cadence at which queue depths and subscription counts are sampled into metrics.
+const METRICS_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Clone)]
 struct SubscriptionClosedSender {
     inner: Sender<SubscriptionClosedEvent>,
@@ -46,12 +119,81 @@ impl StreamClosedSender<SubscriptionClosedEvent> for SubscriptionClosedSender {
     }
 }
 
-type AccountUpdateSender = TokioSender<Result<TimestampedAccountUpdate, Status>>;
-type PartialAccountUpdateSender = TokioSender<Result<MaybePartialAccountUpdate, Status>>;
-type SlotUpdateSender = TokioSender<Result<TimestampedSlotUpdate, Status>>;
-type SlotEntryUpdateSender = TokioSender<Result<TimestampedSlotEntryUpdate, Status>>;
-type TransactionUpdateSender = TokioSender<Result<TimestampedTransactionUpdate, Status>>;
-type BlockUpdateSender = TokioSender<Result<TimestampedBlockUpdate, Status>>;
+/// Streams bare updates, as subscriptions did before heartbeats were streamed alongside them.
+type LegacyStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+type AccountUpdateStream =
+    Pin<Box<dyn Stream<Item = Result<EncodedMaybeAccountUpdate, Status>> + Send>>;
+type LegacyAccountUpdateStream = LegacyStream<EncodedTimestampedAccountUpdate>;
+type SubscriberStream<T> = ReceiverStream<Result<T, Status>>;
+type PartialAccountUpdateSender = SubscriberSender<MaybePartialAccountUpdate>;
+type SlotUpdateSender = SubscriberSender<MaybeSlotUpdate>;
+type SlotEntryUpdateSender = SubscriberSender<MaybeSlotEntryUpdate>;
+type TransactionUpdateSender = SubscriberSender<MaybeTransactionUpdate>;
+type BlockUpdateSender = SubscriberSender<MaybeBlockUpdate>;
+type FullBlockSender = SubscriberSender<MaybeBlock>;
+type SlotTipSender = SubscriberSender<MaybeSlotTipUpdate>;
+
+/// An entry as notified by the validator. Its hash is only streamed as part of full blocks.
+#[derive(Clone)]
+pub struct SlotEntryNotification {
+    pub entry_update: TimestampedSlotEntryUpdate,
+    pub entry: Entry,
+}
+
+/// Sent over the startup channel, which account updates are only sent on if the plugin buffers
+/// startup updates separately from live ones.
+#[allow(clippy::large_enum_variant)]
+pub enum StartupNotification {
+    Account(TimestampedAccountUpdate),
+    /// The validator has notified every account loaded at startup.
+    Complete,
+}
+
+/// Logs who is subscribing and enforces the access grant attached to the request, if access
+/// tokens are configured. The returned permit must be held for as long as the subscription is open.
+fn authorize_subscription<T>(
+    request: &Request<T>,
+    method: &str,
+    num_accounts: usize,
+) -> Result<Option<SubscriptionPermit>, Status> {
+    info!(
+        "{} subscribing with {method}",
+        client_identity(request)
+            .as_deref()
+            .unwrap_or("anonymous client")
+    );
+    request
+        .extensions()
+        .get::<Arc<AccessGrant>>()
+        .map(|grant| grant.authorize_subscription(method, num_accounts))
+        .transpose()
+}
+
+/// Queues the status a subscriber's stream ends with.
+fn send_status<T>(sender: &SubscriberSender<T>, status: Status) -> GeyserServiceResult<()> {
+    sender.send_status(status).map_err(|e| match e {
+        TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
+        TokioTrySendError::Closed(_) => GeyserServiceError::NotificationReceiverDisconnected,
+    })
+}
+
+/// Queues a heartbeat for a subscriber, dropping it if the subscriber's buffer is full since
+/// whatever is buffered lets the client know the stream is alive.
+fn try_send_heartbeat<T>(sender: &SubscriberSender<T>, hb: T) -> GeyserServiceResult<()> {
+    sender.try_send(Ok(hb)).map_err(|e| match e {
+        TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
+        TokioTrySendError::Closed(_) => GeyserServiceError::NotificationReceiverDisconnected,
+    })
+}
+
+/// Records the outcome of queueing an update for a subscriber.
+fn record_send<T>(metrics: &SubscriptionMetrics, result: &Result<(), TokioTrySendError<T>>) {
+    match result {
+        Ok(()) => metrics.record_sent(),
+        Err(TokioTrySendError::Full(_)) => metrics.record_dropped(),
+        Err(TokioTrySendError::Closed(_)) => {}
+    }
+}
 
 trait AccountUpdateStreamer<T> {
     fn stream_update(&self, update: &T) -> GeyserServiceResult<()>;
@@ -63,29 +205,73 @@ trait HeartbeatStreamer {
 
 trait ErrorStatusStreamer {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()>;
+
+    /// Streams the status once what's buffered for the subscriber has been sent.
+    fn stream_closing_status(&self, status: Status) -> GeyserServiceResult<()> {
+        // channels stream it in order
+        self.stream_error(status)
+    }
+}
+
+trait StartupCompleteStreamer {
+    fn send_startup_complete(&self) -> GeyserServiceResult<()>;
 }
 
+/// Account and program subscriptions as tracked by the event loop. Live updates are dispatched to
+/// them by the [AccountUpdateDispatcher].
 struct AccountUpdateSubscription {
-    notification_sender: AccountUpdateSender,
+    notification_sender: AccountUpdateQueueSender,
+    filter: SubscriptionFilter,
     accounts: HashSet<Vec<u8>>,
+    /// Shared with the dispatcher, which records what's sent and dropped.
+    metrics: Arc<SubscriptionMetrics>,
+    client: SubscriptionClient,
+}
+
+impl AccountUpdateSubscription {
+    /// Streams the cached value of accounts to a new subscriber, flagged as a snapshot.
+    fn stream_initial_state<'a>(
+        &self,
+        cached_updates: impl Iterator<Item = &'a Arc<SharedAccountUpdate>>,
+    ) -> GeyserServiceResult<()> {
+        let snapshot = cached_updates.map(|cached_update| {
+            let mut update = TimestampedAccountUpdate::clone(cached_update);
+            if let Some(account_update) = update.account_update.as_mut() {
+                account_update.is_snapshot = true;
+            }
+            Arc::new(update.into())
+        });
+        Ok(self.notification_sender.send_snapshot(snapshot)?)
+    }
+}
+
+impl HeartbeatStreamer for AccountUpdateSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        Ok(self.notification_sender.send_heartbeat()?)
+    }
+}
+
+impl StartupCompleteStreamer for AccountUpdateSubscription {
+    fn send_startup_complete(&self) -> GeyserServiceResult<()> {
+        Ok(self.notification_sender.mark_startup_complete()?)
+    }
 }
 
 impl ErrorStatusStreamer for AccountUpdateSubscription {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
-        self.notification_sender
-            .try_send(Err(status))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+        Ok(self.notification_sender.stream_error(status)?)
+    }
+
+    fn stream_closing_status(&self, status: Status) -> GeyserServiceResult<()> {
+        Ok(self.notification_sender.close(status)?)
     }
 }
 
 struct PartialAccountUpdateSubscription {
     subscription_tx: PartialAccountUpdateSender,
     skip_votes: bool,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
 }
 
 impl AccountUpdateStreamer<PartialAccountUpdate> for PartialAccountUpdateSubscription {
@@ -103,146 +289,425 @@ impl AccountUpdateStreamer<PartialAccountUpdate> for PartialAccountUpdateSubscri
                 update.clone(),
             )),
         };
-        self.subscription_tx
-            .try_send(Ok(update))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+        let result = self.subscription_tx.try_send(Ok(update));
+        record_send(&self.metrics, &result);
+        result.map_err(|e| match e {
+            TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
+            TokioTrySendError::Closed(_) => GeyserServiceError::NotificationReceiverDisconnected,
+        })
     }
 }
 
 impl HeartbeatStreamer for PartialAccountUpdateSubscription {
     fn send_heartbeat(&self) -> GeyserServiceResult<()> {
-        self.subscription_tx
-            .try_send(Ok(MaybePartialAccountUpdate {
+        try_send_heartbeat(
+            &self.subscription_tx,
+            MaybePartialAccountUpdate {
                 msg: Some(maybe_partial_account_update::Msg::Hb(Heartbeat {})),
-            }))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+            },
+        )
+    }
+}
+
+impl StartupCompleteStreamer for PartialAccountUpdateSubscription {
+    fn send_startup_complete(&self) -> GeyserServiceResult<()> {
+        let result = self.subscription_tx.try_send(Ok(MaybePartialAccountUpdate {
+            msg: Some(maybe_partial_account_update::Msg::StartupComplete(
+                StartupComplete {},
+            )),
+        }));
+        record_send(&self.metrics, &result);
+        result.map_err(|e| match e {
+            TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
+            TokioTrySendError::Closed(_) => GeyserServiceError::NotificationReceiverDisconnected,
+        })
     }
 }
 
 impl ErrorStatusStreamer for PartialAccountUpdateSubscription {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
-        self.subscription_tx
-            .try_send(Err(status))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+        send_status(&self.subscription_tx, status)
     }
 }
 
 struct SlotUpdateSubscription {
     subscription_tx: SlotUpdateSender,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
 }
 
 struct SlotEntryUpdateSubscription {
     subscription_tx: SlotEntryUpdateSender,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
+}
+
+impl HeartbeatStreamer for SlotUpdateSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        try_send_heartbeat(
+            &self.subscription_tx,
+            MaybeSlotUpdate {
+                msg: Some(maybe_slot_update::Msg::Hb(Heartbeat {})),
+            },
+        )
+    }
+}
+
+impl HeartbeatStreamer for SlotEntryUpdateSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        try_send_heartbeat(
+            &self.subscription_tx,
+            MaybeSlotEntryUpdate {
+                msg: Some(maybe_slot_entry_update::Msg::Hb(Heartbeat {})),
+            },
+        )
+    }
 }
 
 impl ErrorStatusStreamer for SlotUpdateSubscription {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
-        self.subscription_tx
-            .try_send(Err(status))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+        send_status(&self.subscription_tx, status)
     }
 }
 
 impl ErrorStatusStreamer for SlotEntryUpdateSubscription {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
-        self.subscription_tx
-            .try_send(Err(status))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+        send_status(&self.subscription_tx, status)
     }
 }
 
 struct BlockUpdateSubscription {
     notification_sender: BlockUpdateSender,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
+}
+
+impl HeartbeatStreamer for BlockUpdateSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        try_send_heartbeat(
+            &self.notification_sender,
+            MaybeBlockUpdate {
+                msg: Some(maybe_block_update::Msg::Hb(Heartbeat {})),
+            },
+        )
+    }
 }
 
 impl ErrorStatusStreamer for BlockUpdateSubscription {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
-        self.notification_sender
-            .try_send(Err(status))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
-            })
+        send_status(&self.notification_sender, status)
+    }
+}
+
+struct FullBlockSubscription {
+    notification_sender: FullBlockSender,
+    include_account_writes: bool,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
+}
+
+impl HeartbeatStreamer for FullBlockSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        try_send_heartbeat(
+            &self.notification_sender,
+            MaybeBlock {
+                msg: Some(maybe_block::Msg::Hb(Heartbeat {})),
+            },
+        )
+    }
+}
+
+impl ErrorStatusStreamer for FullBlockSubscription {
+    fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
+        send_status(&self.notification_sender, status)
+    }
+}
+
+struct SlotTipSubscription {
+    notification_sender: SlotTipSender,
+    /// Commitment levels streamed to the subscriber.
+    commitments: HashSet<i32>,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
+}
+
+impl HeartbeatStreamer for SlotTipSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        try_send_heartbeat(
+            &self.notification_sender,
+            MaybeSlotTipUpdate {
+                msg: Some(maybe_slot_tip_update::Msg::Hb(Heartbeat {})),
+            },
+        )
+    }
+}
+
+impl ErrorStatusStreamer for SlotTipSubscription {
+    fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
+        send_status(&self.notification_sender, status)
     }
 }
 
 struct TransactionUpdateSubscription {
     notification_sender: TransactionUpdateSender,
+    full_transaction_data: bool,
+    metrics: SubscriptionMetrics,
+    client: SubscriptionClient,
+}
+
+impl HeartbeatStreamer for TransactionUpdateSubscription {
+    fn send_heartbeat(&self) -> GeyserServiceResult<()> {
+        try_send_heartbeat(
+            &self.notification_sender,
+            MaybeTransactionUpdate {
+                msg: Some(maybe_transaction_update::Msg::Hb(Heartbeat {})),
+            },
+        )
+    }
 }
 
 impl ErrorStatusStreamer for TransactionUpdateSubscription {
     fn stream_error(&self, status: Status) -> GeyserServiceResult<()> {
-        self.notification_sender
-            .try_send(Err(status))
-            .map_err(|e| match e {
-                TokioTrySendError::Full(_) => GeyserServiceError::NotificationReceiverFull,
-                TokioTrySendError::Closed(_) => {
-                    GeyserServiceError::NotificationReceiverDisconnected
-                }
+        send_status(&self.notification_sender, status)
+    }
+}
+
+impl DescribeSubscription for AccountUpdateSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn filter_summary(&self) -> String {
+        match self.filter {
+            SubscriptionFilter::Pubkey => format!("accounts: {}", self.accounts.len()),
+            SubscriptionFilter::Owner => format!("programs: {}", self.accounts.len()),
+        }
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.notification_sender.queued_updates()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for PartialAccountUpdateSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn filter_summary(&self) -> String {
+        if self.skip_votes {
+            "skipping vote accounts".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.subscription_tx.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for SlotUpdateSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.subscription_tx.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for SlotEntryUpdateSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.subscription_tx.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for TransactionUpdateSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn filter_summary(&self) -> String {
+        if self.full_transaction_data {
+            String::new()
+        } else {
+            "without transaction data".to_string()
+        }
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.notification_sender.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for BlockUpdateSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.notification_sender.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for FullBlockSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn filter_summary(&self) -> String {
+        if self.include_account_writes {
+            "with account writes".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.notification_sender.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
+    }
+}
+
+impl DescribeSubscription for SlotTipSubscription {
+    fn client(&self) -> &SubscriptionClient {
+        &self.client
+    }
+
+    fn filter_summary(&self) -> String {
+        let mut commitments: Vec<i32> = self.commitments.iter().copied().collect();
+        commitments.sort_unstable();
+        commitments
+            .into_iter()
+            .filter_map(|c| match CommitmentLevel::try_from(c).ok()? {
+                CommitmentLevel::ProcessedCommitment => Some("processed"),
+                CommitmentLevel::ConfirmedCommitment => Some("confirmed"),
+                CommitmentLevel::FinalizedCommitment => Some("finalized"),
             })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn queued_updates(&self) -> usize {
+        self.notification_sender.queued_messages()
+    }
+
+    fn dropped_updates(&self) -> u64 {
+        self.metrics.dropped()
     }
 }
 
+enum ShutdownEvent {
+    /// Streams `Unavailable` to every subscriber for the given reason, closing their
+    /// subscriptions along with any added from then on.
+    CloseSubscriptions(String),
+    Exit,
+}
+
+/// What a subscription replaying from the journal replays, as of being added to the event loop.
+struct ReplayBound {
+    /// Highest seq dispatched before the subscription was added.
+    max_seq: u64,
+    /// Seqs up to `max_seq` held awaiting their transaction, which are streamed live once
+    /// released instead.
+    held_seqs: HashSet<u64>,
+}
+
 #[allow(clippy::enum_variant_names)]
 enum SubscriptionAddedEvent {
     AccountUpdateSubscription {
         uuid: Uuid,
-        notification_sender: AccountUpdateSender,
+        client: SubscriptionClient,
+        notification_sender: AccountUpdateQueueSender,
         accounts: HashSet<Vec<u8>>,
+        send_initial_state: bool,
+        /// Set for subscriptions replaying from the journal, notified of what to replay up until
+        /// once the subscription is added.
+        replay_bound_sender: Option<oneshot::Sender<ReplayBound>>,
+        /// Set for subscriptions coalescing writes per slot.
+        coalesce_max_delay: Option<Duration>,
     },
     ProgramUpdateSubscription {
         uuid: Uuid,
-        notification_sender: AccountUpdateSender,
+        client: SubscriptionClient,
+        notification_sender: AccountUpdateQueueSender,
         programs: HashSet<Vec<u8>>,
+        send_initial_state: bool,
+        replay_bound_sender: Option<oneshot::Sender<ReplayBound>>,
+        coalesce_max_delay: Option<Duration>,
     },
     PartialAccountUpdateSubscription {
         uuid: Uuid,
+        client: SubscriptionClient,
         notification_sender: PartialAccountUpdateSender,
         skip_votes: bool,
     },
     SlotUpdateSubscription {
         uuid: Uuid,
+        client: SubscriptionClient,
         notification_sender: SlotUpdateSender,
     },
     SlotEntryUpdateSubscription {
         uuid: Uuid,
+        client: SubscriptionClient,
         notification_sender: SlotEntryUpdateSender,
     },
     TransactionUpdateSubscription {
         uuid: Uuid,
+        client: SubscriptionClient,
         notification_sender: TransactionUpdateSender,
+        /// Set if the client may receive the transaction and its status meta.
+        full_transaction_data: bool,
     },
     BlockUpdateSubscription {
         uuid: Uuid,
+        client: SubscriptionClient,
         notification_sender: BlockUpdateSender,
     },
+    FullBlockSubscription {
+        uuid: Uuid,
+        client: SubscriptionClient,
+        notification_sender: FullBlockSender,
+        include_account_writes: bool,
+    },
+    SlotTipSubscription {
+        uuid: Uuid,
+        client: SubscriptionClient,
+        notification_sender: SlotTipSender,
+        commitments: HashSet<i32>,
+    },
 }
 
 impl Debug for SubscriptionAddedEvent {
@@ -269,6 +734,12 @@ impl Debug for SubscriptionAddedEvent {
             SubscriptionAddedEvent::BlockUpdateSubscription { uuid, .. } => {
                 ("block_update_subscribe".to_string(), uuid)
             }
+            SubscriptionAddedEvent::FullBlockSubscription { uuid, .. } => {
+                ("full_block_subscribe".to_string(), uuid)
+            }
+            SubscriptionAddedEvent::SlotTipSubscription { uuid, .. } => {
+                ("slot_tip_subscribe".to_string(), uuid)
+            }
         };
         writeln!(
             f,
@@ -283,6 +754,55 @@ impl Display for SubscriptionAddedEvent {
     }
 }
 
+impl SubscriptionAddedEvent {
+    /// Streams the status to the subscriber in place of registering its subscription.
+    fn reject(self, status: Status) {
+        let result = match &self {
+            SubscriptionAddedEvent::AccountUpdateSubscription {
+                notification_sender,
+                ..
+            }
+            | SubscriptionAddedEvent::ProgramUpdateSubscription {
+                notification_sender,
+                ..
+            } => notification_sender
+                .stream_error(status)
+                .map_err(GeyserServiceError::from),
+            SubscriptionAddedEvent::PartialAccountUpdateSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+            SubscriptionAddedEvent::SlotUpdateSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+            SubscriptionAddedEvent::SlotEntryUpdateSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+            SubscriptionAddedEvent::TransactionUpdateSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+            SubscriptionAddedEvent::BlockUpdateSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+            SubscriptionAddedEvent::FullBlockSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+            SubscriptionAddedEvent::SlotTipSubscription {
+                notification_sender,
+                ..
+            } => send_status(notification_sender, status),
+        };
+        if let Err(e) = result {
+            warn!("error rejecting subscription [{self}]: {e}");
+        }
+    }
+}
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 enum SubscriptionClosedEvent {
@@ -293,6 +813,8 @@ enum SubscriptionClosedEvent {
     SlotEntryUpdateSubscription(Uuid),
     TransactionUpdateSubscription(Uuid),
     BlockUpdateSubscription(Uuid),
+    FullBlockSubscription(Uuid),
+    SlotTipSubscription(Uuid),
 }
 
 #[derive(Error, Debug)]
@@ -307,12 +829,26 @@ pub enum GeyserServiceError {
     NotificationReceiverDisconnected,
 }
 
+impl From<QueueSendError> for GeyserServiceError {
+    fn from(e: QueueSendError) -> Self {
+        match e {
+            QueueSendError::Full => GeyserServiceError::NotificationReceiverFull,
+            QueueSendError::Closed => GeyserServiceError::NotificationReceiverDisconnected,
+        }
+    }
+}
+
 type GeyserServiceResult<T> = Result<T, GeyserServiceError>;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ServerTlsConfig {
     pub cert_path: String,
     pub key_path: String,
+
+    /// PEM bundle of the CAs client certificates must be issued by. If set, clients must
+    /// authenticate with a certificate, in addition to any access token, and are identified in
+    /// logs by its subject. Only read at startup.
+    pub client_ca_path: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -325,27 +861,192 @@ pub struct GeyserServiceConfig {
 
     pub tls_config: Option<ServerTlsConfig>,
     pub access_token: Option<String>,
+
+    /// Tokens granted access to the service, each with its own permissions and quotas. Checked
+    /// in addition to `access_token`, which is granted unrestricted access.
+    pub access_tokens: Option<Vec<AccessTokenConfig>>,
+
+    /// Keeps the latest value of every account seen so that subscribers can request the
+    /// current state upon subscribing, and clients can read it with `GetAccount`.
+    /// Bounded by the plugin's `account_cache_owners`, which is required. Defaults to false.
+    account_cache_enabled: Option<bool>,
+
+    /// Max time writes are held for by coalescing subscriptions that don't specify one.
+    /// Defaults to 1000ms.
+    coalesce_max_delay_ms: Option<u64>,
+
+    /// Number of threads account and program subscription updates are dispatched on.
+    /// Defaults to 4.
+    dispatch_threads: Option<usize>,
+
+    /// Compresses responses with gzip or zstd for clients that accept either. Defaults to false.
+    compression_enabled: Option<bool>,
+
+    /// Assembles full blocks for `SubscribeFullBlocks`. Defaults to false.
+    full_blocks_enabled: Option<bool>,
+
+    /// Holds onto every account write until its block is assembled, so that full block
+    /// subscribers can request them. Defaults to false.
+    full_block_account_writes_enabled: Option<bool>,
+
+    /// Holds account writes until their transaction is received, linking its index, invoked
+    /// programs and status to them. Delays account streams accordingly. Defaults to false.
+    transaction_linkage_enabled: Option<bool>,
+
+    /// Max time writes are held for awaiting their transaction, after which they're streamed
+    /// without it. Later writes to the same accounts are held up meanwhile, keeping each account's
+    /// writes in order. Defaults to 2000ms.
+    transaction_linkage_max_delay_ms: Option<u64>,
+
+    /// Cadence of the HTTP/2 pings sent on every connection. Defaults to 10000ms.
+    http2_keepalive_interval_ms: Option<u64>,
+
+    /// Time a ping may go unacknowledged before its connection is closed. Defaults to 20000ms.
+    http2_keepalive_timeout_ms: Option<u64>,
+
+    /// Max number of concurrent streams, i.e. subscriptions and calls, per connection.
+    /// Unlimited by default.
+    pub max_concurrent_streams: Option<u32>,
+
+    /// Max time subscribers are given upon shutdown to receive what's buffered for them, along
+    /// with the reason they're being disconnected. Defaults to 5000ms.
+    shutdown_drain_timeout_ms: Option<u64>,
+
+    /// Token required to call the admin service, which is only served if set. Only read at
+    /// startup.
+    pub admin_access_token: Option<String>,
 }
 
-pub struct GeyserService {
-    /// Highest slot observed for a write, thus far.
-    /// This value is returned in the http headers to clients on initial connection.
-    highest_write_slot: Arc<AtomicU64>,
+impl GeyserServiceConfig {
+    pub fn account_cache_enabled(&self) -> bool {
+        self.account_cache_enabled.unwrap_or(false)
+    }
 
-    /// General service configurations.
-    service_config: GeyserServiceConfig,
+    pub fn compression_enabled(&self) -> bool {
+        self.compression_enabled.unwrap_or(false)
+    }
 
-    /// Used to add new subscriptions.
-    subscription_added_tx: Sender<SubscriptionAddedEvent>,
+    pub fn http2_keepalive_interval(&self) -> Duration {
+        Duration::from_millis(
+            self.http2_keepalive_interval_ms
+                .unwrap_or(DEFAULT_HTTP2_KEEPALIVE_INTERVAL_MS),
+        )
+    }
 
-    /// Used to close existing subscriptions.
-    subscription_closed_sender: SubscriptionClosedSender,
+    pub fn http2_keepalive_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.http2_keepalive_timeout_ms
+                .unwrap_or(DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_MS),
+        )
+    }
 
-    /// Internal event loop thread.
-    t_hdl: JoinHandle<()>,
+    pub fn shutdown_drain_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.shutdown_drain_timeout_ms
+                .unwrap_or(DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS),
+        )
+    }
+}
+
+/// Applies the settings of a reloaded [GeyserServiceConfig] that can change while the service is
+/// running. Everything else only takes effect upon restart.
+#[derive(Clone)]
+pub struct GeyserServiceReloader {
+    heartbeat_interval_ms: Arc<AtomicU64>,
+    heartbeat_interval_tx: Sender<Duration>,
+}
+
+impl GeyserServiceReloader {
+    pub fn reload(&self, service_config: &GeyserServiceConfig) {
+        let heartbeat_interval_ms = service_config.heartbeat_interval_ms;
+        if self
+            .heartbeat_interval_ms
+            .swap(heartbeat_interval_ms, Ordering::Relaxed)
+            != heartbeat_interval_ms
+        {
+            info!("heartbeat interval changed to {heartbeat_interval_ms}ms");
+            let _ = self
+                .heartbeat_interval_tx
+                .send(Duration::from_millis(heartbeat_interval_ms));
+        }
+    }
+}
+
+/// Shuts the service down in order: subscribers are told why they're being disconnected, the
+/// server is given time to stream out what's buffered for them, and the event loop is stopped last.
+pub struct GeyserServiceShutdown {
+    is_shutting_down: Arc<AtomicBool>,
+    shutdown_tx: Sender<ShutdownEvent>,
+    t_hdl: JoinHandle<()>,
+}
+
+impl GeyserServiceShutdown {
+    /// Rejects new subscriptions and streams `Unavailable` to every subscriber, which is then
+    /// disconnected once what's buffered for it has been sent.
+    pub fn close_subscriptions(&self, reason: &str) {
+        self.is_shutting_down.store(true, Ordering::Relaxed);
+        let _ = self
+            .shutdown_tx
+            .send(ShutdownEvent::CloseSubscriptions(reason.to_string()));
+    }
+
+    /// Stops the event loop and waits for it to exit.
+    pub fn join(self) {
+        let _ = self.shutdown_tx.send(ShutdownEvent::Exit);
+        self.t_hdl.join().unwrap();
+    }
+}
+
+pub struct GeyserService {
+    /// Shared with the plugin, which records channel drops reported to subscribers.
+    metrics: Arc<GeyserMetrics>,
+
+    /// Used to replay account updates to subscriptions requesting a `from_slot`.
+    journal_reader: Option<JournalReader>,
+
+    /// Updated by the event loop as account updates are received, if enabled.
+    account_cache: Option<Arc<RwLock<AccountCache>>>,
+
+    /// Updated by the event loop as slot statuses are received.
+    slot_tracker: Arc<RwLock<SlotTracker>>,
+
+    /// Highest slot observed for a write, thus far.
+    /// This value is returned in the http headers to clients on initial connection.
+    highest_write_slot: Arc<AtomicU64>,
+
+    /// General service configurations.
+    service_config: GeyserServiceConfig,
+
+    /// Cadence of heartbeats, which may differ from `service_config` once reloaded.
+    heartbeat_interval_ms: Arc<AtomicU64>,
+
+    /// Used to change the cadence of heartbeats.
+    heartbeat_interval_tx: Sender<Duration>,
+
+    /// Used to add new subscriptions.
+    subscription_added_tx: Sender<SubscriptionAddedEvent>,
+
+    /// Used to close existing subscriptions.
+    subscription_closed_sender: SubscriptionClosedSender,
+
+    /// Whether the event loop is alive, as reported by the health service.
+    event_loop_liveness: EventLoopLiveness,
+
+    /// Set once shutting down, after which subscriptions are rejected.
+    is_shutting_down: Arc<AtomicBool>,
+
+    /// Used to shut the event loop down.
+    shutdown_tx: Sender<ShutdownEvent>,
+
+    /// Used to hand requests to the admin service off to the event loop.
+    admin_request_tx: Sender<AdminRequest>,
+
+    /// Internal event loop thread, handed off to whoever shuts the service down.
+    t_hdl: Option<JoinHandle<()>>,
 }
 
 impl GeyserService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         service_config: GeyserServiceConfig,
         // Account updates streamed from the validator.
@@ -353,42 +1054,644 @@ impl GeyserService {
         // Slot updates streamed from the validator.
         slot_update_rx: Receiver<TimestampedSlotUpdate>,
         // Slot updates streamed from the validator.
-        slot_entry_update_rx: Receiver<TimestampedSlotEntryUpdate>,
+        slot_entry_update_rx: Receiver<SlotEntryNotification>,
         // Block metadata receiver
         block_update_receiver: Receiver<TimestampedBlockUpdate>,
         // Transaction updates
         transaction_update_receiver: Receiver<TimestampedTransactionUpdate>,
+        // Startup account updates if buffered separately, followed by the end of startup.
+        startup_notification_rx: Receiver<StartupNotification>,
         // This value is maintained in the upstream context.
         highest_write_slot: Arc<AtomicU64>,
+        // Set if the plugin journals account updates.
+        journal_reader: Option<JournalReader>,
+        // Owners of the accounts held in the account cache, which the plugin requires whenever the
+        // cache is enabled. All accounts are cached if unset, which only suits a handful of them,
+        // e.g. the mock server's fixtures.
+        account_cache_owners: Option<HashSet<Vec<u8>>>,
+        // Metrics recorded by the plugin and the service.
+        metrics: Arc<GeyserMetrics>,
     ) -> Self {
         let (subscription_added_tx, subscription_added_rx) = unbounded();
         let (subscription_closed_tx, subscription_closed_rx) = unbounded();
+        let (heartbeat_interval_tx, heartbeat_interval_rx) = unbounded();
+        let (shutdown_tx, shutdown_rx) = unbounded();
+        let (admin_request_tx, admin_request_rx) = unbounded();
         let heartbeat_tick = tick(Duration::from_millis(service_config.heartbeat_interval_ms));
+        let metrics_sample_tick = tick(METRICS_SAMPLE_INTERVAL);
+
+        let block_assembler = service_config
+            .full_blocks_enabled
+            .unwrap_or(false)
+            .then(|| {
+                BlockAssembler::new(
+                    service_config
+                        .full_block_account_writes_enabled
+                        .unwrap_or(false),
+                )
+            });
+
+        let transaction_linker = service_config
+            .transaction_linkage_enabled
+            .unwrap_or(false)
+            .then(|| {
+                TransactionLinker::new(Duration::from_millis(
+                    service_config
+                        .transaction_linkage_max_delay_ms
+                        .unwrap_or(DEFAULT_TRANSACTION_LINKAGE_MAX_DELAY_MS),
+                ))
+            });
 
+        let slot_tracker = Arc::new(RwLock::new(SlotTracker::default()));
+        let account_cache = service_config
+            .account_cache_enabled()
+            .then(|| Arc::new(RwLock::new(AccountCache::new(account_cache_owners))));
+
+        let heartbeat_interval_ms = Arc::new(AtomicU64::new(service_config.heartbeat_interval_ms));
+        // ticks along with heartbeats
+        let event_loop_liveness = EventLoopLiveness::new(heartbeat_interval_ms.clone());
         let t_hdl = Self::event_loop(
+            account_cache.clone(),
+            block_assembler,
+            transaction_linker,
+            slot_tracker.clone(),
+            service_config
+                .dispatch_threads
+                .unwrap_or(DEFAULT_DISPATCH_THREADS),
             account_update_rx,
             slot_update_rx,
             slot_entry_update_rx,
             block_update_receiver,
             transaction_update_receiver,
+            startup_notification_rx,
             subscription_added_rx,
             subscription_closed_rx,
             heartbeat_tick,
+            heartbeat_interval_rx,
+            metrics_sample_tick,
+            shutdown_rx,
+            admin_request_rx,
+            event_loop_liveness.clone(),
+            metrics.clone(),
         );
 
-        Self {
-            highest_write_slot,
-            service_config,
-            subscription_added_tx,
-            subscription_closed_sender: SubscriptionClosedSender {
-                inner: subscription_closed_tx,
-            },
-            t_hdl,
+        Self {
+            metrics,
+            journal_reader,
+            account_cache,
+            slot_tracker,
+            highest_write_slot,
+            heartbeat_interval_ms,
+            heartbeat_interval_tx,
+            service_config,
+            subscription_added_tx,
+            subscription_closed_sender: SubscriptionClosedSender {
+                inner: subscription_closed_tx,
+            },
+            event_loop_liveness,
+            is_shutting_down: Arc::new(AtomicBool::new(false)),
+            shutdown_tx,
+            admin_request_tx,
+            t_hdl: Some(t_hdl),
+        }
+    }
+
+    /// Returns the handle used to shut the service down, which joins the event loop and so is
+    /// only returned once.
+    pub fn shutdown_handle(&mut self) -> Option<GeyserServiceShutdown> {
+        Some(GeyserServiceShutdown {
+            is_shutting_down: self.is_shutting_down.clone(),
+            shutdown_tx: self.shutdown_tx.clone(),
+            t_hdl: self.t_hdl.take()?,
+        })
+    }
+
+    fn ensure_accepting_subscriptions(&self) -> Result<(), Status> {
+        if self.is_shutting_down.load(Ordering::Relaxed) {
+            return Err(Status::unavailable("server is shutting down"));
+        }
+        Ok(())
+    }
+
+    /// Returns a handle used to apply config changes while the service is running.
+    pub fn reloader(&self) -> GeyserServiceReloader {
+        GeyserServiceReloader {
+            heartbeat_interval_ms: self.heartbeat_interval_ms.clone(),
+            heartbeat_interval_tx: self.heartbeat_interval_tx.clone(),
+        }
+    }
+
+    pub fn event_loop_liveness(&self) -> EventLoopLiveness {
+        self.event_loop_liveness.clone()
+    }
+
+    /// Returns the admin service, which lists and kills the service's subscriptions.
+    pub fn admin_service(&self) -> GeyserAdminService {
+        GeyserAdminService::new(self.admin_request_tx.clone())
+    }
+
+    fn account_cache(&self, method: &str) -> Result<&RwLock<AccountCache>, Status> {
+        self.account_cache.as_deref().ok_or_else(|| {
+            Status::failed_precondition(format!("{method} requires account_cache_enabled"))
+        })
+    }
+
+    fn check_initial_state_supported(&self, send_initial_state: bool) -> Result<(), Status> {
+        if send_initial_state && !self.service_config.account_cache_enabled() {
+            return Err(Status::failed_precondition(
+                "send_initial_state requires account_cache_enabled",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Registers an account subscription, whose queue is streamed to the client thru `wrap`.
+    fn add_account_update_subscription<T, S>(
+        &self,
+        request: Request<SubscribeAccountUpdatesRequest>,
+        method: &'static str,
+        stream_name: &'static str,
+        wrap: impl FnOnce(AccountUpdateStream) -> S,
+    ) -> Result<Response<SubscriptionStream<Uuid, T, S>>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, method, request.get_ref().accounts.len())?;
+        let SubscribeAccountUpdatesRequest {
+            accounts,
+            send_initial_state,
+            from_slot,
+            backpressure_policy,
+            coalesce,
+        } = request.into_inner();
+        let accounts: HashSet<Vec<u8>> = accounts.into_iter().collect();
+        let all_valid_pubkeys = accounts.iter().all(|a| a.len() == 32);
+        if !all_valid_pubkeys {
+            return Err(Status::invalid_argument(
+                "a pubkey with length != 32 was provided",
+            ));
+        }
+        self.check_initial_state_supported(send_initial_state)?;
+        let replay = self.check_replay_supported(from_slot, send_initial_state)?;
+        let backpressure_policy = BackpressurePolicy::try_from(backpressure_policy)
+            .map_err(|_| Status::invalid_argument("unknown backpressure_policy"))?;
+
+        let filter = accounts.clone();
+        let (notification_sender, replay_bound_sender, notification_stream) = self
+            .account_update_stream(backpressure_policy, replay, move |update| {
+                filter.contains(&update.pubkey)
+            });
+
+        let uuid = Uuid::new_v4();
+        self.subscription_added_tx
+            .try_send(SubscriptionAddedEvent::AccountUpdateSubscription {
+                uuid,
+                client,
+                notification_sender,
+                accounts,
+                send_initial_state,
+                replay_bound_sender,
+                coalesce_max_delay: self.coalesce_max_delay(coalesce),
+            })
+            .map_err(|e| {
+                error!(
+                    "failed to add subscribe_account_updates subscription: {}",
+                    e
+                );
+                Status::internal("error adding subscription")
+            })?;
+
+        let stream = SubscriptionStream::from_stream(
+            wrap(notification_stream),
+            uuid,
+            (
+                self.subscription_closed_sender.clone(),
+                SubscriptionClosedEvent::AccountUpdateSubscription(uuid),
+            ),
+            stream_name,
+        )
+        .with_permit(permit);
+        let mut resp = Response::new(stream);
+        resp.metadata_mut().insert(
+            HIGHEST_WRITE_SLOT_HEADER,
+            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
+        );
+
+        Ok(resp)
+    }
+
+    /// Registers a program subscription, whose queue is streamed to the client thru `wrap`.
+    fn add_program_update_subscription<T, S>(
+        &self,
+        request: Request<SubscribeProgramsUpdatesRequest>,
+        method: &'static str,
+        stream_name: &'static str,
+        wrap: impl FnOnce(AccountUpdateStream) -> S,
+    ) -> Result<Response<SubscriptionStream<Uuid, T, S>>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, method, request.get_ref().programs.len())?;
+        let SubscribeProgramsUpdatesRequest {
+            programs,
+            send_initial_state,
+            from_slot,
+            backpressure_policy,
+            coalesce,
+        } = request.into_inner();
+        let programs: HashSet<Vec<u8>> = programs.into_iter().collect();
+        let all_valid_pubkeys = programs.iter().all(|a| a.len() == 32);
+        if !all_valid_pubkeys {
+            return Err(Status::invalid_argument(
+                "a pubkey with length != 32 was provided",
+            ));
+        }
+        self.check_initial_state_supported(send_initial_state)?;
+        let replay = self.check_replay_supported(from_slot, send_initial_state)?;
+        let backpressure_policy = BackpressurePolicy::try_from(backpressure_policy)
+            .map_err(|_| Status::invalid_argument("unknown backpressure_policy"))?;
+
+        let filter = programs.clone();
+        let (notification_sender, replay_bound_sender, notification_stream) = self
+            .account_update_stream(backpressure_policy, replay, move |update| {
+                filter.contains(&update.owner)
+            });
+
+        let uuid = Uuid::new_v4();
+        self.subscription_added_tx
+            .try_send(SubscriptionAddedEvent::ProgramUpdateSubscription {
+                uuid,
+                client,
+                notification_sender,
+                programs,
+                send_initial_state,
+                replay_bound_sender,
+                coalesce_max_delay: self.coalesce_max_delay(coalesce),
+            })
+            .map_err(|e| {
+                error!(
+                    "failed to add subscribe_program_updates subscription: {}",
+                    e
+                );
+                Status::internal("error adding subscription")
+            })?;
+
+        let stream = SubscriptionStream::from_stream(
+            wrap(notification_stream),
+            uuid,
+            (
+                self.subscription_closed_sender.clone(),
+                SubscriptionClosedEvent::ProgramUpdateSubscription(uuid),
+            ),
+            stream_name,
+        )
+        .with_permit(permit);
+        let mut resp = Response::new(stream);
+        resp.metadata_mut().insert(
+            HIGHEST_WRITE_SLOT_HEADER,
+            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
+        );
+
+        Ok(resp)
+    }
+
+    /// Registers a slot subscription, whose channel is streamed to the client thru `wrap`.
+    fn add_slot_update_subscription<T, S>(
+        &self,
+        request: Request<SubscribeSlotUpdateRequest>,
+        method: &'static str,
+        stream_name: &'static str,
+        wrap: impl FnOnce(SubscriberStream<MaybeSlotUpdate>) -> S,
+    ) -> Result<Response<SubscriptionStream<Uuid, T, S>>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, method, 0)?;
+        let (subscription_tx, subscription_rx) =
+            subscriber_channel(self.service_config.subscriber_buffer_size);
+
+        let uuid = Uuid::new_v4();
+        self.subscription_added_tx
+            .try_send(SubscriptionAddedEvent::SlotUpdateSubscription {
+                uuid,
+                client,
+                notification_sender: subscription_tx,
+            })
+            .map_err(|e| {
+                error!("failed to add subscribe_slot_updates subscription: {}", e);
+                Status::internal("error adding subscription")
+            })?;
+
+        let stream = SubscriptionStream::from_stream(
+            wrap(ReceiverStream::new(subscription_rx)),
+            uuid,
+            (
+                self.subscription_closed_sender.clone(),
+                SubscriptionClosedEvent::SlotUpdateSubscription(uuid),
+            ),
+            stream_name,
+        )
+        .with_permit(permit);
+        let mut resp = Response::new(stream);
+        resp.metadata_mut().insert(
+            HIGHEST_WRITE_SLOT_HEADER,
+            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
+        );
+
+        Ok(resp)
+    }
+
+    /// Registers a slot entry subscription, whose channel is streamed to the client thru `wrap`.
+    fn add_slot_entry_update_subscription<T, S>(
+        &self,
+        request: Request<SubscribeSlotEntryUpdateRequest>,
+        method: &'static str,
+        stream_name: &'static str,
+        wrap: impl FnOnce(SubscriberStream<MaybeSlotEntryUpdate>) -> S,
+    ) -> Result<Response<SubscriptionStream<Uuid, T, S>>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, method, 0)?;
+        let (subscription_tx, subscription_rx) =
+            subscriber_channel(self.service_config.subscriber_buffer_size);
+
+        let uuid = Uuid::new_v4();
+        self.subscription_added_tx
+            .try_send(SubscriptionAddedEvent::SlotEntryUpdateSubscription {
+                uuid,
+                client,
+                notification_sender: subscription_tx,
+            })
+            .map_err(|e| {
+                error!("failed to add subscribe_slot_entry_updates subscription: {e}");
+                Status::internal("error adding subscription")
+            })?;
+
+        let stream = SubscriptionStream::from_stream(
+            wrap(ReceiverStream::new(subscription_rx)),
+            uuid,
+            (
+                self.subscription_closed_sender.clone(),
+                SubscriptionClosedEvent::SlotEntryUpdateSubscription(uuid),
+            ),
+            stream_name,
+        )
+        .with_permit(permit);
+        let mut resp = Response::new(stream);
+        resp.metadata_mut().insert(
+            HIGHEST_WRITE_SLOT_HEADER,
+            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
+        );
+
+        Ok(resp)
+    }
+
+    /// Registers a transaction subscription, whose channel is streamed to the client thru `wrap`.
+    fn add_transaction_update_subscription<T, S>(
+        &self,
+        request: Request<SubscribeTransactionUpdatesRequest>,
+        method: &'static str,
+        stream_name: &'static str,
+        wrap: impl FnOnce(SubscriberStream<MaybeTransactionUpdate>) -> S,
+    ) -> Result<Response<SubscriptionStream<Uuid, T, S>>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, method, 0)?;
+        let full_transaction_data = request
+            .extensions()
+            .get::<Arc<AccessGrant>>()
+            .map_or(true, |grant| grant.full_transaction_data_allowed());
+        let (subscription_tx, subscription_rx) =
+            subscriber_channel(self.service_config.subscriber_buffer_size);
+
+        let uuid = Uuid::new_v4();
+        self.subscription_added_tx
+            .try_send(SubscriptionAddedEvent::TransactionUpdateSubscription {
+                uuid,
+                client,
+                notification_sender: subscription_tx,
+                full_transaction_data,
+            })
+            .map_err(|e| {
+                error!(
+                    "failed to add subscribe_transaction_updates subscription: {}",
+                    e
+                );
+                Status::internal("error adding subscription")
+            })?;
+
+        let stream = SubscriptionStream::from_stream(
+            wrap(ReceiverStream::new(subscription_rx)),
+            uuid,
+            (
+                self.subscription_closed_sender.clone(),
+                SubscriptionClosedEvent::TransactionUpdateSubscription(uuid),
+            ),
+            stream_name,
+        )
+        .with_permit(permit);
+
+        let mut resp = Response::new(stream);
+        resp.metadata_mut().insert(
+            HIGHEST_WRITE_SLOT_HEADER,
+            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
+        );
+        Ok(resp)
+    }
+
+    /// Registers a block subscription, whose channel is streamed to the client thru `wrap`.
+    fn add_block_update_subscription<T, S>(
+        &self,
+        request: Request<SubscribeBlockUpdatesRequest>,
+        method: &'static str,
+        stream_name: &'static str,
+        wrap: impl FnOnce(SubscriberStream<MaybeBlockUpdate>) -> S,
+    ) -> Result<Response<SubscriptionStream<Uuid, T, S>>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, method, 0)?;
+        let (subscription_tx, subscription_rx) =
+            subscriber_channel(self.service_config.subscriber_buffer_size);
+
+        let uuid = Uuid::new_v4();
+        self.subscription_added_tx
+            .try_send(SubscriptionAddedEvent::BlockUpdateSubscription {
+                uuid,
+                client,
+                notification_sender: subscription_tx,
+            })
+            .map_err(|e| {
+                error!("failed to add subscribe_block_updates subscription: {}", e);
+                Status::internal("error adding subscription")
+            })?;
+
+        let stream = SubscriptionStream::from_stream(
+            wrap(ReceiverStream::new(subscription_rx)),
+            uuid,
+            (
+                self.subscription_closed_sender.clone(),
+                SubscriptionClosedEvent::BlockUpdateSubscription(uuid),
+            ),
+            stream_name,
+        )
+        .with_permit(permit);
+
+        let mut resp = Response::new(stream);
+        resp.metadata_mut().insert(
+            HIGHEST_WRITE_SLOT_HEADER,
+            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
+        );
+        Ok(resp)
+    }
+
+    /// Returns the max delay of a coalescing subscription.
+    fn coalesce_max_delay(&self, coalesce: Option<CoalesceOptions>) -> Option<Duration> {
+        coalesce.map(|CoalesceOptions { max_delay_ms }| {
+            let max_delay_ms = if max_delay_ms == 0 {
+                self.service_config
+                    .coalesce_max_delay_ms
+                    .unwrap_or(DEFAULT_COALESCE_MAX_DELAY_MS)
+            } else {
+                max_delay_ms as u64
+            };
+            Duration::from_millis(max_delay_ms)
+        })
+    }
+
+    fn check_replay_supported(
+        &self,
+        from_slot: Option<u64>,
+        send_initial_state: bool,
+    ) -> Result<Option<(JournalReader, u64)>, Status> {
+        let Some(from_slot) = from_slot else {
+            return Ok(None);
+        };
+        if send_initial_state {
+            return Err(Status::invalid_argument(
+                "from_slot and send_initial_state are mutually exclusive",
+            ));
+        }
+        let journal_reader = self
+            .journal_reader
+            .as_ref()
+            .ok_or_else(|| Status::failed_precondition("from_slot requires journal_config"))?;
+        journal_reader
+            .check_retained(from_slot)
+            .map_err(journal_error_status)?;
+
+        Ok(Some((journal_reader.clone(), from_slot)))
+    }
+
+    /// Streams journaled updates matching the filter from `from_slot` up until the subscription was
+    /// registered with the event loop, then forwards live updates buffered in the meantime. Writes
+    /// still awaiting their transaction at that point are left to the live updates, which stream
+    /// them linked to it.
+    async fn replay_journal<F>(
+        journal_reader: JournalReader,
+        from_slot: u64,
+        matches_filter: F,
+        replay_bound_receiver: oneshot::Receiver<ReplayBound>,
+        mut live_update_receiver: AccountUpdateQueueReceiver,
+        notification_sender: TokioSender<Result<EncodedMaybeAccountUpdate, Status>>,
+    ) where
+        F: Fn(&AccountUpdate) -> bool + Send + 'static,
+    {
+        let Ok(ReplayBound { max_seq, held_seqs }) = replay_bound_receiver.await else {
+            // rejected by the event loop, which streams why thru the queue
+            if let Some(Err(status)) = live_update_receiver.next().await {
+                let _ = notification_sender.send(Err(status)).await;
+            }
+            return;
+        };
+
+        let replay_sender = notification_sender.clone();
+        let replay_result = tokio::task::spawn_blocking(move || {
+            journal_reader.wait_for_seq(max_seq, JOURNAL_CATCH_UP_TIMEOUT)?;
+            journal_reader.replay(from_slot, max_seq, |update| {
+                let Some(account_update) = update.account_update.as_ref() else {
+                    return true;
+                };
+                if !matches_filter(account_update) || held_seqs.contains(&account_update.seq) {
+                    return true;
+                }
+                replay_sender
+                    .blocking_send(Ok(MaybeAccountUpdate {
+                        msg: Some(maybe_account_update::Msg::AccountUpdate(update)),
+                    }
+                    .into()))
+                    .is_ok()
+            })
+        })
+        .await;
+
+        let replay_status = match replay_result {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(journal_error_status(e)),
+            Err(e) => {
+                error!("journal replay task failed: {}", e);
+                Some(Status::internal("error replaying journal"))
+            }
+        };
+        if let Some(status) = replay_status {
+            let _ = notification_sender.send(Err(status)).await;
+            return;
+        }
+
+        live_update_receiver.finish_replay();
+        while let Some(update) = live_update_receiver.next().await {
+            if notification_sender.send(update).await.is_err() {
+                return;
+            }
         }
     }
 
-    pub fn join(self) {
-        self.t_hdl.join().unwrap();
+    /// Creates the queue backing an account or program subscription and the stream returned to the
+    /// client. When replaying, the stream is fed by a task replaying the journal before handing off
+    /// to the queue, and the returned oneshot must be passed along to the event loop.
+    fn account_update_stream<F>(
+        &self,
+        backpressure_policy: BackpressurePolicy,
+        replay: Option<(JournalReader, u64)>,
+        matches_filter: F,
+    ) -> (
+        AccountUpdateQueueSender,
+        Option<oneshot::Sender<ReplayBound>>,
+        AccountUpdateStream,
+    )
+    where
+        F: Fn(&AccountUpdate) -> bool + Send + 'static,
+    {
+        let (notification_sender, notification_receiver) = account_update_queue(
+            self.service_config.subscriber_buffer_size,
+            backpressure_policy,
+            self.metrics.channel_drops(Channel::AccountUpdate),
+        );
+
+        match replay {
+            Some((journal_reader, from_slot)) => {
+                let (replay_sender, replay_receiver) =
+                    channel(self.service_config.subscriber_buffer_size);
+                let (replay_bound_sender, replay_bound_receiver) = oneshot::channel();
+                notification_receiver.start_replay();
+                tokio::spawn(Self::replay_journal(
+                    journal_reader,
+                    from_slot,
+                    matches_filter,
+                    replay_bound_receiver,
+                    notification_receiver,
+                    replay_sender,
+                ));
+                (
+                    notification_sender,
+                    Some(replay_bound_sender),
+                    Box::pin(ReceiverStream::new(replay_receiver)),
+                )
+            }
+            None => (notification_sender, None, Box::pin(notification_receiver)),
+        }
     }
 
     /// Main event loop that handles the following:
@@ -397,14 +1700,26 @@ impl GeyserService {
     ///     3. Receive geyser events and stream them to subscribers.
     #[allow(clippy::too_many_arguments)]
     fn event_loop(
+        account_cache: Option<Arc<RwLock<AccountCache>>>,
+        mut block_assembler: Option<BlockAssembler>,
+        mut transaction_linker: Option<TransactionLinker>,
+        slot_tracker: Arc<RwLock<SlotTracker>>,
+        dispatch_threads: usize,
         account_update_rx: Receiver<TimestampedAccountUpdate>,
         slot_update_rx: Receiver<TimestampedSlotUpdate>,
-        slot_entry_update_rx: Receiver<TimestampedSlotEntryUpdate>,
+        slot_entry_update_rx: Receiver<SlotEntryNotification>,
         block_update_receiver: Receiver<TimestampedBlockUpdate>,
         transaction_update_receiver: Receiver<TimestampedTransactionUpdate>,
+        startup_notification_rx: Receiver<StartupNotification>,
         subscription_added_rx: Receiver<SubscriptionAddedEvent>,
         subscription_closed_rx: Receiver<SubscriptionClosedEvent>,
-        heartbeat_tick: Receiver<Instant>,
+        mut heartbeat_tick: Receiver<Instant>,
+        heartbeat_interval_rx: Receiver<Duration>,
+        metrics_sample_tick: Receiver<Instant>,
+        shutdown_rx: Receiver<ShutdownEvent>,
+        admin_request_rx: Receiver<AdminRequest>,
+        liveness: EventLoopLiveness,
+        metrics: Arc<GeyserMetrics>,
     ) -> JoinHandle<()> {
         Builder::new()
             .name("geyser-service-event-loop".to_string())
@@ -423,60 +1738,202 @@ impl GeyserService {
 
                 let mut transaction_update_subscriptions: HashMap<Uuid, TransactionUpdateSubscription> = HashMap::new();
                 let mut block_update_subscriptions: HashMap<Uuid, BlockUpdateSubscription> = HashMap::new();
+                let mut full_block_subscriptions: HashMap<Uuid, FullBlockSubscription> = HashMap::new();
+                let mut slot_tip_subscriptions: HashMap<Uuid, SlotTipSubscription> = HashMap::new();
+
+                let dispatcher = AccountUpdateDispatcher::new(dispatch_threads);
+                // Highest account update seq dispatched thus far, used to hand off from journal replay to live updates.
+                let mut highest_dispatched_seq = 0;
+                // Streamed to subscribers once shutting down.
+                let mut shutdown_status: Option<Status> = None;
+                let transaction_linkage_expiry_tick = if transaction_linker.is_some() {
+                    tick(TRANSACTION_LINKAGE_EXPIRY_INTERVAL)
+                } else {
+                    never()
+                };
 
-                loop {
+                'event_loop: loop {
+                    let mut reloaded_heartbeat_interval = None;
                     crossbeam_channel::select! {
+                        recv(heartbeat_interval_rx) -> maybe_heartbeat_interval => {
+                            match maybe_heartbeat_interval {
+                                Err(e) => {
+                                    error!("error receiving a heartbeat interval: {}", e);
+                                    break 'event_loop;
+                                },
+                                Ok(heartbeat_interval) => reloaded_heartbeat_interval = Some(heartbeat_interval),
+                            }
+                        }
                         recv(heartbeat_tick) -> _ => {
                             debug!("sending heartbeats");
+                            liveness.record_tick();
+                            let _timer = metrics.start_event_timer(EventLoopEvent::Heartbeat);
                             let failed_subscription_ids = Self::send_heartbeats(&partial_account_update_subscriptions);
                             Self::drop_subscriptions(&failed_subscription_ids, &mut partial_account_update_subscriptions);
+                            let failed_subscription_ids = Self::send_heartbeats(&account_update_subscriptions);
+                            Self::drop_account_update_subscriptions(&failed_subscription_ids, &mut account_update_subscriptions, &dispatcher);
+                            let failed_subscription_ids = Self::send_heartbeats(&program_update_subscriptions);
+                            Self::drop_account_update_subscriptions(&failed_subscription_ids, &mut program_update_subscriptions, &dispatcher);
+                            let failed_subscription_ids = Self::send_heartbeats(&slot_update_subscriptions);
+                            Self::drop_subscriptions(&failed_subscription_ids, &mut slot_update_subscriptions);
+                            let failed_subscription_ids = Self::send_heartbeats(&slot_entry_update_subscriptions);
+                            Self::drop_subscriptions(&failed_subscription_ids, &mut slot_entry_update_subscriptions);
+                            let failed_subscription_ids = Self::send_heartbeats(&transaction_update_subscriptions);
+                            Self::drop_subscriptions(&failed_subscription_ids, &mut transaction_update_subscriptions);
+                            let failed_subscription_ids = Self::send_heartbeats(&block_update_subscriptions);
+                            Self::drop_subscriptions(&failed_subscription_ids, &mut block_update_subscriptions);
+                            let failed_subscription_ids = Self::send_heartbeats(&full_block_subscriptions);
+                            Self::drop_subscriptions(&failed_subscription_ids, &mut full_block_subscriptions);
+                            let failed_subscription_ids = Self::send_heartbeats(&slot_tip_subscriptions);
+                            Self::drop_subscriptions(&failed_subscription_ids, &mut slot_tip_subscriptions);
+
+                            let upstream_dropped_updates = metrics.channel_drops(Channel::AccountUpdate);
+                            let failed_subscription_ids = Self::send_lag_reports(&account_update_subscriptions, upstream_dropped_updates);
+                            Self::drop_account_update_subscriptions(&failed_subscription_ids, &mut account_update_subscriptions, &dispatcher);
+                            let failed_subscription_ids = Self::send_lag_reports(&program_update_subscriptions, upstream_dropped_updates);
+                            Self::drop_account_update_subscriptions(&failed_subscription_ids, &mut program_update_subscriptions, &dispatcher);
+                        }
+                        recv(metrics_sample_tick) -> _ => {
+                            metrics.set_channel_queue_depth(Channel::AccountUpdate, account_update_rx.len());
+                            metrics.set_channel_queue_depth(Channel::SlotUpdate, slot_update_rx.len());
+                            metrics.set_channel_queue_depth(Channel::SlotEntryUpdate, slot_entry_update_rx.len());
+                            metrics.set_channel_queue_depth(Channel::BlockUpdate, block_update_receiver.len());
+                            metrics.set_channel_queue_depth(Channel::TransactionUpdate, transaction_update_receiver.len());
+
+                            metrics.set_active_subscriptions(ACCOUNT_SUBSCRIPTION, account_update_subscriptions.len());
+                            metrics.set_active_subscriptions(PROGRAM_SUBSCRIPTION, program_update_subscriptions.len());
+                            metrics.set_active_subscriptions(PARTIAL_ACCOUNT_SUBSCRIPTION, partial_account_update_subscriptions.len());
+                            metrics.set_active_subscriptions(SLOT_SUBSCRIPTION, slot_update_subscriptions.len());
+                            metrics.set_active_subscriptions(SLOT_ENTRY_SUBSCRIPTION, slot_entry_update_subscriptions.len());
+                            metrics.set_active_subscriptions(TRANSACTION_SUBSCRIPTION, transaction_update_subscriptions.len());
+                            metrics.set_active_subscriptions(BLOCK_SUBSCRIPTION, block_update_subscriptions.len());
+                            metrics.set_active_subscriptions(FULL_BLOCK_SUBSCRIPTION, full_block_subscriptions.len());
+                            metrics.set_active_subscriptions(SLOT_TIP_SUBSCRIPTION, slot_tip_subscriptions.len());
                         }
                         recv(subscription_added_rx) -> maybe_subscription_added => {
                             info!("received new subscription");
-                            if let Err(e) = Self::handle_subscription_added(maybe_subscription_added, &mut account_update_subscriptions, &mut partial_account_update_subscriptions, &mut slot_update_subscriptions, &mut slot_entry_update_subscriptions,  &mut program_update_subscriptions, &mut transaction_update_subscriptions, &mut block_update_subscriptions) {
+                            let _timer = metrics.start_event_timer(EventLoopEvent::SubscriptionAdded);
+                            // added after the service stopped accepting subscriptions, but before it was told to close them
+                            if let (Some(status), Ok(subscription_added)) = (&shutdown_status, &maybe_subscription_added) {
+                                info!("rejecting subscription [{subscription_added}] as the service is shutting down");
+                                maybe_subscription_added.unwrap().reject(status.clone());
+                            } else if let Err(e) = Self::handle_subscription_added(maybe_subscription_added, &mut account_update_subscriptions, &mut partial_account_update_subscriptions, &mut slot_update_subscriptions, &mut slot_entry_update_subscriptions,  &mut program_update_subscriptions, &mut transaction_update_subscriptions, &mut block_update_subscriptions, &mut full_block_subscriptions, &mut slot_tip_subscriptions, account_cache.as_deref(), highest_dispatched_seq, transaction_linker.as_ref(), &dispatcher, &metrics) {
                                 error!("error adding new subscription: {}", e);
-                                return;
+                                break 'event_loop;
+                            }
+                        },
+                        recv(shutdown_rx) -> maybe_shutdown_event => {
+                            match maybe_shutdown_event {
+                                Ok(ShutdownEvent::CloseSubscriptions(reason)) => {
+                                    info!("closing subscriptions: {reason}");
+                                    let status = Status::unavailable(reason);
+                                    Self::close_subscriptions(&status, &mut account_update_subscriptions, &mut program_update_subscriptions, &mut partial_account_update_subscriptions, &mut slot_update_subscriptions, &mut slot_entry_update_subscriptions, &mut transaction_update_subscriptions, &mut block_update_subscriptions, &mut full_block_subscriptions, &mut slot_tip_subscriptions, &dispatcher);
+                                    shutdown_status = Some(status);
+                                }
+                                Ok(ShutdownEvent::Exit) | Err(_) => {
+                                    info!("shutting down event loop");
+                                    break 'event_loop;
+                                }
+                            }
+                        },
+                        recv(admin_request_rx) -> maybe_admin_request => {
+                            match maybe_admin_request {
+                                Ok(AdminRequest::ListSubscriptions(response_tx)) => {
+                                    let _ = response_tx.send(Self::list_subscriptions(&account_update_subscriptions, &program_update_subscriptions, &partial_account_update_subscriptions, &slot_update_subscriptions, &slot_entry_update_subscriptions, &transaction_update_subscriptions, &block_update_subscriptions, &full_block_subscriptions, &slot_tip_subscriptions));
+                                }
+                                Ok(AdminRequest::KillSubscription(uuid, response_tx)) => {
+                                    info!("killing subscription {uuid}");
+                                    let _ = response_tx.send(Self::kill_subscription(uuid, &mut account_update_subscriptions, &mut program_update_subscriptions, &mut partial_account_update_subscriptions, &mut slot_update_subscriptions, &mut slot_entry_update_subscriptions, &mut transaction_update_subscriptions, &mut block_update_subscriptions, &mut full_block_subscriptions, &mut slot_tip_subscriptions, &dispatcher));
+                                }
+                                Err(e) => {
+                                    error!("error receiving admin request: {}", e);
+                                    break 'event_loop;
+                                }
                             }
                         },
                         recv(subscription_closed_rx) -> maybe_subscription_closed => {
                             info!("closing subscription");
-                            if let Err(e) = Self::handle_subscription_closed(maybe_subscription_closed, &mut account_update_subscriptions, &mut partial_account_update_subscriptions, &mut slot_update_subscriptions, &mut slot_entry_update_subscriptions, &mut program_update_subscriptions, &mut transaction_update_subscriptions, &mut block_update_subscriptions) {
+                            let _timer = metrics.start_event_timer(EventLoopEvent::SubscriptionClosed);
+                            if let Err(e) = Self::handle_subscription_closed(maybe_subscription_closed, &mut account_update_subscriptions, &mut partial_account_update_subscriptions, &mut slot_update_subscriptions, &mut slot_entry_update_subscriptions, &mut program_update_subscriptions, &mut transaction_update_subscriptions, &mut block_update_subscriptions, &mut full_block_subscriptions, &mut slot_tip_subscriptions, &dispatcher) {
                                 error!("error closing existing subscription: {}", e);
-                                return;
+                                break 'event_loop;
                             }
                         },
                         recv(account_update_rx) -> maybe_account_update => {
                             debug!("received account update");
-                            match Self::handle_account_update_event(maybe_account_update, &account_update_subscriptions, &partial_account_update_subscriptions, &program_update_subscriptions) {
-                                Err(e) => {
-                                    error!("error handling an account update event: {}", e);
-                                    return;
-                                },
-                                Ok(failed_subscription_ids) => {
-                                    Self::drop_subscriptions(&failed_subscription_ids, &mut account_update_subscriptions);
-                                    Self::drop_subscriptions(&failed_subscription_ids, &mut partial_account_update_subscriptions);
-                                    Self::drop_subscriptions(&failed_subscription_ids, &mut program_update_subscriptions);
-                                },
+                            let _timer = metrics.start_event_timer(EventLoopEvent::AccountUpdate);
+                            if let Err(e) = Self::handle_account_update(maybe_account_update, &startup_notification_rx, &account_update_rx, transaction_linker.as_mut(), &mut highest_dispatched_seq, &mut account_update_subscriptions, &mut program_update_subscriptions, &mut partial_account_update_subscriptions, account_cache.as_deref(), block_assembler.as_mut(), &dispatcher) {
+                                error!("error handling an account update event: {}", e);
+                                break 'event_loop;
+                            }
+                        },
+                        recv(transaction_linkage_expiry_tick) -> _ => {
+                            let Some(transaction_linker) = transaction_linker.as_mut() else {
+                                continue;
+                            };
+                            let _timer = metrics.start_event_timer(EventLoopEvent::AccountUpdate);
+                            let result = transaction_linker.expire(Instant::now()).into_iter().try_for_each(|update| Self::dispatch_account_update(Ok(update), &mut highest_dispatched_seq, &mut partial_account_update_subscriptions, account_cache.as_deref(), block_assembler.as_mut(), &dispatcher));
+                            if let Err(e) = result {
+                                error!("error handling an account update event: {}", e);
+                                break 'event_loop;
+                            }
+                        },
+                        recv(startup_notification_rx) -> maybe_startup_notification => {
+                            debug!("received startup notification");
+                            let _timer = metrics.start_event_timer(EventLoopEvent::AccountUpdate);
+                            let result = Self::handle_startup_notification(maybe_startup_notification, &account_update_rx, transaction_linker.as_mut(), &mut highest_dispatched_seq, &mut account_update_subscriptions, &mut program_update_subscriptions, &mut partial_account_update_subscriptions, account_cache.as_deref(), block_assembler.as_mut(), &dispatcher);
+                            if let Err(e) = result {
+                                error!("error handling a startup notification: {}", e);
+                                break 'event_loop;
                             }
                         },
                         recv(slot_update_rx) -> maybe_slot_update => {
                             debug!("received slot update");
+                            let _timer = metrics.start_event_timer(EventLoopEvent::SlotUpdate);
+                            if let Ok(TimestampedSlotUpdate { slot_update: Some(slot_update), .. }) = &maybe_slot_update {
+                                dispatcher.handle_slot_update(slot_update);
+                                if let Some(block_assembler) = block_assembler.as_mut() {
+                                    match SlotUpdateStatus::try_from(slot_update.status) {
+                                        Ok(SlotUpdateStatus::Dead) => block_assembler.slot_dead(slot_update.slot),
+                                        Ok(SlotUpdateStatus::Rooted) => {
+                                            for block in block_assembler.slot_rooted(slot_update.slot) {
+                                                let failed_subscription_ids = Self::handle_full_block(block, &full_block_subscriptions);
+                                                Self::drop_subscriptions(&failed_subscription_ids, &mut full_block_subscriptions);
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                                let tip_updates = slot_tracker.write().unwrap().update(slot_update);
+                                let failed_subscription_ids = Self::handle_slot_tip_updates(tip_updates, &slot_tip_subscriptions);
+                                Self::drop_subscriptions(&failed_subscription_ids, &mut slot_tip_subscriptions);
+                            }
                             match Self::handle_slot_update_event(maybe_slot_update, &slot_update_subscriptions) {
                                 Err(e) => {
                                     error!("error handling a slot update event: {}", e);
-                                    return;
+                                    break 'event_loop;
                                 },
                                 Ok(failed_subscription_ids) => {
                                     Self::drop_subscriptions(&failed_subscription_ids, &mut slot_update_subscriptions);
                                 },
                             }
                         },
-                        recv(slot_entry_update_rx) -> maybe_slot_entry_update => {
+                        recv(slot_entry_update_rx) -> maybe_slot_entry_notification => {
                             debug!("received slot entry update");
+                            let _timer = metrics.start_event_timer(EventLoopEvent::SlotEntryUpdate);
+                            let maybe_slot_entry_update = maybe_slot_entry_notification.map(|SlotEntryNotification { entry_update, entry }| {
+                                if let (Some(block_assembler), Some(update)) = (block_assembler.as_mut(), &entry_update.entry_update) {
+                                    if let Some(block) = block_assembler.insert_entry(update.slot, entry) {
+                                        let failed_subscription_ids = Self::handle_full_block(block, &full_block_subscriptions);
+                                        Self::drop_subscriptions(&failed_subscription_ids, &mut full_block_subscriptions);
+                                    }
+                                }
+                                entry_update
+                            });
                             match Self::handle_slot_entry_update_event(maybe_slot_entry_update, &slot_entry_update_subscriptions) {
                                 Err(e) => {
                                     error!("error handling a slot entry update event: {}", e);
-                                    return;
+                                    break 'event_loop;
                                 },
                                 Ok(failed_subscription_ids) => {
                                     Self::drop_subscriptions(&failed_subscription_ids, &mut slot_entry_update_subscriptions);
@@ -485,10 +1942,17 @@ impl GeyserService {
                         },
                         recv(block_update_receiver) -> maybe_block_update => {
                             debug!("received block update");
+                            let _timer = metrics.start_event_timer(EventLoopEvent::BlockUpdate);
+                            if let (Some(block_assembler), Ok(TimestampedBlockUpdate { block_update: Some(block_update), .. })) = (block_assembler.as_mut(), &maybe_block_update) {
+                                if let Some(block) = block_assembler.insert_block_update(block_update.clone()) {
+                                    let failed_subscription_ids = Self::handle_full_block(block, &full_block_subscriptions);
+                                    Self::drop_subscriptions(&failed_subscription_ids, &mut full_block_subscriptions);
+                                }
+                            }
                             match Self::handle_block_update_event(maybe_block_update, &block_update_subscriptions) {
                                 Err(e) => {
                                     error!("error handling a block update event: {}", e);
-                                    return;
+                                    break 'event_loop;
                                 },
                                 Ok(failed_subscription_ids) => {
                                     Self::drop_subscriptions(&failed_subscription_ids, &mut block_update_subscriptions);
@@ -497,10 +1961,24 @@ impl GeyserService {
                         },
                         recv(transaction_update_receiver) -> maybe_transaction_update => {
                             debug!("received transaction update");
+                            let _timer = metrics.start_event_timer(EventLoopEvent::TransactionUpdate);
+                            if let (Some(transaction_linker), Ok(TimestampedTransactionUpdate { transaction: Some(transaction), .. })) = (transaction_linker.as_mut(), &maybe_transaction_update) {
+                                let result = transaction_linker.insert_transaction(transaction).into_iter().try_for_each(|update| Self::dispatch_account_update(Ok(update), &mut highest_dispatched_seq, &mut partial_account_update_subscriptions, account_cache.as_deref(), block_assembler.as_mut(), &dispatcher));
+                                if let Err(e) = result {
+                                    error!("error handling an account update event: {}", e);
+                                    break 'event_loop;
+                                }
+                            }
+                            if let (Some(block_assembler), Ok(TimestampedTransactionUpdate { transaction: Some(transaction), .. })) = (block_assembler.as_mut(), &maybe_transaction_update) {
+                                if let Some(block) = block_assembler.insert_transaction(transaction.clone()) {
+                                    let failed_subscription_ids = Self::handle_full_block(block, &full_block_subscriptions);
+                                    Self::drop_subscriptions(&failed_subscription_ids, &mut full_block_subscriptions);
+                                }
+                            }
                             match Self::handle_transaction_update_event(maybe_transaction_update, &transaction_update_subscriptions) {
                                 Err(e) => {
                                     error!("error handling a transaction update event: {}", e);
-                                    return;
+                                    break 'event_loop;
                                 },
                                 Ok(failed_subscription_ids) => {
                                     Self::drop_subscriptions(&failed_subscription_ids, &mut transaction_update_subscriptions);
@@ -508,7 +1986,12 @@ impl GeyserService {
                             }
                         },
                     }
+                    if let Some(heartbeat_interval) = reloaded_heartbeat_interval {
+                        heartbeat_tick = tick(heartbeat_interval);
+                    }
                 }
+                liveness.record_exit();
+                dispatcher.join();
             })
             .unwrap()
     }
@@ -517,14 +2000,16 @@ impl GeyserService {
         maybe_block_update: Result<TimestampedBlockUpdate, RecvError>,
         subscriptions: &HashMap<Uuid, BlockUpdateSubscription>,
     ) -> GeyserServiceResult<Vec<Uuid>> {
-        let block_update = maybe_block_update?;
+        let mut block_update = maybe_block_update?;
+        update_timing::stamp_dispatch(&mut block_update.timing);
         Ok(subscriptions
             .iter()
             .filter_map(|(uuid, sub)| {
-                if matches!(
-                    sub.notification_sender.try_send(Ok(block_update.clone())),
-                    Err(TokioTrySendError::Closed(_))
-                ) {
+                let result = sub.notification_sender.try_send(Ok(MaybeBlockUpdate {
+                    msg: Some(maybe_block_update::Msg::BlockUpdate(block_update.clone())),
+                }));
+                record_send(&sub.metrics, &result);
+                if matches!(result, Err(TokioTrySendError::Closed(_))) {
                     Some(*uuid)
                 } else {
                     None
@@ -533,19 +2018,100 @@ impl GeyserService {
             .collect())
     }
 
+    fn handle_slot_tip_updates(
+        tip_updates: Vec<SlotTipUpdate>,
+        subscriptions: &HashMap<Uuid, SlotTipSubscription>,
+    ) -> Vec<Uuid> {
+        let mut failed_subscription_ids = vec![];
+        for tip_update in tip_updates {
+            let ts = Some(prost_types::Timestamp::from(SystemTime::now()));
+            for (uuid, sub) in subscriptions {
+                if !sub.commitments.contains(&tip_update.commitment) {
+                    continue;
+                }
+                let result = sub.notification_sender.try_send(Ok(MaybeSlotTipUpdate {
+                    msg: Some(maybe_slot_tip_update::Msg::TipUpdate(
+                        TimestampedSlotTipUpdate {
+                            ts,
+                            tip_update: Some(tip_update),
+                        },
+                    )),
+                }));
+                record_send(&sub.metrics, &result);
+                if matches!(result, Err(TokioTrySendError::Closed(_))) {
+                    failed_subscription_ids.push(*uuid);
+                }
+            }
+        }
+        failed_subscription_ids
+    }
+
+    /// Streams an assembled block to full block subscribers, without account writes unless requested.
+    fn handle_full_block(
+        block: Block,
+        subscriptions: &HashMap<Uuid, FullBlockSubscription>,
+    ) -> Vec<Uuid> {
+        let ts = Some(prost_types::Timestamp::from(SystemTime::now()));
+        // streamed to subscribers that didn't request account writes
+        let mut block_without_account_writes = None;
+        subscriptions
+            .iter()
+            .filter_map(|(uuid, sub)| {
+                let block = if sub.include_account_writes || block.account_writes.is_empty() {
+                    block.clone()
+                } else {
+                    block_without_account_writes
+                        .get_or_insert_with(|| Block {
+                            account_writes: vec![],
+                            ..block.clone()
+                        })
+                        .clone()
+                };
+                let result = sub.notification_sender.try_send(Ok(MaybeBlock {
+                    msg: Some(maybe_block::Msg::Block(TimestampedBlock {
+                        ts,
+                        block: Some(block),
+                    })),
+                }));
+                record_send(&sub.metrics, &result);
+                if matches!(result, Err(TokioTrySendError::Closed(_))) {
+                    Some(*uuid)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     fn handle_transaction_update_event(
         maybe_transaction_update: Result<TimestampedTransactionUpdate, RecvError>,
         subscriptions: &HashMap<Uuid, TransactionUpdateSubscription>,
     ) -> GeyserServiceResult<Vec<Uuid>> {
-        let transaction_update = maybe_transaction_update?;
+        let mut transaction_update = maybe_transaction_update?;
+        update_timing::stamp_dispatch(&mut transaction_update.timing);
+        // streamed to subscribers that aren't allowed full transaction data
+        let mut stripped_transaction_update = None;
         Ok(subscriptions
             .iter()
             .filter_map(|(uuid, sub)| {
-                if matches!(
-                    sub.notification_sender
-                        .try_send(Ok(transaction_update.clone())),
-                    Err(TokioTrySendError::Closed(_))
-                ) {
+                let update = if sub.full_transaction_data {
+                    transaction_update.clone()
+                } else {
+                    stripped_transaction_update
+                        .get_or_insert_with(|| {
+                            let mut update = transaction_update.clone();
+                            if let Some(transaction) = update.transaction.as_mut() {
+                                transaction.tx = None;
+                            }
+                            update
+                        })
+                        .clone()
+                };
+                let result = sub.notification_sender.try_send(Ok(MaybeTransactionUpdate {
+                    msg: Some(maybe_transaction_update::Msg::TransactionUpdate(update)),
+                }));
+                record_send(&sub.metrics, &result);
+                if matches!(result, Err(TokioTrySendError::Closed(_))) {
                     Some(*uuid)
                 } else {
                     None
@@ -565,6 +2131,13 @@ impl GeyserService {
         program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
         transaction_update_subscriptions: &mut HashMap<Uuid, TransactionUpdateSubscription>,
         block_update_subscriptions: &mut HashMap<Uuid, BlockUpdateSubscription>,
+        full_block_subscriptions: &mut HashMap<Uuid, FullBlockSubscription>,
+        slot_tip_subscriptions: &mut HashMap<Uuid, SlotTipSubscription>,
+        account_cache: Option<&RwLock<AccountCache>>,
+        highest_dispatched_seq: u64,
+        transaction_linker: Option<&TransactionLinker>,
+        dispatcher: &AccountUpdateDispatcher,
+        metrics: &Arc<GeyserMetrics>,
     ) -> GeyserServiceResult<()> {
         let subscription_added = maybe_subscription_added?;
         info!("new subscription: {:?}", subscription_added);
@@ -572,19 +2145,53 @@ impl GeyserService {
         match subscription_added {
             SubscriptionAddedEvent::AccountUpdateSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
                 accounts,
+                send_initial_state,
+                replay_bound_sender,
+                coalesce_max_delay,
             } => {
-                account_update_subscriptions.insert(
+                let subscription = AccountUpdateSubscription {
+                    notification_sender: subscription_tx,
+                    filter: SubscriptionFilter::Pubkey,
+                    accounts,
+                    metrics: Arc::new(
+                        metrics.subscription_metrics(ACCOUNT_SUBSCRIPTION, uuid.to_string()),
+                    ),
+                    client,
+                };
+                if send_initial_state {
+                    let cache = account_cache
+                        .expect("checked upon subscribing")
+                        .read()
+                        .unwrap();
+                    let cached_updates = subscription.accounts.iter().filter_map(|a| cache.get(a));
+                    if !Self::stream_initial_state(&subscription, cached_updates) {
+                        return Ok(());
+                    }
+                }
+                if let Some(replay_bound_sender) = replay_bound_sender {
+                    let _ = replay_bound_sender.send(ReplayBound {
+                        max_seq: highest_dispatched_seq,
+                        held_seqs: transaction_linker
+                            .map(TransactionLinker::held_seqs)
+                            .unwrap_or_default(),
+                    });
+                }
+                dispatcher.add_subscription(
                     uuid,
-                    AccountUpdateSubscription {
-                        notification_sender: subscription_tx,
-                        accounts,
-                    },
+                    subscription.filter,
+                    &subscription.accounts,
+                    &subscription.notification_sender,
+                    coalesce_max_delay,
+                    subscription.metrics.clone(),
                 );
+                account_update_subscriptions.insert(uuid, subscription);
             }
             SubscriptionAddedEvent::PartialAccountUpdateSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
                 skip_votes,
             } => {
@@ -593,54 +2200,152 @@ impl GeyserService {
                     PartialAccountUpdateSubscription {
                         subscription_tx,
                         skip_votes,
+                        metrics: metrics
+                            .subscription_metrics(PARTIAL_ACCOUNT_SUBSCRIPTION, uuid.to_string()),
+                        client,
                     },
                 );
             }
             SubscriptionAddedEvent::SlotUpdateSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
             } => {
-                slot_update_subscriptions.insert(uuid, SlotUpdateSubscription { subscription_tx });
+                slot_update_subscriptions.insert(
+                    uuid,
+                    SlotUpdateSubscription {
+                        subscription_tx,
+                        metrics: metrics.subscription_metrics(SLOT_SUBSCRIPTION, uuid.to_string()),
+                        client,
+                    },
+                );
             }
             SubscriptionAddedEvent::SlotEntryUpdateSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
             } => {
-                slot_entry_update_subscriptions
-                    .insert(uuid, SlotEntryUpdateSubscription { subscription_tx });
+                slot_entry_update_subscriptions.insert(
+                    uuid,
+                    SlotEntryUpdateSubscription {
+                        subscription_tx,
+                        metrics: metrics
+                            .subscription_metrics(SLOT_ENTRY_SUBSCRIPTION, uuid.to_string()),
+                        client,
+                    },
+                );
             }
             SubscriptionAddedEvent::ProgramUpdateSubscription {
                 uuid,
+                client,
                 notification_sender,
                 programs,
+                send_initial_state,
+                replay_bound_sender,
+                coalesce_max_delay,
             } => {
-                program_update_subscriptions.insert(
+                let subscription = AccountUpdateSubscription {
+                    notification_sender,
+                    filter: SubscriptionFilter::Owner,
+                    accounts: programs,
+                    metrics: Arc::new(
+                        metrics.subscription_metrics(PROGRAM_SUBSCRIPTION, uuid.to_string()),
+                    ),
+                    client,
+                };
+                if send_initial_state {
+                    let cache = account_cache
+                        .expect("checked upon subscribing")
+                        .read()
+                        .unwrap();
+                    let cached_updates = subscription
+                        .accounts
+                        .iter()
+                        .flat_map(|program| cache.get_by_owner(program));
+                    if !Self::stream_initial_state(&subscription, cached_updates) {
+                        return Ok(());
+                    }
+                }
+                if let Some(replay_bound_sender) = replay_bound_sender {
+                    let _ = replay_bound_sender.send(ReplayBound {
+                        max_seq: highest_dispatched_seq,
+                        held_seqs: transaction_linker
+                            .map(TransactionLinker::held_seqs)
+                            .unwrap_or_default(),
+                    });
+                }
+                dispatcher.add_subscription(
                     uuid,
-                    AccountUpdateSubscription {
-                        notification_sender,
-                        accounts: programs,
-                    },
+                    subscription.filter,
+                    &subscription.accounts,
+                    &subscription.notification_sender,
+                    coalesce_max_delay,
+                    subscription.metrics.clone(),
                 );
+                program_update_subscriptions.insert(uuid, subscription);
             }
             SubscriptionAddedEvent::TransactionUpdateSubscription {
                 uuid,
+                client,
                 notification_sender,
+                full_transaction_data,
             } => {
                 transaction_update_subscriptions.insert(
                     uuid,
                     TransactionUpdateSubscription {
                         notification_sender,
+                        full_transaction_data,
+                        metrics: metrics
+                            .subscription_metrics(TRANSACTION_SUBSCRIPTION, uuid.to_string()),
+                        client,
                     },
                 );
             }
             SubscriptionAddedEvent::BlockUpdateSubscription {
                 uuid,
+                client,
                 notification_sender,
             } => {
                 block_update_subscriptions.insert(
                     uuid,
                     BlockUpdateSubscription {
                         notification_sender,
+                        metrics: metrics.subscription_metrics(BLOCK_SUBSCRIPTION, uuid.to_string()),
+                        client,
+                    },
+                );
+            }
+            SubscriptionAddedEvent::FullBlockSubscription {
+                uuid,
+                client,
+                notification_sender,
+                include_account_writes,
+            } => {
+                full_block_subscriptions.insert(
+                    uuid,
+                    FullBlockSubscription {
+                        notification_sender,
+                        include_account_writes,
+                        metrics: metrics
+                            .subscription_metrics(FULL_BLOCK_SUBSCRIPTION, uuid.to_string()),
+                        client,
+                    },
+                );
+            }
+            SubscriptionAddedEvent::SlotTipSubscription {
+                uuid,
+                client,
+                notification_sender,
+                commitments,
+            } => {
+                slot_tip_subscriptions.insert(
+                    uuid,
+                    SlotTipSubscription {
+                        notification_sender,
+                        commitments,
+                        metrics: metrics
+                            .subscription_metrics(SLOT_TIP_SUBSCRIPTION, uuid.to_string()),
+                        client,
                     },
                 );
             }
@@ -649,6 +2354,21 @@ impl GeyserService {
         Ok(())
     }
 
+    /// Streams the initial state to a new subscription.
+    /// Returns false if the subscription should not be added.
+    fn stream_initial_state<'a>(
+        subscription: &AccountUpdateSubscription,
+        cached_updates: impl Iterator<Item = &'a Arc<SharedAccountUpdate>>,
+    ) -> bool {
+        match subscription.stream_initial_state(cached_updates) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("error streaming initial state: {}", e);
+                false
+            }
+        }
+    }
+
     /// Handles closing existing subscriptions.
     #[allow(clippy::too_many_arguments)]
     fn handle_subscription_closed(
@@ -660,13 +2380,21 @@ impl GeyserService {
         program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
         transaction_update_subscriptions: &mut HashMap<Uuid, TransactionUpdateSubscription>,
         block_update_subscriptions: &mut HashMap<Uuid, BlockUpdateSubscription>,
+        full_block_subscriptions: &mut HashMap<Uuid, FullBlockSubscription>,
+        slot_tip_subscriptions: &mut HashMap<Uuid, SlotTipSubscription>,
+        dispatcher: &AccountUpdateDispatcher,
     ) -> GeyserServiceResult<()> {
         let subscription_closed = maybe_subscription_closed?;
         info!("closing subscription: {:?}", subscription_closed);
 
         match subscription_closed {
             SubscriptionClosedEvent::AccountUpdateSubscription(subscription_id) => {
-                let _ = account_update_subscriptions.remove(&subscription_id);
+                if account_update_subscriptions
+                    .remove(&subscription_id)
+                    .is_some()
+                {
+                    dispatcher.remove_subscription(subscription_id);
+                }
             }
             SubscriptionClosedEvent::PartialAccountUpdateSubscription(subscription_id) => {
                 let _ = partial_account_update_subscriptions.remove(&subscription_id);
@@ -678,7 +2406,12 @@ impl GeyserService {
                 let _ = slot_entry_update_subscriptions.remove(&subscription_id);
             }
             SubscriptionClosedEvent::ProgramUpdateSubscription(subscription_id) => {
-                let _ = program_update_subscriptions.remove(&subscription_id);
+                if program_update_subscriptions
+                    .remove(&subscription_id)
+                    .is_some()
+                {
+                    dispatcher.remove_subscription(subscription_id);
+                }
             }
             SubscriptionClosedEvent::TransactionUpdateSubscription(subscription_id) => {
                 let _ = transaction_update_subscriptions.remove(&subscription_id);
@@ -686,52 +2419,37 @@ impl GeyserService {
             SubscriptionClosedEvent::BlockUpdateSubscription(subscription_id) => {
                 let _ = block_update_subscriptions.remove(&subscription_id);
             }
+            SubscriptionClosedEvent::FullBlockSubscription(subscription_id) => {
+                let _ = full_block_subscriptions.remove(&subscription_id);
+            }
+            SubscriptionClosedEvent::SlotTipSubscription(subscription_id) => {
+                let _ = slot_tip_subscriptions.remove(&subscription_id);
+            }
         }
 
         Ok(())
     }
 
-    /// Streams account updates to subscribers.
+    /// Hands account updates off to the dispatcher and streams partial updates to subscribers.
     fn handle_account_update_event(
         maybe_account_update: Result<TimestampedAccountUpdate, RecvError>,
-        account_update_subscriptions: &HashMap<Uuid, AccountUpdateSubscription>,
         partial_account_update_subscriptions: &HashMap<Uuid, PartialAccountUpdateSubscription>,
-        program_update_subscriptions: &HashMap<Uuid, AccountUpdateSubscription>,
+        account_cache: Option<&RwLock<AccountCache>>,
+        block_assembler: Option<&mut BlockAssembler>,
+        dispatcher: &AccountUpdateDispatcher,
     ) -> GeyserServiceResult<Vec<Uuid>> {
-        let account_update = maybe_account_update?;
+        let mut account_update = maybe_account_update?;
+        update_timing::stamp_dispatch(&mut account_update.timing);
+        let account_update = Arc::new(SharedAccountUpdate::new(account_update));
         let update = account_update.account_update.as_ref().unwrap();
 
-        let failed_account_update_sends =
-            account_update_subscriptions
-                .iter()
-                .filter_map(|(uuid, sub)| {
-                    if sub.accounts.contains(update.pubkey.as_slice())
-                        && matches!(
-                            sub.notification_sender.try_send(Ok(account_update.clone())),
-                            Err(TokioTrySendError::Closed(_))
-                        )
-                    {
-                        Some(*uuid)
-                    } else {
-                        None
-                    }
-                });
-
-        let failed_program_update_sends =
-            program_update_subscriptions
-                .iter()
-                .filter_map(|(uuid, sub)| {
-                    if sub.accounts.contains(update.owner.as_slice())
-                        && matches!(
-                            sub.notification_sender.try_send(Ok(account_update.clone())),
-                            Err(TokioTrySendError::Closed(_))
-                        )
-                    {
-                        Some(*uuid)
-                    } else {
-                        None
-                    }
-                });
+        if let Some(account_cache) = account_cache {
+            account_cache.write().unwrap().insert(&account_update);
+        }
+        if let Some(block_assembler) = block_assembler {
+            block_assembler.insert_account_write(account_update.clone());
+        }
+        dispatcher.dispatch(account_update.clone());
 
         let partial_account_update = PartialAccountUpdate {
             slot: update.slot,
@@ -756,10 +2474,242 @@ impl GeyserService {
                 }
             });
 
-        Ok(failed_account_update_sends
-            .chain(failed_partial_account_update_sends)
-            .chain(failed_program_update_sends)
-            .collect())
+        Ok(failed_partial_account_update_sends.collect())
+    }
+
+    /// Holds the update if it's awaiting its transaction.
+    fn link_account_update(
+        maybe_account_update: Result<TimestampedAccountUpdate, RecvError>,
+        transaction_linker: Option<&mut TransactionLinker>,
+    ) -> Option<Result<TimestampedAccountUpdate, RecvError>> {
+        match (maybe_account_update, transaction_linker) {
+            (Ok(update), Some(transaction_linker)) => transaction_linker
+                .insert_account_update(update, Instant::now())
+                .map(Ok),
+            (maybe_account_update, _) => Some(maybe_account_update),
+        }
+    }
+
+    /// Streams the account update to subscribers, dropping partial account update subscriptions
+    /// that have gone away.
+    fn dispatch_account_update(
+        maybe_account_update: Result<TimestampedAccountUpdate, RecvError>,
+        highest_dispatched_seq: &mut u64,
+        partial_account_update_subscriptions: &mut HashMap<Uuid, PartialAccountUpdateSubscription>,
+        account_cache: Option<&RwLock<AccountCache>>,
+        block_assembler: Option<&mut BlockAssembler>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) -> GeyserServiceResult<()> {
+        if let Ok(TimestampedAccountUpdate {
+            account_update: Some(update),
+            ..
+        }) = &maybe_account_update
+        {
+            *highest_dispatched_seq = (*highest_dispatched_seq).max(update.seq);
+        }
+        let failed_subscription_ids = Self::handle_account_update_event(
+            maybe_account_update,
+            partial_account_update_subscriptions,
+            account_cache,
+            block_assembler,
+            dispatcher,
+        )?;
+        Self::drop_subscriptions(
+            &failed_subscription_ids,
+            partial_account_update_subscriptions,
+        );
+        Ok(())
+    }
+
+    /// Dispatches a live account update. The end of startup is notified on its own channel, so may
+    /// not have been received ahead of live updates notified after it, in which case it's
+    /// streamed first.
+    #[allow(clippy::too_many_arguments)]
+    fn handle_account_update(
+        maybe_account_update: Result<TimestampedAccountUpdate, RecvError>,
+        startup_notification_rx: &Receiver<StartupNotification>,
+        account_update_rx: &Receiver<TimestampedAccountUpdate>,
+        mut transaction_linker: Option<&mut TransactionLinker>,
+        highest_dispatched_seq: &mut u64,
+        account_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        partial_account_update_subscriptions: &mut HashMap<Uuid, PartialAccountUpdateSubscription>,
+        account_cache: Option<&RwLock<AccountCache>>,
+        mut block_assembler: Option<&mut BlockAssembler>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) -> GeyserServiceResult<()> {
+        let mut held_updates = vec![];
+        if matches!(&maybe_account_update, Ok(TimestampedAccountUpdate { account_update: Some(update), .. }) if !update.is_startup)
+        {
+            for notification in startup_notification_rx.try_iter() {
+                held_updates.extend(Self::apply_startup_notification(
+                    notification,
+                    account_update_rx,
+                    transaction_linker.as_deref_mut(),
+                    highest_dispatched_seq,
+                    account_update_subscriptions,
+                    program_update_subscriptions,
+                    partial_account_update_subscriptions,
+                    account_cache,
+                    block_assembler.as_deref_mut(),
+                    dispatcher,
+                )?);
+            }
+        }
+        if let Some(update) =
+            Self::link_account_update(maybe_account_update, transaction_linker.as_deref_mut())
+        {
+            Self::dispatch_account_update(
+                update,
+                highest_dispatched_seq,
+                partial_account_update_subscriptions,
+                account_cache,
+                block_assembler.as_deref_mut(),
+                dispatcher,
+            )?;
+        }
+        for update in held_updates {
+            if let Some(update) =
+                Self::link_account_update(Ok(update), transaction_linker.as_deref_mut())
+            {
+                Self::dispatch_account_update(
+                    update,
+                    highest_dispatched_seq,
+                    partial_account_update_subscriptions,
+                    account_cache,
+                    block_assembler.as_deref_mut(),
+                    dispatcher,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn handle_startup_notification(
+        maybe_startup_notification: Result<StartupNotification, RecvError>,
+        account_update_rx: &Receiver<TimestampedAccountUpdate>,
+        mut transaction_linker: Option<&mut TransactionLinker>,
+        highest_dispatched_seq: &mut u64,
+        account_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        partial_account_update_subscriptions: &mut HashMap<Uuid, PartialAccountUpdateSubscription>,
+        account_cache: Option<&RwLock<AccountCache>>,
+        mut block_assembler: Option<&mut BlockAssembler>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) -> GeyserServiceResult<()> {
+        let held_update = Self::apply_startup_notification(
+            maybe_startup_notification?,
+            account_update_rx,
+            transaction_linker.as_deref_mut(),
+            highest_dispatched_seq,
+            account_update_subscriptions,
+            program_update_subscriptions,
+            partial_account_update_subscriptions,
+            account_cache,
+            block_assembler.as_deref_mut(),
+            dispatcher,
+        )?;
+        if let Some(update) =
+            held_update.and_then(|update| Self::link_account_update(Ok(update), transaction_linker))
+        {
+            Self::dispatch_account_update(
+                update,
+                highest_dispatched_seq,
+                partial_account_update_subscriptions,
+                account_cache,
+                block_assembler,
+                dispatcher,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dispatches a startup account update, or streams the end of startup once the startup updates
+    /// notified on the live channel ahead of it have been dispatched. A live update taken off the
+    /// channel while doing so is returned, to be dispatched after it.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_startup_notification(
+        notification: StartupNotification,
+        account_update_rx: &Receiver<TimestampedAccountUpdate>,
+        mut transaction_linker: Option<&mut TransactionLinker>,
+        highest_dispatched_seq: &mut u64,
+        account_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        partial_account_update_subscriptions: &mut HashMap<Uuid, PartialAccountUpdateSubscription>,
+        account_cache: Option<&RwLock<AccountCache>>,
+        mut block_assembler: Option<&mut BlockAssembler>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) -> GeyserServiceResult<Option<TimestampedAccountUpdate>> {
+        let StartupNotification::Account(update) = notification else {
+            info!("startup complete");
+            let mut held_update = None;
+            for update in account_update_rx.try_iter() {
+                if !update.account_update.as_ref().is_some_and(|u| u.is_startup) {
+                    held_update = Some(update);
+                    break;
+                }
+                if let Some(update) =
+                    Self::link_account_update(Ok(update), transaction_linker.as_deref_mut())
+                {
+                    Self::dispatch_account_update(
+                        update,
+                        highest_dispatched_seq,
+                        partial_account_update_subscriptions,
+                        account_cache,
+                        block_assembler.as_deref_mut(),
+                        dispatcher,
+                    )?;
+                }
+            }
+            dispatcher.flush();
+            let failed_subscription_ids = Self::send_startup_complete(account_update_subscriptions);
+            Self::drop_account_update_subscriptions(
+                &failed_subscription_ids,
+                account_update_subscriptions,
+                dispatcher,
+            );
+            let failed_subscription_ids = Self::send_startup_complete(program_update_subscriptions);
+            Self::drop_account_update_subscriptions(
+                &failed_subscription_ids,
+                program_update_subscriptions,
+                dispatcher,
+            );
+            let failed_subscription_ids =
+                Self::send_startup_complete(partial_account_update_subscriptions);
+            Self::drop_subscriptions(
+                &failed_subscription_ids,
+                partial_account_update_subscriptions,
+            );
+            return Ok(held_update);
+        };
+        Self::dispatch_account_update(
+            Ok(update),
+            highest_dispatched_seq,
+            partial_account_update_subscriptions,
+            account_cache,
+            block_assembler,
+            dispatcher,
+        )?;
+        Ok(None)
+    }
+
+    /// Streams the end of startup to account update subscribers. Account and program
+    /// subscriptions are only marked once the dispatcher has caught up, so that it follows the
+    /// startup updates.
+    fn send_startup_complete<S: StartupCompleteStreamer>(
+        subscriptions: &HashMap<Uuid, S>,
+    ) -> Vec<Uuid> {
+        subscriptions
+            .iter()
+            .filter_map(|(uuid, sub)| {
+                matches!(
+                    sub.send_startup_complete(),
+                    Err(GeyserServiceError::NotificationReceiverDisconnected)
+                )
+                .then_some(*uuid)
+            })
+            .collect()
     }
 
     fn send_heartbeats<S: HeartbeatStreamer>(subscriptions: &HashMap<Uuid, S>) -> Vec<Uuid> {
@@ -775,20 +2725,70 @@ impl GeyserService {
         failed_subscription_ids
     }
 
+    /// Streams lag reports to account update subscribers that had updates dropped since the last report.
+    fn send_lag_reports(
+        subscriptions: &HashMap<Uuid, AccountUpdateSubscription>,
+        upstream_dropped_updates: u64,
+    ) -> Vec<Uuid> {
+        subscriptions
+            .iter()
+            .filter_map(|(uuid, sub)| {
+                if matches!(
+                    sub.notification_sender.report_lag(upstream_dropped_updates),
+                    Err(QueueSendError::Closed)
+                ) {
+                    Some(*uuid)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Streams slot updates to subscribers
     /// Returns a vector of UUIDs that failed to send to due to the subscription being closed
     fn handle_slot_update_event(
         maybe_slot_update: Result<TimestampedSlotUpdate, RecvError>,
         slot_update_subscriptions: &HashMap<Uuid, SlotUpdateSubscription>,
     ) -> GeyserServiceResult<Vec<Uuid>> {
-        let slot_update = maybe_slot_update?;
+        let mut slot_update = maybe_slot_update?;
+        update_timing::stamp_dispatch(&mut slot_update.timing);
         let failed_subscription_ids = slot_update_subscriptions
             .iter()
             .filter_map(|(uuid, sub)| {
-                if matches!(
-                    sub.subscription_tx.try_send(Ok(slot_update)),
-                    Err(TokioTrySendError::Closed(_))
-                ) {
+                let result = sub.subscription_tx.try_send(Ok(MaybeSlotUpdate {
+                    msg: Some(maybe_slot_update::Msg::SlotUpdate(slot_update)),
+                }));
+                record_send(&sub.metrics, &result);
+                if matches!(result, Err(TokioTrySendError::Closed(_))) {
+                    Some(*uuid)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Ok(failed_subscription_ids)
+    }
+
+    /// Streams slot updates to subscribers
+    /// Returns a vector of UUIDs that failed to send to due to the subscription being closed
+    fn handle_slot_entry_update_event(
+        maybe_slot_entry_update: Result<TimestampedSlotEntryUpdate, RecvError>,
+        slot_entry_update_subscriptions: &HashMap<Uuid, SlotEntryUpdateSubscription>,
+    ) -> GeyserServiceResult<Vec<Uuid>> {
+        let mut slot_entry_update = maybe_slot_entry_update?;
+        update_timing::stamp_dispatch(&mut slot_entry_update.timing);
+        let failed_subscription_ids = slot_entry_update_subscriptions
+            .iter()
+            .filter_map(|(uuid, sub)| {
+                let result = sub.subscription_tx.try_send(Ok(MaybeSlotEntryUpdate {
+                    msg: Some(maybe_slot_entry_update::Msg::EntryUpdate(
+                        slot_entry_update.clone(),
+                    )),
+                }));
+                record_send(&sub.metrics, &result);
+                if matches!(result, Err(TokioTrySendError::Closed(_))) {
                     Some(*uuid)
                 } else {
                     None
@@ -799,28 +2799,152 @@ impl GeyserService {
         Ok(failed_subscription_ids)
     }
 
-    /// Streams slot updates to subscribers
-    /// Returns a vector of UUIDs that failed to send to due to the subscription being closed
-    fn handle_slot_entry_update_event(
-        maybe_slot_entry_update: Result<TimestampedSlotEntryUpdate, RecvError>,
-        slot_entry_update_subscriptions: &HashMap<Uuid, SlotEntryUpdateSubscription>,
-    ) -> GeyserServiceResult<Vec<Uuid>> {
-        let slot_entry_update = maybe_slot_entry_update?;
-        let failed_subscription_ids = slot_entry_update_subscriptions
-            .iter()
-            .filter_map(|(uuid, sub)| {
-                if matches!(
-                    sub.subscription_tx.try_send(Ok(slot_entry_update)),
-                    Err(TokioTrySendError::Closed(_))
-                ) {
-                    Some(*uuid)
-                } else {
-                    None
-                }
-            })
-            .collect();
+    /// Drop broken account and program update connections, along with their dispatcher state.
+    fn drop_account_update_subscriptions(
+        subscription_ids: &[Uuid],
+        subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) {
+        for sub_id in subscription_ids {
+            dispatcher.remove_subscription(*sub_id);
+        }
+        Self::drop_subscriptions(subscription_ids, subscriptions);
+    }
+
+    /// Streams the status to every subscriber and closes their subscriptions. Their streams end
+    /// once what's buffered for them has been sent.
+    #[allow(clippy::too_many_arguments)]
+    fn close_subscriptions(
+        status: &Status,
+        account_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        partial_account_update_subscriptions: &mut HashMap<Uuid, PartialAccountUpdateSubscription>,
+        slot_update_subscriptions: &mut HashMap<Uuid, SlotUpdateSubscription>,
+        slot_entry_update_subscriptions: &mut HashMap<Uuid, SlotEntryUpdateSubscription>,
+        transaction_update_subscriptions: &mut HashMap<Uuid, TransactionUpdateSubscription>,
+        block_update_subscriptions: &mut HashMap<Uuid, BlockUpdateSubscription>,
+        full_block_subscriptions: &mut HashMap<Uuid, FullBlockSubscription>,
+        slot_tip_subscriptions: &mut HashMap<Uuid, SlotTipSubscription>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) {
+        for sub_id in account_update_subscriptions
+            .keys()
+            .chain(program_update_subscriptions.keys())
+        {
+            dispatcher.remove_subscription(*sub_id);
+        }
+        Self::close_all(status, account_update_subscriptions);
+        Self::close_all(status, program_update_subscriptions);
+        Self::close_all(status, partial_account_update_subscriptions);
+        Self::close_all(status, slot_update_subscriptions);
+        Self::close_all(status, slot_entry_update_subscriptions);
+        Self::close_all(status, transaction_update_subscriptions);
+        Self::close_all(status, block_update_subscriptions);
+        Self::close_all(status, full_block_subscriptions);
+        Self::close_all(status, slot_tip_subscriptions);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn list_subscriptions(
+        account_update_subscriptions: &HashMap<Uuid, AccountUpdateSubscription>,
+        program_update_subscriptions: &HashMap<Uuid, AccountUpdateSubscription>,
+        partial_account_update_subscriptions: &HashMap<Uuid, PartialAccountUpdateSubscription>,
+        slot_update_subscriptions: &HashMap<Uuid, SlotUpdateSubscription>,
+        slot_entry_update_subscriptions: &HashMap<Uuid, SlotEntryUpdateSubscription>,
+        transaction_update_subscriptions: &HashMap<Uuid, TransactionUpdateSubscription>,
+        block_update_subscriptions: &HashMap<Uuid, BlockUpdateSubscription>,
+        full_block_subscriptions: &HashMap<Uuid, FullBlockSubscription>,
+        slot_tip_subscriptions: &HashMap<Uuid, SlotTipSubscription>,
+    ) -> Vec<SubscriptionInfo> {
+        admin::describe_all(ACCOUNT_SUBSCRIPTION, account_update_subscriptions)
+            .chain(admin::describe_all(
+                PROGRAM_SUBSCRIPTION,
+                program_update_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                PARTIAL_ACCOUNT_SUBSCRIPTION,
+                partial_account_update_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                SLOT_SUBSCRIPTION,
+                slot_update_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                SLOT_ENTRY_SUBSCRIPTION,
+                slot_entry_update_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                TRANSACTION_SUBSCRIPTION,
+                transaction_update_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                BLOCK_SUBSCRIPTION,
+                block_update_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                FULL_BLOCK_SUBSCRIPTION,
+                full_block_subscriptions,
+            ))
+            .chain(admin::describe_all(
+                SLOT_TIP_SUBSCRIPTION,
+                slot_tip_subscriptions,
+            ))
+            .collect()
+    }
+
+    /// Streams `Cancelled` to the subscriber and closes its subscription, returning false if
+    /// there's no such subscription.
+    #[allow(clippy::too_many_arguments)]
+    fn kill_subscription(
+        uuid: Uuid,
+        account_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        program_update_subscriptions: &mut HashMap<Uuid, AccountUpdateSubscription>,
+        partial_account_update_subscriptions: &mut HashMap<Uuid, PartialAccountUpdateSubscription>,
+        slot_update_subscriptions: &mut HashMap<Uuid, SlotUpdateSubscription>,
+        slot_entry_update_subscriptions: &mut HashMap<Uuid, SlotEntryUpdateSubscription>,
+        transaction_update_subscriptions: &mut HashMap<Uuid, TransactionUpdateSubscription>,
+        block_update_subscriptions: &mut HashMap<Uuid, BlockUpdateSubscription>,
+        full_block_subscriptions: &mut HashMap<Uuid, FullBlockSubscription>,
+        slot_tip_subscriptions: &mut HashMap<Uuid, SlotTipSubscription>,
+        dispatcher: &AccountUpdateDispatcher,
+    ) -> bool {
+        let status = Status::cancelled("subscription killed by an administrator");
+        if Self::kill(uuid, &status, account_update_subscriptions)
+            || Self::kill(uuid, &status, program_update_subscriptions)
+        {
+            dispatcher.remove_subscription(uuid);
+            return true;
+        }
+        Self::kill(uuid, &status, partial_account_update_subscriptions)
+            || Self::kill(uuid, &status, slot_update_subscriptions)
+            || Self::kill(uuid, &status, slot_entry_update_subscriptions)
+            || Self::kill(uuid, &status, transaction_update_subscriptions)
+            || Self::kill(uuid, &status, block_update_subscriptions)
+            || Self::kill(uuid, &status, full_block_subscriptions)
+            || Self::kill(uuid, &status, slot_tip_subscriptions)
+    }
+
+    fn kill<S: ErrorStatusStreamer>(
+        uuid: Uuid,
+        status: &Status,
+        subscriptions: &mut HashMap<Uuid, S>,
+    ) -> bool {
+        let Some(sub) = subscriptions.remove(&uuid) else {
+            return false;
+        };
+        if let Err(e) = sub.stream_error(status.clone()) {
+            warn!("error streaming status to subscription {uuid}: {e}");
+        }
+        true
+    }
 
-        Ok(failed_subscription_ids)
+    fn close_all<S: ErrorStatusStreamer>(status: &Status, subscriptions: &mut HashMap<Uuid, S>) {
+        for (sub_id, sub) in subscriptions.drain() {
+            if let Err(e) = sub.stream_closing_status(status.clone()) {
+                // the stream still ends, just without the status
+                warn!("error streaming status to subscription {sub_id}: {e}");
+            }
+        }
     }
 
     /// Drop broken connections.
@@ -836,114 +2960,165 @@ impl GeyserService {
     }
 }
 
+/// Streams only the account updates, as account and program subscriptions did before heartbeats,
+/// lag reports and the end of startup were streamed alongside them.
+fn only_account_updates(stream: AccountUpdateStream) -> LegacyAccountUpdateStream {
+    Box::pin(stream.filter_map(|frame| match frame {
+        Ok(frame) => frame.into_timestamped().map(Ok),
+        Err(status) => Some(Err(status)),
+    }))
+}
+
+/// Streams only the updates of a channel-backed subscription, as its stream did before heartbeats
+/// were streamed alongside them.
+fn only_updates<M: Send + 'static, T: Send + 'static>(
+    stream: SubscriberStream<M>,
+    into_update: fn(M) -> Option<T>,
+) -> LegacyStream<T> {
+    Box::pin(stream.filter_map(move |msg| match msg {
+        Ok(msg) => into_update(msg).map(Ok),
+        Err(status) => Some(Err(status)),
+    }))
+}
+
+fn journal_error_status(e: JournalError) -> Status {
+    match e {
+        JournalError::SlotPruned { .. } => Status::out_of_range(e.to_string()),
+        JournalError::Gap { .. } => Status::data_loss(e.to_string()),
+        JournalError::NotCaughtUp { .. } => {
+            Status::unavailable(format!("{e}, replay would be incomplete"))
+        }
+        e => {
+            error!("error reading journal: {}", e);
+            Status::internal("error reading journal")
+        }
+    }
+}
+
 #[tonic::async_trait]
 impl Geyser for GeyserService {
     async fn get_heartbeat_interval(
         &self,
-        _request: Request<EmptyRequest>,
+        request: Request<EmptyRequest>,
     ) -> Result<Response<GetHeartbeatIntervalResponse>, Status> {
+        if let Some(grant) = request.extensions().get::<Arc<AccessGrant>>() {
+            grant.check_method("GetHeartbeatInterval")?;
+        }
         Ok(Response::new(GetHeartbeatIntervalResponse {
-            heartbeat_interval_ms: self.service_config.heartbeat_interval_ms,
+            heartbeat_interval_ms: self.heartbeat_interval_ms.load(Ordering::Relaxed),
         }))
     }
 
-    type SubscribeAccountUpdatesStream = SubscriptionStream<Uuid, TimestampedAccountUpdate>;
-    async fn subscribe_account_updates(
+    async fn get_slot_state(
         &self,
-        request: Request<SubscribeAccountUpdatesRequest>,
-    ) -> Result<Response<Self::SubscribeAccountUpdatesStream>, Status> {
-        let (notification_sender, notification_receiver) =
-            channel(self.service_config.subscriber_buffer_size);
+        request: Request<EmptyRequest>,
+    ) -> Result<Response<GetSlotStateResponse>, Status> {
+        if let Some(grant) = request.extensions().get::<Arc<AccessGrant>>() {
+            grant.check_method("GetSlotState")?;
+        }
+        Ok(Response::new(
+            self.slot_tracker.read().unwrap().slot_state(),
+        ))
+    }
 
-        let accounts: HashSet<Vec<u8>> = request.into_inner().accounts.into_iter().collect();
-        let all_valid_pubkeys = accounts.iter().all(|a| a.len() == 32);
-        if !all_valid_pubkeys {
-            return Err(Status::invalid_argument(
-                "a pubkey with length != 32 was provided",
-            ));
+    async fn get_account(
+        &self,
+        request: Request<GetAccountRequest>,
+    ) -> Result<Response<GetAccountResponse>, Status> {
+        if let Some(grant) = request.extensions().get::<Arc<AccessGrant>>() {
+            grant.check_method("GetAccount")?;
         }
+        let account = self
+            .account_cache("GetAccount")?
+            .read()
+            .unwrap()
+            .get(&request.get_ref().pubkey)
+            .and_then(|u| u.account_update.clone())
+            .ok_or_else(|| Status::not_found("account not cached"))?;
+        Ok(Response::new(GetAccountResponse {
+            account: Some(account),
+        }))
+    }
 
-        let uuid = Uuid::new_v4();
-        self.subscription_added_tx
-            .try_send(SubscriptionAddedEvent::AccountUpdateSubscription {
-                uuid,
-                notification_sender,
-                accounts,
+    async fn get_multiple_accounts(
+        &self,
+        request: Request<GetMultipleAccountsRequest>,
+    ) -> Result<Response<GetMultipleAccountsResponse>, Status> {
+        let pubkeys = &request.get_ref().pubkeys;
+        if let Some(grant) = request.extensions().get::<Arc<AccessGrant>>() {
+            grant.check_method("GetMultipleAccounts")?;
+            grant.check_num_accounts(pubkeys.len())?;
+        }
+        if pubkeys.len() > MAX_GET_MULTIPLE_ACCOUNTS {
+            return Err(Status::invalid_argument(format!(
+                "at most {MAX_GET_MULTIPLE_ACCOUNTS} accounts may be requested at once"
+            )));
+        }
+        let cache = self.account_cache("GetMultipleAccounts")?.read().unwrap();
+        let accounts = pubkeys
+            .iter()
+            .map(|pubkey| CachedAccount {
+                pubkey: pubkey.clone(),
+                account: cache.get(pubkey).and_then(|u| u.account_update.clone()),
             })
-            .map_err(|e| {
-                error!(
-                    "failed to add subscribe_account_updates subscription: {}",
-                    e
-                );
-                Status::internal("error adding subscription")
-            })?;
+            .collect();
+        Ok(Response::new(GetMultipleAccountsResponse { accounts }))
+    }
 
-        let stream = SubscriptionStream::new(
-            notification_receiver,
-            uuid,
-            (
-                self.subscription_closed_sender.clone(),
-                SubscriptionClosedEvent::AccountUpdateSubscription(uuid),
-            ),
+    type SubscribeAccountUpdatesStream =
+        SubscriptionStream<Uuid, EncodedTimestampedAccountUpdate, LegacyAccountUpdateStream>;
+    async fn subscribe_account_updates(
+        &self,
+        request: Request<SubscribeAccountUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeAccountUpdatesStream>, Status> {
+        self.add_account_update_subscription(
+            request,
+            "SubscribeAccountUpdates",
             "subscribe_account_updates",
-        );
-        let mut resp = Response::new(stream);
-        resp.metadata_mut().insert(
-            HIGHEST_WRITE_SLOT_HEADER,
-            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
-        );
-
-        Ok(resp)
+            only_account_updates,
+        )
     }
 
-    type SubscribeProgramUpdatesStream = SubscriptionStream<Uuid, TimestampedAccountUpdate>;
+    type SubscribeAccountUpdatesV2Stream =
+        SubscriptionStream<Uuid, EncodedMaybeAccountUpdate, AccountUpdateStream>;
+    async fn subscribe_account_updates_v2(
+        &self,
+        request: Request<SubscribeAccountUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeAccountUpdatesV2Stream>, Status> {
+        self.add_account_update_subscription(
+            request,
+            "SubscribeAccountUpdatesV2",
+            "subscribe_account_updates_v2",
+            |stream| stream,
+        )
+    }
 
+    type SubscribeProgramUpdatesStream =
+        SubscriptionStream<Uuid, EncodedTimestampedAccountUpdate, LegacyAccountUpdateStream>;
     async fn subscribe_program_updates(
         &self,
         request: Request<SubscribeProgramsUpdatesRequest>,
     ) -> Result<Response<Self::SubscribeProgramUpdatesStream>, Status> {
-        let (notification_sender, notification_receiver) =
-            channel(self.service_config.subscriber_buffer_size);
-
-        let programs: HashSet<Vec<u8>> = request.into_inner().programs.into_iter().collect();
-        let all_valid_pubkeys = programs.iter().all(|a| a.len() == 32);
-        if !all_valid_pubkeys {
-            return Err(Status::invalid_argument(
-                "a pubkey with length != 32 was provided",
-            ));
-        }
-
-        let uuid = Uuid::new_v4();
-        self.subscription_added_tx
-            .try_send(SubscriptionAddedEvent::ProgramUpdateSubscription {
-                uuid,
-                notification_sender,
-                programs,
-            })
-            .map_err(|e| {
-                error!(
-                    "failed to add subscribe_account_updates subscription: {}",
-                    e
-                );
-                Status::internal("error adding subscription")
-            })?;
-
-        let stream = SubscriptionStream::new(
-            notification_receiver,
-            uuid,
-            (
-                self.subscription_closed_sender.clone(),
-                SubscriptionClosedEvent::ProgramUpdateSubscription(uuid),
-            ),
+        self.add_program_update_subscription(
+            request,
+            "SubscribeProgramUpdates",
             "subscribe_program_updates",
-        );
-        let mut resp = Response::new(stream);
-        resp.metadata_mut().insert(
-            HIGHEST_WRITE_SLOT_HEADER,
-            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
-        );
+            only_account_updates,
+        )
+    }
 
-        Ok(resp)
+    type SubscribeProgramUpdatesV2Stream =
+        SubscriptionStream<Uuid, EncodedMaybeAccountUpdate, AccountUpdateStream>;
+    async fn subscribe_program_updates_v2(
+        &self,
+        request: Request<SubscribeProgramsUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeProgramUpdatesV2Stream>, Status> {
+        self.add_program_update_subscription(
+            request,
+            "SubscribeProgramUpdatesV2",
+            "subscribe_program_updates_v2",
+            |stream| stream,
+        )
     }
 
     type SubscribePartialAccountUpdatesStream = SubscriptionStream<Uuid, MaybePartialAccountUpdate>;
@@ -951,13 +3126,18 @@ impl Geyser for GeyserService {
         &self,
         request: Request<SubscribePartialAccountUpdatesRequest>,
     ) -> Result<Response<Self::SubscribePartialAccountUpdatesStream>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, "SubscribePartialAccountUpdates", 0)?;
         let (subscription_tx, subscription_rx) =
-            channel(self.service_config.subscriber_buffer_size);
+            subscriber_channel(self.service_config.subscriber_buffer_size);
 
         let uuid = Uuid::new_v4();
         self.subscription_added_tx
             .try_send(SubscriptionAddedEvent::PartialAccountUpdateSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
                 skip_votes: request.into_inner().skip_vote_accounts,
             })
@@ -977,7 +3157,8 @@ impl Geyser for GeyserService {
                 SubscriptionClosedEvent::PartialAccountUpdateSubscription(uuid),
             ),
             "subscribe_partial_account_updates",
-        );
+        )
+        .with_permit(permit);
         let mut resp = Response::new(stream);
         resp.metadata_mut().insert(
             HIGHEST_WRITE_SLOT_HEADER,
@@ -987,100 +3168,185 @@ impl Geyser for GeyserService {
         Ok(resp)
     }
 
-    type SubscribeSlotUpdatesStream = SubscriptionStream<Uuid, TimestampedSlotUpdate>;
+    type SubscribeSlotUpdatesStream =
+        SubscriptionStream<Uuid, TimestampedSlotUpdate, LegacyStream<TimestampedSlotUpdate>>;
     async fn subscribe_slot_updates(
         &self,
-        _request: Request<SubscribeSlotUpdateRequest>,
+        request: Request<SubscribeSlotUpdateRequest>,
     ) -> Result<Response<Self::SubscribeSlotUpdatesStream>, Status> {
-        let (subscription_tx, subscription_rx) =
-            channel(self.service_config.subscriber_buffer_size);
-
-        let uuid = Uuid::new_v4();
-        self.subscription_added_tx
-            .try_send(SubscriptionAddedEvent::SlotUpdateSubscription {
-                uuid,
-                notification_sender: subscription_tx,
-            })
-            .map_err(|e| {
-                error!("failed to add subscribe_slot_updates subscription: {}", e);
-                Status::internal("error adding subscription")
-            })?;
-
-        let stream = SubscriptionStream::new(
-            subscription_rx,
-            uuid,
-            (
-                self.subscription_closed_sender.clone(),
-                SubscriptionClosedEvent::SlotUpdateSubscription(uuid),
-            ),
+        self.add_slot_update_subscription(
+            request,
+            "SubscribeSlotUpdates",
             "subscribe_slot_updates",
-        );
-        let mut resp = Response::new(stream);
-        resp.metadata_mut().insert(
-            HIGHEST_WRITE_SLOT_HEADER,
-            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
-        );
+            |stream| {
+                only_updates(stream, |msg| match msg.msg {
+                    Some(maybe_slot_update::Msg::SlotUpdate(update)) => Some(update),
+                    _ => None,
+                })
+            },
+        )
+    }
 
-        Ok(resp)
+    type SubscribeSlotUpdatesV2Stream = SubscriptionStream<Uuid, MaybeSlotUpdate>;
+    async fn subscribe_slot_updates_v2(
+        &self,
+        request: Request<SubscribeSlotUpdateRequest>,
+    ) -> Result<Response<Self::SubscribeSlotUpdatesV2Stream>, Status> {
+        self.add_slot_update_subscription(
+            request,
+            "SubscribeSlotUpdatesV2",
+            "subscribe_slot_updates_v2",
+            |stream| stream,
+        )
     }
 
-    type SubscribeSlotEntryUpdatesStream = SubscriptionStream<Uuid, TimestampedSlotEntryUpdate>;
+    type SubscribeSlotEntryUpdatesStream = SubscriptionStream<
+        Uuid,
+        TimestampedSlotEntryUpdate,
+        LegacyStream<TimestampedSlotEntryUpdate>,
+    >;
     async fn subscribe_slot_entry_updates(
         &self,
-        _request: Request<SubscribeSlotEntryUpdateRequest>,
+        request: Request<SubscribeSlotEntryUpdateRequest>,
     ) -> Result<Response<Self::SubscribeSlotEntryUpdatesStream>, Status> {
-        let (subscription_tx, subscription_rx) =
-            channel(self.service_config.subscriber_buffer_size);
-
-        let uuid = Uuid::new_v4();
-        self.subscription_added_tx
-            .try_send(SubscriptionAddedEvent::SlotEntryUpdateSubscription {
-                uuid,
-                notification_sender: subscription_tx,
-            })
-            .map_err(|e| {
-                error!("failed to add subscribe_slot_entry_updates subscription: {e}");
-                Status::internal("error adding subscription")
-            })?;
-
-        let stream = SubscriptionStream::new(
-            subscription_rx,
-            uuid,
-            (
-                self.subscription_closed_sender.clone(),
-                SubscriptionClosedEvent::SlotEntryUpdateSubscription(uuid),
-            ),
+        self.add_slot_entry_update_subscription(
+            request,
+            "SubscribeSlotEntryUpdates",
             "subscribe_slot_entry_updates",
-        );
-        let mut resp = Response::new(stream);
-        resp.metadata_mut().insert(
-            HIGHEST_WRITE_SLOT_HEADER,
-            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
-        );
-
-        Ok(resp)
+            |stream| {
+                only_updates(stream, |msg| match msg.msg {
+                    Some(maybe_slot_entry_update::Msg::EntryUpdate(update)) => Some(update),
+                    _ => None,
+                })
+            },
+        )
     }
 
-    type SubscribeTransactionUpdatesStream = SubscriptionStream<Uuid, TimestampedTransactionUpdate>;
+    type SubscribeSlotEntryUpdatesV2Stream = SubscriptionStream<Uuid, MaybeSlotEntryUpdate>;
+    async fn subscribe_slot_entry_updates_v2(
+        &self,
+        request: Request<SubscribeSlotEntryUpdateRequest>,
+    ) -> Result<Response<Self::SubscribeSlotEntryUpdatesV2Stream>, Status> {
+        self.add_slot_entry_update_subscription(
+            request,
+            "SubscribeSlotEntryUpdatesV2",
+            "subscribe_slot_entry_updates_v2",
+            |stream| stream,
+        )
+    }
 
+    type SubscribeTransactionUpdatesStream = SubscriptionStream<
+        Uuid,
+        TimestampedTransactionUpdate,
+        LegacyStream<TimestampedTransactionUpdate>,
+    >;
     async fn subscribe_transaction_updates(
         &self,
-        _request: Request<SubscribeTransactionUpdatesRequest>,
+        request: Request<SubscribeTransactionUpdatesRequest>,
     ) -> Result<Response<Self::SubscribeTransactionUpdatesStream>, Status> {
+        self.add_transaction_update_subscription(
+            request,
+            "SubscribeTransactionUpdates",
+            "subscribe_transaction_updates",
+            |stream| {
+                only_updates(stream, |msg| match msg.msg {
+                    Some(maybe_transaction_update::Msg::TransactionUpdate(update)) => Some(update),
+                    _ => None,
+                })
+            },
+        )
+    }
+
+    type SubscribeTransactionUpdatesV2Stream = SubscriptionStream<Uuid, MaybeTransactionUpdate>;
+    async fn subscribe_transaction_updates_v2(
+        &self,
+        request: Request<SubscribeTransactionUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeTransactionUpdatesV2Stream>, Status> {
+        self.add_transaction_update_subscription(
+            request,
+            "SubscribeTransactionUpdatesV2",
+            "subscribe_transaction_updates_v2",
+            |stream| stream,
+        )
+    }
+
+    type SubscribeBlockUpdatesStream =
+        SubscriptionStream<Uuid, TimestampedBlockUpdate, LegacyStream<TimestampedBlockUpdate>>;
+    async fn subscribe_block_updates(
+        &self,
+        request: Request<SubscribeBlockUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeBlockUpdatesStream>, Status> {
+        self.add_block_update_subscription(
+            request,
+            "SubscribeBlockUpdates",
+            "subscribe_block_updates",
+            |stream| {
+                only_updates(stream, |msg| match msg.msg {
+                    Some(maybe_block_update::Msg::BlockUpdate(update)) => Some(update),
+                    _ => None,
+                })
+            },
+        )
+    }
+
+    type SubscribeBlockUpdatesV2Stream = SubscriptionStream<Uuid, MaybeBlockUpdate>;
+    async fn subscribe_block_updates_v2(
+        &self,
+        request: Request<SubscribeBlockUpdatesRequest>,
+    ) -> Result<Response<Self::SubscribeBlockUpdatesV2Stream>, Status> {
+        self.add_block_update_subscription(
+            request,
+            "SubscribeBlockUpdatesV2",
+            "subscribe_block_updates_v2",
+            |stream| stream,
+        )
+    }
+
+    type SubscribeFullBlocksStream = SubscriptionStream<Uuid, MaybeBlock>;
+    async fn subscribe_full_blocks(
+        &self,
+        request: Request<SubscribeFullBlocksRequest>,
+    ) -> Result<Response<Self::SubscribeFullBlocksStream>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, "SubscribeFullBlocks", 0)?;
+        if !request
+            .extensions()
+            .get::<Arc<AccessGrant>>()
+            .map_or(true, |grant| grant.full_transaction_data_allowed())
+        {
+            return Err(Status::permission_denied(
+                "full blocks require full transaction data",
+            ));
+        }
+        if !self.service_config.full_blocks_enabled.unwrap_or(false) {
+            return Err(Status::failed_precondition("full blocks are not enabled"));
+        }
+        let include_account_writes = request.get_ref().include_account_writes;
+        if include_account_writes
+            && !self
+                .service_config
+                .full_block_account_writes_enabled
+                .unwrap_or(false)
+        {
+            return Err(Status::failed_precondition(
+                "full block account writes are not enabled",
+            ));
+        }
         let (subscription_tx, subscription_rx) =
-            channel(self.service_config.subscriber_buffer_size);
+            subscriber_channel(self.service_config.subscriber_buffer_size);
 
         let uuid = Uuid::new_v4();
         self.subscription_added_tx
-            .try_send(SubscriptionAddedEvent::TransactionUpdateSubscription {
+            .try_send(SubscriptionAddedEvent::FullBlockSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
+                include_account_writes,
             })
             .map_err(|e| {
-                error!(
-                    "failed to add subscribe_transaction_updates subscription: {}",
-                    e
-                );
+                error!("failed to add subscribe_full_blocks subscription: {}", e);
                 Status::internal("error adding subscription")
             })?;
 
@@ -1089,10 +3355,11 @@ impl Geyser for GeyserService {
             uuid,
             (
                 self.subscription_closed_sender.clone(),
-                SubscriptionClosedEvent::TransactionUpdateSubscription(uuid),
+                SubscriptionClosedEvent::FullBlockSubscription(uuid),
             ),
-            "subscribe_transaction_updates",
-        );
+            "subscribe_full_blocks",
+        )
+        .with_permit(permit);
 
         let mut resp = Response::new(stream);
         resp.metadata_mut().insert(
@@ -1102,22 +3369,39 @@ impl Geyser for GeyserService {
         Ok(resp)
     }
 
-    type SubscribeBlockUpdatesStream = SubscriptionStream<Uuid, TimestampedBlockUpdate>;
-    async fn subscribe_block_updates(
+    type SubscribeSlotTipsStream = SubscriptionStream<Uuid, MaybeSlotTipUpdate>;
+    async fn subscribe_slot_tips(
         &self,
-        _request: Request<SubscribeBlockUpdatesRequest>,
-    ) -> Result<Response<Self::SubscribeBlockUpdatesStream>, Status> {
+        request: Request<SubscribeSlotTipsRequest>,
+    ) -> Result<Response<Self::SubscribeSlotTipsStream>, Status> {
+        self.ensure_accepting_subscriptions()?;
+        let client = SubscriptionClient::new(&request);
+
+        let permit = authorize_subscription(&request, "SubscribeSlotTips", 0)?;
+        let mut commitments: HashSet<i32> = request.get_ref().commitments.iter().copied().collect();
+        if commitments.is_empty() {
+            commitments = [
+                CommitmentLevel::ProcessedCommitment,
+                CommitmentLevel::ConfirmedCommitment,
+                CommitmentLevel::FinalizedCommitment,
+            ]
+            .into_iter()
+            .map(|c| c as i32)
+            .collect();
+        }
         let (subscription_tx, subscription_rx) =
-            channel(self.service_config.subscriber_buffer_size);
+            subscriber_channel(self.service_config.subscriber_buffer_size);
 
         let uuid = Uuid::new_v4();
         self.subscription_added_tx
-            .try_send(SubscriptionAddedEvent::BlockUpdateSubscription {
+            .try_send(SubscriptionAddedEvent::SlotTipSubscription {
                 uuid,
+                client,
                 notification_sender: subscription_tx,
+                commitments,
             })
             .map_err(|e| {
-                error!("failed to add subscribe_block_updates subscription: {}", e);
+                error!("failed to add subscribe_slot_tips subscription: {}", e);
                 Status::internal("error adding subscription")
             })?;
 
@@ -1126,16 +3410,12 @@ impl Geyser for GeyserService {
             uuid,
             (
                 self.subscription_closed_sender.clone(),
-                SubscriptionClosedEvent::BlockUpdateSubscription(uuid),
+                SubscriptionClosedEvent::SlotTipSubscription(uuid),
             ),
-            "subscribe_block_updates",
-        );
+            "subscribe_slot_tips",
+        )
+        .with_permit(permit);
 
-        let mut resp = Response::new(stream);
-        resp.metadata_mut().insert(
-            HIGHEST_WRITE_SLOT_HEADER,
-            MetadataValue::from(self.highest_write_slot.load(Ordering::Relaxed)),
-        );
-        Ok(resp)
+        Ok(Response::new(stream))
     }
 }
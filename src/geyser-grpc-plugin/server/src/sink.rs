@@ -0,0 +1,579 @@
+//! Destinations the plugin streams updates to.
+//!
+//! The gRPC service is always fed. Additional sinks are configured under `sinks` in the plugin
+//! config, each with its own filters and buffer. They're written to from a dedicated thread per
+//! sink, so a destination that falls behind only drops its own updates.
+
+use std::{
+    collections::HashSet,
+    io,
+    str::FromStr,
+    sync::Arc,
+    thread::{Builder, JoinHandle},
+};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use jito_geyser_protos::solana::geyser::{
+    sink_message, SinkMessage, StartupComplete, TimestampedAccountUpdate, TimestampedBlockUpdate,
+    TimestampedSlotUpdate, TimestampedTransactionUpdate, UpdateTiming,
+};
+use log::*;
+use serde_derive::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+use crate::{
+    file_sink::{FileSinkConfig, FileSinkWriter},
+    kafka_sink::{KafkaSinkConfig, KafkaSinkWriter},
+    metrics::{Channel, GeyserMetrics},
+    server::{SlotEntryNotification, StartupNotification},
+    update_timing,
+};
+
+/// Max number of updates handed to a sink writer at once.
+const MAX_BATCH_SIZE: usize = 1_000;
+
+#[derive(Error, Debug)]
+pub enum SinkError {
+    #[error("SinkDisconnected")]
+    Disconnected,
+
+    #[error("InvalidConfig {0}")]
+    InvalidConfig(String),
+
+    #[error("IoError {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("KafkaError {0}")]
+    KafkaError(String),
+
+    #[error("KafkaNotLeader partition {0}")]
+    KafkaNotLeader(i32),
+}
+
+pub type SinkResult<T> = Result<T, SinkError>;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateType {
+    Account,
+    Slot,
+    Entry,
+    Block,
+    Transaction,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum SinkUpdate {
+    Account(TimestampedAccountUpdate),
+    Slot(TimestampedSlotUpdate),
+    Entry(SlotEntryNotification),
+    Block(TimestampedBlockUpdate),
+    Transaction(TimestampedTransactionUpdate),
+    /// Follows the last of the account updates notified at startup.
+    StartupComplete,
+}
+
+impl SinkUpdate {
+    pub fn update_type(&self) -> UpdateType {
+        match self {
+            SinkUpdate::Account(_) => UpdateType::Account,
+            SinkUpdate::Slot(_) => UpdateType::Slot,
+            SinkUpdate::Entry(_) => UpdateType::Entry,
+            SinkUpdate::Block(_) => UpdateType::Block,
+            SinkUpdate::Transaction(_) => UpdateType::Transaction,
+            SinkUpdate::StartupComplete => UpdateType::Account,
+        }
+    }
+
+    fn timing_mut(&mut self) -> Option<&mut Option<UpdateTiming>> {
+        match self {
+            SinkUpdate::Account(u) => Some(&mut u.timing),
+            SinkUpdate::Slot(u) => Some(&mut u.timing),
+            SinkUpdate::Entry(u) => Some(&mut u.entry_update.timing),
+            SinkUpdate::Block(u) => Some(&mut u.timing),
+            SinkUpdate::Transaction(u) => Some(&mut u.timing),
+            SinkUpdate::StartupComplete => None,
+        }
+    }
+}
+
+impl From<SinkUpdate> for SinkMessage {
+    fn from(update: SinkUpdate) -> Self {
+        let msg = match update {
+            SinkUpdate::Account(u) => sink_message::Msg::AccountUpdate(u),
+            SinkUpdate::Slot(u) => sink_message::Msg::SlotUpdate(u),
+            SinkUpdate::Entry(u) => sink_message::Msg::EntryUpdate(u.entry_update),
+            SinkUpdate::Block(u) => sink_message::Msg::BlockUpdate(u),
+            SinkUpdate::Transaction(u) => sink_message::Msg::TransactionUpdate(u),
+            SinkUpdate::StartupComplete => sink_message::Msg::StartupComplete(StartupComplete {}),
+        };
+        SinkMessage { msg: Some(msg) }
+    }
+}
+
+pub trait Sink: Send + Sync {
+    /// Identifies the sink in logs and metrics.
+    fn name(&self) -> &str;
+
+    /// Checked before the update is cloned for the sink.
+    fn accepts(&self, update: &SinkUpdate) -> bool;
+
+    /// Called from validator threads, so must not block. Updates are dropped if the sink is
+    /// behind; an error is only returned once the sink can no longer take updates. The gRPC sink
+    /// is the exception, blocking on startup updates if configured to buffer them.
+    fn send(&self, update: SinkUpdate) -> SinkResult<()>;
+
+    /// Writes out buffered updates and waits for the sink to exit.
+    fn shutdown(self: Box<Self>);
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SinkFilters {
+    /// Update types written to the sink. Defaults to all.
+    pub update_types: Option<Vec<UpdateType>>,
+
+    /// Base58 encoded accounts whose updates are written, along with those owned by `owners`.
+    /// All account updates are written if neither is set.
+    pub accounts: Option<Vec<String>>,
+    pub owners: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkKind {
+    File(FileSinkConfig),
+    Kafka(KafkaSinkConfig),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SinkConfig {
+    /// Identifies the sink in logs and metrics.
+    pub name: String,
+
+    #[serde(flatten)]
+    pub kind: SinkKind,
+
+    #[serde(default)]
+    pub filters: SinkFilters,
+
+    /// Number of updates buffered for the sink before they're dropped.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+}
+
+impl SinkConfig {
+    const DEFAULT_BUFFER_SIZE: usize = 100_000;
+}
+
+fn default_buffer_size() -> usize {
+    SinkConfig::DEFAULT_BUFFER_SIZE
+}
+
+/// Spawns the sink described by the config.
+pub fn build_sink(config: &SinkConfig, metrics: Arc<GeyserMetrics>) -> SinkResult<Box<dyn Sink>> {
+    let filter = SinkFilter::new(&config.filters)?;
+    let sink = match &config.kind {
+        SinkKind::File(file_config) => {
+            ThreadedSink::new(config, filter, FileSinkWriter::new(file_config)?, metrics)?
+        }
+        SinkKind::Kafka(kafka_config) => {
+            ThreadedSink::new(config, filter, KafkaSinkWriter::new(kafka_config), metrics)?
+        }
+    };
+    Ok(Box::new(sink))
+}
+
+struct SinkFilter {
+    update_types: Option<HashSet<UpdateType>>,
+    accounts: Option<HashSet<Vec<u8>>>,
+    owners: Option<HashSet<Vec<u8>>>,
+}
+
+impl SinkFilter {
+    fn new(filters: &SinkFilters) -> SinkResult<Self> {
+        let parse_pubkeys = |pubkeys: &Option<Vec<String>>| {
+            pubkeys
+                .as_ref()
+                .map(|pubkeys| {
+                    pubkeys
+                        .iter()
+                        .map(|pubkey| {
+                            Pubkey::from_str(pubkey)
+                                .map(|pubkey| pubkey.to_bytes().to_vec())
+                                .map_err(|e| SinkError::InvalidConfig(format!("{pubkey}: {e}")))
+                        })
+                        .collect::<SinkResult<HashSet<_>>>()
+                })
+                .transpose()
+        };
+        Ok(Self {
+            update_types: filters
+                .update_types
+                .as_ref()
+                .map(|types| types.iter().copied().collect()),
+            accounts: parse_pubkeys(&filters.accounts)?,
+            owners: parse_pubkeys(&filters.owners)?,
+        })
+    }
+
+    fn accepts(&self, update: &SinkUpdate) -> bool {
+        if self
+            .update_types
+            .as_ref()
+            .is_some_and(|types| !types.contains(&update.update_type()))
+        {
+            return false;
+        }
+        let SinkUpdate::Account(TimestampedAccountUpdate {
+            account_update: Some(account_update),
+            ..
+        }) = update
+        else {
+            return true;
+        };
+        if self.accounts.is_none() && self.owners.is_none() {
+            return true;
+        }
+        self.accounts
+            .as_ref()
+            .is_some_and(|accounts| accounts.contains(&account_update.pubkey))
+            || self
+                .owners
+                .as_ref()
+                .is_some_and(|owners| owners.contains(&account_update.owner))
+    }
+}
+
+/// Writes batches of updates to a sink's destination.
+pub trait SinkWriter: Send + 'static {
+    /// Updates are in the order they were received by the plugin.
+    fn write(&mut self, messages: &[SinkMessage]) -> SinkResult<()>;
+}
+
+/// Hands updates off to a [SinkWriter] running on its own thread.
+struct ThreadedSink {
+    name: String,
+    filter: SinkFilter,
+    update_sender: Sender<SinkUpdate>,
+    metrics: Arc<GeyserMetrics>,
+    t_hdl: JoinHandle<()>,
+}
+
+impl ThreadedSink {
+    fn new<W: SinkWriter>(
+        config: &SinkConfig,
+        filter: SinkFilter,
+        writer: W,
+        metrics: Arc<GeyserMetrics>,
+    ) -> SinkResult<Self> {
+        let (update_sender, update_receiver) = bounded(config.buffer_size);
+        let t_hdl = {
+            let name = config.name.clone();
+            let metrics = metrics.clone();
+            Builder::new()
+                .name(format!("geyser-sink-{name}"))
+                .spawn(move || Self::write_loop(&name, writer, update_receiver, &metrics))?
+        };
+        Ok(Self {
+            name: config.name.clone(),
+            filter,
+            update_sender,
+            metrics,
+            t_hdl,
+        })
+    }
+
+    /// Batches the writer fails to write are dropped and counted as such, leaving it to the writer
+    /// to recover for the next batch.
+    fn write_loop<W: SinkWriter>(
+        name: &str,
+        mut writer: W,
+        update_receiver: Receiver<SinkUpdate>,
+        metrics: &GeyserMetrics,
+    ) {
+        let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+        while let Ok(update) = update_receiver.recv() {
+            batch.push(SinkMessage::from(update));
+            batch.extend(
+                update_receiver
+                    .try_iter()
+                    .take(MAX_BATCH_SIZE - 1)
+                    .map(SinkMessage::from),
+            );
+            if let Err(e) = writer.write(&batch) {
+                metrics.record_sink_drops(name, batch.len());
+                error!("{name} sink dropped {} updates: {}", batch.len(), e);
+            }
+            batch.clear();
+        }
+    }
+}
+
+impl Sink for ThreadedSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accepts(&self, update: &SinkUpdate) -> bool {
+        self.filter.accepts(update)
+    }
+
+    fn send(&self, update: SinkUpdate) -> SinkResult<()> {
+        match self.update_sender.try_send(update) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.metrics.record_sink_drop(&self.name);
+                warn!("{} sink full, skipping", self.name);
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(SinkError::Disconnected),
+        }
+    }
+
+    fn shutdown(self: Box<Self>) {
+        drop(self.update_sender);
+        self.t_hdl.join().unwrap();
+    }
+}
+
+/// Feeds the gRPC service's event loop.
+pub struct GrpcSink {
+    pub account_update_sender: Sender<TimestampedAccountUpdate>,
+    pub slot_update_sender: Sender<TimestampedSlotUpdate>,
+    pub slot_entry_update_sender: Sender<SlotEntryNotification>,
+    pub block_update_sender: Sender<TimestampedBlockUpdate>,
+    pub transaction_update_sender: Sender<TimestampedTransactionUpdate>,
+    pub startup_notification_sender: Sender<StartupNotification>,
+    /// Set if startup account updates are sent on the startup channel, blocking while it's full
+    /// rather than dropping them.
+    pub buffer_startup_updates: bool,
+    pub metrics: Arc<GeyserMetrics>,
+}
+
+impl GrpcSink {
+    fn try_send<T>(&self, sender: &Sender<T>, update: T, channel: Channel) -> SinkResult<()> {
+        match sender.try_send(update) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.metrics.record_channel_drop(channel);
+                warn!("{} channel full, skipping", channel.as_str());
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(SinkError::Disconnected),
+        }
+    }
+
+    fn send_startup_notification(&self, notification: StartupNotification) -> SinkResult<()> {
+        self.startup_notification_sender
+            .send(notification)
+            .map_err(|_| SinkError::Disconnected)
+    }
+}
+
+impl Sink for GrpcSink {
+    fn name(&self) -> &str {
+        "grpc"
+    }
+
+    fn accepts(&self, _update: &SinkUpdate) -> bool {
+        true
+    }
+
+    fn send(&self, update: SinkUpdate) -> SinkResult<()> {
+        match update {
+            SinkUpdate::Account(u)
+                if self.buffer_startup_updates
+                    && u.account_update.as_ref().is_some_and(|u| u.is_startup) =>
+            {
+                self.send_startup_notification(StartupNotification::Account(u))
+            }
+            SinkUpdate::Account(u) => {
+                self.try_send(&self.account_update_sender, u, Channel::AccountUpdate)
+            }
+            SinkUpdate::Slot(u) => self.try_send(&self.slot_update_sender, u, Channel::SlotUpdate),
+            SinkUpdate::Entry(u) => {
+                self.try_send(&self.slot_entry_update_sender, u, Channel::SlotEntryUpdate)
+            }
+            SinkUpdate::Block(u) => {
+                self.try_send(&self.block_update_sender, u, Channel::BlockUpdate)
+            }
+            SinkUpdate::Transaction(u) => self.try_send(
+                &self.transaction_update_sender,
+                u,
+                Channel::TransactionUpdate,
+            ),
+            SinkUpdate::StartupComplete => {
+                self.send_startup_notification(StartupNotification::Complete)
+            }
+        }
+    }
+
+    fn shutdown(self: Box<Self>) {}
+}
+
+/// Fans updates out to the gRPC sink and every additional sink.
+pub struct Sinks {
+    grpc: GrpcSink,
+    sinks: Vec<Box<dyn Sink>>,
+    metrics: Arc<GeyserMetrics>,
+}
+
+impl Sinks {
+    pub fn new(grpc: GrpcSink, sinks: Vec<Box<dyn Sink>>, metrics: Arc<GeyserMetrics>) -> Self {
+        Self {
+            grpc,
+            sinks,
+            metrics,
+        }
+    }
+
+    /// Clones the update for every interested additional sink, handing it off to the gRPC sink
+    /// last. Only the gRPC sink's error is returned; the additional sinks' are logged and the
+    /// update counted as dropped by them, so that a dead destination doesn't fail the plugin.
+    pub fn send(&self, mut update: SinkUpdate) -> SinkResult<()> {
+        if let Some(timing) = update.timing_mut() {
+            update_timing::stamp_enqueue(timing);
+        }
+        for sink in self.sinks.iter().filter(|sink| sink.accepts(&update)) {
+            if let Err(e) = sink.send(update.clone()) {
+                self.metrics.record_sink_drop(sink.name());
+                error!("{} sink send error: {}", sink.name(), e);
+            }
+        }
+        self.grpc.send(update).inspect_err(|e| {
+            error!("{} sink send error: {}", self.grpc.name(), e);
+        })
+    }
+
+    pub fn shutdown(self) {
+        for sink in self.sinks {
+            sink.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+
+    use crossbeam_channel::unbounded;
+    use jito_geyser_protos::solana::geyser::AccountUpdate;
+
+    use super::*;
+
+    /// A sink whose destination has gone away.
+    struct DisconnectedSink;
+
+    impl Sink for DisconnectedSink {
+        fn name(&self) -> &str {
+            "disconnected"
+        }
+
+        fn accepts(&self, _update: &SinkUpdate) -> bool {
+            true
+        }
+
+        fn send(&self, _update: SinkUpdate) -> SinkResult<()> {
+            Err(SinkError::Disconnected)
+        }
+
+        fn shutdown(self: Box<Self>) {}
+    }
+
+    fn account_update(pubkey: Pubkey, owner: Pubkey) -> SinkUpdate {
+        SinkUpdate::Account(TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                pubkey: pubkey.to_bytes().to_vec(),
+                owner: owner.to_bytes().to_vec(),
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        })
+    }
+
+    #[test]
+    fn test_filters() {
+        let (account, owner, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let slot_update = SinkUpdate::Slot(TimestampedSlotUpdate::default());
+
+        let filter = SinkFilter::new(&SinkFilters::default()).unwrap();
+        assert!(filter.accepts(&account_update(other, other)));
+        assert!(filter.accepts(&slot_update));
+
+        let filter = SinkFilter::new(&SinkFilters {
+            update_types: Some(vec![UpdateType::Account]),
+            accounts: Some(vec![account.to_string()]),
+            owners: Some(vec![owner.to_string()]),
+        })
+        .unwrap();
+        assert!(filter.accepts(&account_update(account, other)));
+        assert!(filter.accepts(&account_update(other, owner)));
+        assert!(!filter.accepts(&account_update(other, other)));
+        assert!(!filter.accepts(&slot_update));
+
+        assert!(matches!(
+            SinkFilter::new(&SinkFilters {
+                owners: Some(vec!["not a pubkey".to_string()]),
+                ..SinkFilters::default()
+            }),
+            Err(SinkError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_only_grpc_sink_errors_are_returned() {
+        let metrics = Arc::new(GeyserMetrics::new(Arc::new(AtomicU64::new(0))).unwrap());
+        let (account_update_sender, account_update_receiver) = unbounded();
+        let grpc_sink = GrpcSink {
+            account_update_sender,
+            slot_update_sender: unbounded().0,
+            slot_entry_update_sender: unbounded().0,
+            block_update_sender: unbounded().0,
+            transaction_update_sender: unbounded().0,
+            startup_notification_sender: unbounded().0,
+            buffer_startup_updates: false,
+            metrics: metrics.clone(),
+        };
+        let sinks = Sinks::new(grpc_sink, vec![Box::new(DisconnectedSink)], metrics.clone());
+        let update = account_update(Pubkey::new_unique(), Pubkey::new_unique());
+
+        sinks.send(update.clone()).unwrap();
+        assert_eq!(account_update_receiver.len(), 1);
+        assert_eq!(metrics.sink_drops("disconnected"), 1);
+
+        drop(account_update_receiver);
+        assert!(matches!(sinks.send(update), Err(SinkError::Disconnected)));
+    }
+
+    #[test]
+    fn test_sink_config_deserialization() {
+        let config: Vec<SinkConfig> = serde_json::from_str(
+            r#"[
+                {"name": "archive", "type": "file", "path": "/tmp/geyser.ndjson"},
+                {
+                    "name": "stream",
+                    "type": "kafka",
+                    "broker": "127.0.0.1:9092",
+                    "topic": "geyser",
+                    "buffer_size": 10,
+                    "filters": {"update_types": ["slot", "block"]}
+                }
+            ]"#,
+        )
+        .unwrap();
+        assert!(matches!(&config[0].kind, SinkKind::File(c) if c.path == "/tmp/geyser.ndjson"));
+        assert_eq!(config[0].buffer_size, SinkConfig::DEFAULT_BUFFER_SIZE);
+        assert!(matches!(&config[1].kind, SinkKind::Kafka(c) if c.topic == "geyser"));
+        assert_eq!(
+            config[1].filters.update_types,
+            Some(vec![UpdateType::Slot, UpdateType::Block])
+        );
+    }
+}
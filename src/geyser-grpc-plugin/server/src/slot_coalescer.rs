@@ -0,0 +1,185 @@
+//! Holds account writes per slot so that only the final write of each account is streamed.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::pre_encoded::SharedAccountUpdate;
+
+struct PendingSlot {
+    /// Time at which the first write for this slot was held.
+    first_write: Instant,
+
+    /// Highest-seq write keyed by account pubkey.
+    writes: HashMap<Vec<u8>, Arc<SharedAccountUpdate>>,
+}
+
+/// Buffers writes keyed by (slot, pubkey), keeping only the highest-seq write of each account.
+/// A slot's writes are released once the slot is processed or `max_delay` after its first write,
+/// whichever comes first.
+pub struct SlotCoalescer {
+    max_delay: Duration,
+    pending_slots: BTreeMap<u64, PendingSlot>,
+
+    /// Writes for slots at or below this one are released immediately since the slot was already
+    /// flushed. Writes for lower slots on a minority fork are released uncoalesced as a result.
+    highest_processed_slot: u64,
+}
+
+impl SlotCoalescer {
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            pending_slots: BTreeMap::new(),
+            highest_processed_slot: 0,
+        }
+    }
+
+    /// Holds the write, returning it if it should be streamed immediately instead.
+    pub fn insert(
+        &mut self,
+        update: Arc<SharedAccountUpdate>,
+        now: Instant,
+    ) -> Option<Arc<SharedAccountUpdate>> {
+        let Some(account_update) = update.account_update.as_ref() else {
+            return Some(update);
+        };
+        if account_update.slot <= self.highest_processed_slot {
+            return Some(update);
+        }
+
+        let pending_slot = self
+            .pending_slots
+            .entry(account_update.slot)
+            .or_insert_with(|| PendingSlot {
+                first_write: now,
+                writes: HashMap::new(),
+            });
+        let held_seq = pending_slot
+            .writes
+            .get(&account_update.pubkey)
+            .and_then(|u| u.account_update.as_ref())
+            .map(|u| u.seq);
+        if held_seq.map_or(true, |seq| seq < account_update.seq) {
+            pending_slot
+                .writes
+                .insert(account_update.pubkey.clone(), update);
+        }
+        None
+    }
+
+    /// Releases the writes held for a processed slot, in seq order.
+    pub fn slot_processed(&mut self, slot: u64) -> Vec<Arc<SharedAccountUpdate>> {
+        self.highest_processed_slot = self.highest_processed_slot.max(slot);
+        self.pending_slots
+            .remove(&slot)
+            .map(Self::into_sorted_writes)
+            .unwrap_or_default()
+    }
+
+    /// Discards the writes held for a slot that will never be processed.
+    pub fn slot_dead(&mut self, slot: u64) {
+        self.pending_slots.remove(&slot);
+    }
+
+    /// Releases the writes of slots held for longer than `max_delay`, in slot then seq order.
+    pub fn expire(&mut self, now: Instant) -> Vec<Arc<SharedAccountUpdate>> {
+        let expired_slots: Vec<u64> = self
+            .pending_slots
+            .iter()
+            .filter(|(_, pending_slot)| {
+                now.duration_since(pending_slot.first_write) >= self.max_delay
+            })
+            .map(|(slot, _)| *slot)
+            .collect();
+        expired_slots
+            .into_iter()
+            .filter_map(|slot| self.pending_slots.remove(&slot))
+            .flat_map(Self::into_sorted_writes)
+            .collect()
+    }
+
+    fn into_sorted_writes(pending_slot: PendingSlot) -> Vec<Arc<SharedAccountUpdate>> {
+        let mut writes: Vec<Arc<SharedAccountUpdate>> = pending_slot.writes.into_values().collect();
+        writes.sort_by_key(|u| u.account_update.as_ref().map(|u| u.seq));
+        writes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jito_geyser_protos::solana::geyser::{AccountUpdate, TimestampedAccountUpdate};
+
+    use super::*;
+
+    fn update(pubkey: u8, slot: u64, seq: u64) -> Arc<SharedAccountUpdate> {
+        Arc::new(SharedAccountUpdate::new(TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                slot,
+                pubkey: vec![pubkey; 32],
+                seq,
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }))
+    }
+
+    fn seqs(updates: Vec<Arc<SharedAccountUpdate>>) -> Vec<u64> {
+        updates
+            .into_iter()
+            .map(|u| u.account_update.as_ref().unwrap().seq)
+            .collect()
+    }
+
+    #[test]
+    fn test_releases_highest_seq_write_on_slot_processed() {
+        let now = Instant::now();
+        let mut coalescer = SlotCoalescer::new(Duration::from_secs(1));
+        assert!(coalescer.insert(update(1, 10, 1), now).is_none());
+        assert!(coalescer.insert(update(2, 10, 2), now).is_none());
+        assert!(coalescer.insert(update(1, 10, 4), now).is_none());
+        assert!(coalescer.insert(update(1, 10, 3), now).is_none());
+        assert!(coalescer.insert(update(1, 11, 5), now).is_none());
+
+        assert_eq!(seqs(coalescer.slot_processed(10)), vec![2, 4]);
+        assert!(coalescer.slot_processed(10).is_empty());
+
+        // late writes for a processed slot pass through
+        assert_eq!(
+            coalescer
+                .insert(update(1, 10, 6), now)
+                .unwrap()
+                .account_update
+                .as_ref()
+                .unwrap()
+                .seq,
+            6
+        );
+
+        coalescer.slot_dead(11);
+        assert!(coalescer.expire(now + Duration::from_secs(2)).is_empty());
+    }
+
+    #[test]
+    fn test_expires_after_max_delay() {
+        let now = Instant::now();
+        let mut coalescer = SlotCoalescer::new(Duration::from_millis(100));
+        assert!(coalescer.insert(update(1, 10, 1), now).is_none());
+        assert!(coalescer
+            .insert(update(1, 11, 2), now + Duration::from_millis(50))
+            .is_none());
+
+        assert!(coalescer.expire(now + Duration::from_millis(99)).is_empty());
+        assert_eq!(
+            seqs(coalescer.expire(now + Duration::from_millis(100))),
+            vec![1]
+        );
+        assert_eq!(
+            seqs(coalescer.expire(now + Duration::from_millis(150))),
+            vec![2]
+        );
+    }
+}
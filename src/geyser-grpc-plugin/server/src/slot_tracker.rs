@@ -0,0 +1,268 @@
+//! Tracks the tip at each commitment level along with the fork tree above the latest root.
+//!
+//! The tree is built from the parent slots notified by the validator. Slots whose parent isn't
+//! known are assumed to descend from the slots below them, which can only be the case for the
+//! first few slots notified after startup.
+
+use std::collections::{BTreeMap, HashSet};
+
+use jito_geyser_protos::solana::geyser::{
+    CommitmentLevel, GetSlotStateResponse, SlotTipUpdate, SlotUpdate, SlotUpdateStatus, TrackedSlot,
+};
+
+#[derive(Default)]
+struct SlotNode {
+    parent_slot: Option<u64>,
+    commitment: Option<CommitmentLevel>,
+    dead: bool,
+}
+
+#[derive(Default)]
+pub struct SlotTracker {
+    slots: BTreeMap<u64, SlotNode>,
+    processed_slot: Option<u64>,
+    confirmed_slot: Option<u64>,
+    finalized_slot: Option<u64>,
+}
+
+impl SlotTracker {
+    /// Returns the tip changes caused by the update, if any.
+    pub fn update(&mut self, slot_update: &SlotUpdate) -> Vec<SlotTipUpdate> {
+        let slot = slot_update.slot;
+        if self.finalized_slot.is_some_and(|root| slot <= root) {
+            return vec![];
+        }
+        let node = self.slots.entry(slot).or_default();
+        if slot_update.parent_slot.is_some() {
+            node.parent_slot = slot_update.parent_slot;
+        }
+
+        let commitment = match SlotUpdateStatus::try_from(slot_update.status) {
+            Ok(SlotUpdateStatus::Processed) => CommitmentLevel::ProcessedCommitment,
+            Ok(SlotUpdateStatus::Confirmed) => CommitmentLevel::ConfirmedCommitment,
+            Ok(SlotUpdateStatus::Rooted) => CommitmentLevel::FinalizedCommitment,
+            Ok(SlotUpdateStatus::Dead) => {
+                self.mark_dead(slot);
+                return vec![];
+            }
+            _ => return vec![],
+        };
+        self.raise_commitment(slot, commitment);
+
+        let tip_update = match commitment {
+            // the processed tip moves backwards whenever the validator switches to a shorter fork
+            CommitmentLevel::ProcessedCommitment => self.set_tip(commitment, slot),
+            _ if self.tip(commitment).is_some_and(|tip| tip >= slot) => None,
+            _ => self.set_tip(commitment, slot),
+        };
+        if commitment == CommitmentLevel::FinalizedCommitment {
+            self.prune(slot);
+        }
+        tip_update.into_iter().collect()
+    }
+
+    pub fn slot_state(&self) -> GetSlotStateResponse {
+        GetSlotStateResponse {
+            processed_slot: self.processed_slot.unwrap_or_default(),
+            confirmed_slot: self.confirmed_slot.unwrap_or_default(),
+            finalized_slot: self.finalized_slot.unwrap_or_default(),
+            slots: self
+                .slots
+                .iter()
+                .map(|(slot, node)| TrackedSlot {
+                    slot: *slot,
+                    parent_slot: node.parent_slot,
+                    commitment: node.commitment.map(|c| c as i32),
+                    dead: node.dead,
+                })
+                .collect(),
+        }
+    }
+
+    fn tip(&self, commitment: CommitmentLevel) -> Option<u64> {
+        match commitment {
+            CommitmentLevel::ProcessedCommitment => self.processed_slot,
+            CommitmentLevel::ConfirmedCommitment => self.confirmed_slot,
+            CommitmentLevel::FinalizedCommitment => self.finalized_slot,
+        }
+    }
+
+    fn set_tip(&mut self, commitment: CommitmentLevel, slot: u64) -> Option<SlotTipUpdate> {
+        let previous_slot = self.tip(commitment);
+        if previous_slot == Some(slot) {
+            return None;
+        }
+        let fork_switch = previous_slot.is_some_and(|previous| !self.descends_from(slot, previous));
+        match commitment {
+            CommitmentLevel::ProcessedCommitment => self.processed_slot = Some(slot),
+            CommitmentLevel::ConfirmedCommitment => self.confirmed_slot = Some(slot),
+            CommitmentLevel::FinalizedCommitment => self.finalized_slot = Some(slot),
+        }
+        Some(SlotTipUpdate {
+            commitment: commitment as i32,
+            slot,
+            previous_slot,
+            fork_switch,
+        })
+    }
+
+    /// Commitment of a slot implies that of its ancestors.
+    fn raise_commitment(&mut self, slot: u64, commitment: CommitmentLevel) {
+        let mut current = Some(slot);
+        while let Some(node) = current.and_then(|slot| self.slots.get_mut(&slot)) {
+            if node.commitment.is_some_and(|c| c >= commitment) {
+                break;
+            }
+            node.commitment = Some(commitment);
+            current = node.parent_slot;
+        }
+    }
+
+    fn descends_from(&self, slot: u64, ancestor: u64) -> bool {
+        let mut current = slot;
+        loop {
+            if current == ancestor {
+                return true;
+            }
+            if current < ancestor {
+                return false;
+            }
+            match self.slots.get(&current).and_then(|node| node.parent_slot) {
+                Some(parent_slot) => current = parent_slot,
+                None => return true,
+            }
+        }
+    }
+
+    /// Marks the slot and its descendants dead.
+    fn mark_dead(&mut self, slot: u64) {
+        let mut dead_slots = HashSet::from([slot]);
+        for (slot, node) in self.slots.range_mut(slot..) {
+            if dead_slots.contains(slot)
+                || node.parent_slot.is_some_and(|p| dead_slots.contains(&p))
+            {
+                node.dead = true;
+                dead_slots.insert(*slot);
+            }
+        }
+    }
+
+    /// Discards slots below the root along with forks that don't descend from it.
+    fn prune(&mut self, root: u64) {
+        let slots = self.slots.split_off(&root);
+        self.slots.clear();
+        for (slot, node) in slots {
+            if slot == root
+                || node
+                    .parent_slot
+                    .map_or(true, |parent_slot| self.slots.contains_key(&parent_slot))
+            {
+                self.slots.insert(slot, node);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_update(slot: u64, parent_slot: Option<u64>, status: SlotUpdateStatus) -> SlotUpdate {
+        SlotUpdate {
+            slot,
+            parent_slot,
+            status: status as i32,
+        }
+    }
+
+    fn tips(updates: Vec<SlotTipUpdate>) -> Vec<(CommitmentLevel, u64, bool)> {
+        updates
+            .into_iter()
+            .map(|u| (u.commitment(), u.slot, u.fork_switch))
+            .collect()
+    }
+
+    #[test]
+    fn test_tips_follow_forks() {
+        let mut tracker = SlotTracker::default();
+        for (slot, parent_slot) in [(10, 9), (11, 10), (12, 10), (13, 11)] {
+            tracker.update(&slot_update(
+                slot,
+                Some(parent_slot),
+                SlotUpdateStatus::CreatedBank,
+            ));
+        }
+
+        assert_eq!(
+            tips(tracker.update(&slot_update(11, None, SlotUpdateStatus::Processed))),
+            vec![(CommitmentLevel::ProcessedCommitment, 11, false)]
+        );
+        assert_eq!(
+            tips(tracker.update(&slot_update(12, None, SlotUpdateStatus::Processed))),
+            vec![(CommitmentLevel::ProcessedCommitment, 12, true)]
+        );
+        assert_eq!(
+            tips(tracker.update(&slot_update(11, None, SlotUpdateStatus::Confirmed))),
+            vec![(CommitmentLevel::ConfirmedCommitment, 11, false)]
+        );
+        // confirmed tip doesn't move backwards
+        assert!(tracker
+            .update(&slot_update(10, None, SlotUpdateStatus::Confirmed))
+            .is_empty());
+
+        let state = tracker.slot_state();
+        assert_eq!(
+            (
+                state.processed_slot,
+                state.confirmed_slot,
+                state.finalized_slot
+            ),
+            (12, 11, 0)
+        );
+        assert_eq!(
+            state.slots[0].commitment,
+            Some(CommitmentLevel::ConfirmedCommitment as i32)
+        );
+    }
+
+    #[test]
+    fn test_dead_forks_and_pruning() {
+        let mut tracker = SlotTracker::default();
+        for (slot, parent_slot) in [(10, 9), (11, 10), (12, 10), (13, 12), (14, 11)] {
+            tracker.update(&slot_update(
+                slot,
+                Some(parent_slot),
+                SlotUpdateStatus::CreatedBank,
+            ));
+        }
+
+        tracker.update(&slot_update(12, None, SlotUpdateStatus::Dead));
+        let dead_slots: Vec<u64> = tracker
+            .slot_state()
+            .slots
+            .iter()
+            .filter(|s| s.dead)
+            .map(|s| s.slot)
+            .collect();
+        assert_eq!(dead_slots, vec![12, 13]);
+
+        assert_eq!(
+            tips(tracker.update(&slot_update(11, Some(10), SlotUpdateStatus::Rooted))),
+            vec![(CommitmentLevel::FinalizedCommitment, 11, false)]
+        );
+        let state = tracker.slot_state();
+        assert_eq!(
+            state.slots.iter().map(|s| s.slot).collect::<Vec<_>>(),
+            vec![11, 14]
+        );
+        assert_eq!(
+            state.slots[0].commitment,
+            Some(CommitmentLevel::FinalizedCommitment as i32)
+        );
+
+        // updates at or below the root are stale
+        assert!(tracker
+            .update(&slot_update(10, None, SlotUpdateStatus::Processed))
+            .is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+//! Channels backing the subscriptions that the event loop streams to directly, i.e. every one but
+//! account and program subscriptions, which are backed by a [crate::subscriber_queue].
+//!
+//! A slot of each channel is held back from updates so that the status a subscription is closed
+//! with always fits, even if the subscriber isn't keeping up.
+
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+use tonic::Status;
+
+/// Creates a channel buffering up to `capacity` updates, plus the closing status.
+pub fn subscriber_channel<T>(
+    capacity: usize,
+) -> (SubscriberSender<T>, Receiver<Result<T, Status>>) {
+    let (sender, receiver) = channel(capacity.max(1) + 1);
+    (SubscriberSender(sender), receiver)
+}
+
+/// Only ever sent to from the event loop, so that the slot held back can't be taken in between
+/// checking for it and sending.
+pub struct SubscriberSender<T>(Sender<Result<T, Status>>);
+
+impl<T> SubscriberSender<T> {
+    /// Queues an update, failing as if the channel were full once only the slot held back for the
+    /// closing status is left.
+    pub fn try_send(
+        &self,
+        update: Result<T, Status>,
+    ) -> Result<(), TrySendError<Result<T, Status>>> {
+        if self.0.capacity() <= 1 && !self.0.is_closed() {
+            return Err(TrySendError::Full(update));
+        }
+        self.0.try_send(update)
+    }
+
+    /// Queues the status the stream ends with, which is only sent once.
+    pub fn send_status(&self, status: Status) -> Result<(), TrySendError<Result<T, Status>>> {
+        self.0.try_send(Err(status))
+    }
+
+    /// Number of messages buffered for the subscriber.
+    pub fn queued_messages(&self) -> usize {
+        self.0.max_capacity() - self.0.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_fits_full_channel() {
+        let (sender, mut receiver) = subscriber_channel(2);
+        sender.try_send(Ok(1)).unwrap();
+        sender.try_send(Ok(2)).unwrap();
+        assert!(matches!(sender.try_send(Ok(3)), Err(TrySendError::Full(_))));
+        assert_eq!(sender.queued_messages(), 2);
+
+        sender
+            .send_status(Status::unavailable("shutting down"))
+            .unwrap();
+        drop(sender);
+        assert_eq!(receiver.try_recv().unwrap().unwrap(), 1);
+        assert_eq!(receiver.try_recv().unwrap().unwrap(), 2);
+        let status = receiver.try_recv().unwrap().unwrap_err();
+        assert_eq!(status.message(), "shutting down");
+
+        let (sender, receiver) = subscriber_channel::<u64>(1);
+        sender.try_send(Ok(1)).unwrap();
+        drop(receiver);
+        assert!(matches!(
+            sender.try_send(Ok(2)),
+            Err(TrySendError::Closed(_))
+        ));
+    }
+}
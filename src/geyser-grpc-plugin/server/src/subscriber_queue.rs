@@ -0,0 +1,662 @@
+//! Bounded queue between the event loop and an account update subscriber's stream.
+//!
+//! Unlike a channel, the producer side can inspect and rewrite what's buffered, which allows applying
+//! the subscription's [BackpressurePolicy] once the queue is full. Updates are buffered as `Arc`s so
+//! that fanning out to many subscribers doesn't copy them, and streamed as pre-encoded frames so
+//! they're only encoded once.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+use jito_geyser_protos::solana::geyser::{
+    maybe_account_update, BackpressurePolicy, Heartbeat, LagReport, MaybeAccountUpdate,
+    StartupComplete,
+};
+use tokio_stream::Stream;
+use tonic::Status;
+
+use crate::pre_encoded::{EncodedMaybeAccountUpdate, SharedAccountUpdate};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueueSendError {
+    /// The queue is full and the policy doesn't allow making room.
+    Full,
+    /// The receiving stream was dropped.
+    Closed,
+}
+
+struct State {
+    /// Initial state of the subscribed accounts, streamed ahead of buffered updates. It isn't
+    /// bounded by the queue's capacity since it's only sent once, upon subscribing.
+    snapshot: VecDeque<Arc<SharedAccountUpdate>>,
+    /// Buffered updates, the front having id `head_id`.
+    updates: VecDeque<Arc<SharedAccountUpdate>>,
+    head_id: u64,
+
+    /// Id of the buffered update for each account, only maintained when coalescing.
+    queued_ids: HashMap<Vec<u8>, u64>,
+
+    /// Terminal error streamed ahead of any buffered updates.
+    error: Option<Status>,
+    /// Terminal status streamed once everything buffered has been.
+    closing_status: Option<Status>,
+    /// Lag report streamed ahead of any buffered updates.
+    lag_report: Option<LagReport>,
+    /// Set if a heartbeat is to be streamed ahead of any buffered updates.
+    heartbeat: bool,
+    /// Id of the update the end of startup is streamed ahead of, once those before it are streamed.
+    startup_complete_id: Option<u64>,
+    /// Set while the journal is replayed ahead of the buffered updates, which mustn't be dropped.
+    is_replaying: bool,
+
+    dropped_updates: u64,
+    coalesced_updates: u64,
+    /// Upstream drop count as of the previous lag report.
+    last_upstream_dropped_updates: u64,
+
+    waker: Option<Waker>,
+    /// Number of live senders; the stream ends once all are dropped.
+    num_senders: usize,
+    is_receiver_closed: bool,
+    is_finished: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    capacity: usize,
+    policy: BackpressurePolicy,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+pub struct AccountUpdateQueueSender {
+    shared: Arc<Shared>,
+}
+
+pub struct AccountUpdateQueueReceiver {
+    shared: Arc<Shared>,
+}
+
+/// Creates a queue holding up to `capacity` updates. `upstream_dropped_updates` is the number of
+/// updates the plugin has dropped thus far, which lag reports are relative to.
+pub fn account_update_queue(
+    capacity: usize,
+    policy: BackpressurePolicy,
+    upstream_dropped_updates: u64,
+) -> (AccountUpdateQueueSender, AccountUpdateQueueReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            snapshot: VecDeque::new(),
+            updates: VecDeque::new(),
+            head_id: 0,
+            queued_ids: HashMap::new(),
+            error: None,
+            closing_status: None,
+            lag_report: None,
+            heartbeat: false,
+            startup_complete_id: None,
+            is_replaying: false,
+            dropped_updates: 0,
+            coalesced_updates: 0,
+            last_upstream_dropped_updates: upstream_dropped_updates,
+            waker: None,
+            num_senders: 1,
+            is_receiver_closed: false,
+            is_finished: false,
+        }),
+        capacity: capacity.max(1),
+        policy,
+    });
+
+    (
+        AccountUpdateQueueSender {
+            shared: shared.clone(),
+        },
+        AccountUpdateQueueReceiver { shared },
+    )
+}
+
+impl State {
+    fn push(&mut self, update: Arc<SharedAccountUpdate>, policy: BackpressurePolicy) {
+        if policy == BackpressurePolicy::CoalesceLatestPerAccount {
+            if let Some(pubkey) = update.account_update.as_ref().map(|u| u.pubkey.clone()) {
+                let id = self.head_id + self.updates.len() as u64;
+                self.queued_ids.insert(pubkey, id);
+            }
+        }
+        self.updates.push_back(update);
+    }
+
+    fn pop(&mut self) -> Option<Arc<SharedAccountUpdate>> {
+        let update = self.updates.pop_front()?;
+        let id = self.head_id;
+        self.head_id += 1;
+        if let Some(pubkey) = update.account_update.as_ref().map(|u| &u.pubkey) {
+            if self.queued_ids.get(pubkey) == Some(&id) {
+                self.queued_ids.remove(pubkey);
+            }
+        }
+        Some(update)
+    }
+
+    /// Replaces the buffered update for the same account, returning the update if there's none.
+    fn coalesce(&mut self, update: Arc<SharedAccountUpdate>) -> Option<Arc<SharedAccountUpdate>> {
+        let id = update
+            .account_update
+            .as_ref()
+            .and_then(|u| self.queued_ids.get(&u.pubkey));
+        match id {
+            Some(id) => {
+                let idx = (id - self.head_id) as usize;
+                self.updates[idx] = update;
+                None
+            }
+            None => Some(update),
+        }
+    }
+
+    fn wake(&mut self) -> Option<Waker> {
+        self.waker.take()
+    }
+}
+
+impl AccountUpdateQueueSender {
+    /// Number of updates buffered, waiting to be streamed.
+    pub fn queued_updates(&self) -> usize {
+        let state = self.shared.lock();
+        state.snapshot.len() + state.updates.len()
+    }
+
+    /// Buffers the update, applying the backpressure policy if the queue is full.
+    /// Returns true if an update was dropped or replaced to apply the policy.
+    pub fn try_send(&self, update: Arc<SharedAccountUpdate>) -> Result<bool, QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+
+        let dropped = if state.updates.len() >= self.shared.capacity {
+            if state.is_replaying {
+                state.error.get_or_insert_with(|| {
+                    Status::resource_exhausted(
+                        "live updates exceeded the subscriber buffer size while replaying",
+                    )
+                });
+                return Err(QueueSendError::Full);
+            }
+            let update = match self.shared.policy {
+                BackpressurePolicy::DropNewest => {
+                    state.dropped_updates += 1;
+                    return Ok(true);
+                }
+                BackpressurePolicy::Disconnect => return Err(QueueSendError::Full),
+                BackpressurePolicy::DropOldest => update,
+                BackpressurePolicy::CoalesceLatestPerAccount => match state.coalesce(update) {
+                    None => {
+                        state.coalesced_updates += 1;
+                        return Ok(true);
+                    }
+                    Some(update) => update,
+                },
+            };
+            state.pop();
+            state.dropped_updates += 1;
+            state.push(update, self.shared.policy);
+            true
+        } else {
+            state.push(update, self.shared.policy);
+            false
+        };
+
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(dropped)
+    }
+
+    /// Streams the initial state of the subscribed accounts ahead of any buffered updates. It
+    /// doesn't count towards the queue's capacity, so is never subject to the backpressure policy.
+    pub fn send_snapshot(
+        &self,
+        updates: impl IntoIterator<Item = Arc<SharedAccountUpdate>>,
+    ) -> Result<(), QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+        state.snapshot.extend(updates);
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Streams a lag report ahead of any buffered updates if anything was dropped since the last one.
+    pub fn report_lag(&self, upstream_dropped_updates: u64) -> Result<(), QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+
+        let upstream_dropped_since =
+            upstream_dropped_updates.saturating_sub(state.last_upstream_dropped_updates);
+        if state.dropped_updates == 0 && state.coalesced_updates == 0 && upstream_dropped_since == 0
+        {
+            return Ok(());
+        }
+
+        let (dropped_updates, coalesced_updates) = (state.dropped_updates, state.coalesced_updates);
+        let lag_report = state.lag_report.get_or_insert_with(LagReport::default);
+        lag_report.dropped_updates += dropped_updates;
+        lag_report.coalesced_updates += coalesced_updates;
+        lag_report.upstream_dropped_updates += upstream_dropped_since;
+        state.dropped_updates = 0;
+        state.coalesced_updates = 0;
+        state.last_upstream_dropped_updates = upstream_dropped_updates;
+
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Streams a heartbeat ahead of any buffered updates, so that it isn't subject to the
+    /// backpressure policy. Heartbeats not yet streamed are sent as one.
+    pub fn send_heartbeat(&self) -> Result<(), QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+        state.heartbeat = true;
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Streams the end of startup after the updates buffered thus far.
+    pub fn mark_startup_complete(&self) -> Result<(), QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+        state.startup_complete_id = Some(state.head_id + state.updates.len() as u64);
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Streams the status once everything buffered has been and ends the stream, unless it's
+    /// failed first.
+    pub fn close(&self, status: Status) -> Result<(), QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+        state.closing_status.get_or_insert(status);
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Streams the status ahead of any buffered updates and ends the stream.
+    pub fn stream_error(&self, status: Status) -> Result<(), QueueSendError> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_closed {
+            return Err(QueueSendError::Closed);
+        }
+        // Keep the first error; it's the reason the subscription is being torn down.
+        if state.error.is_none() {
+            state.error = Some(status);
+        }
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl Clone for AccountUpdateQueueSender {
+    fn clone(&self) -> Self {
+        self.shared.lock().num_senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for AccountUpdateQueueSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.num_senders -= 1;
+        if state.num_senders > 0 {
+            return;
+        }
+        let waker = state.wake();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl AccountUpdateQueueReceiver {
+    /// Fails the subscription rather than apply the backpressure policy if the queue fills up
+    /// before [Self::finish_replay], so that live updates held while the journal is replayed
+    /// aren't silently dropped.
+    pub fn start_replay(&self) {
+        self.shared.lock().is_replaying = true;
+    }
+
+    pub fn finish_replay(&self) {
+        self.shared.lock().is_replaying = false;
+    }
+}
+
+impl Stream for AccountUpdateQueueReceiver {
+    type Item = Result<EncodedMaybeAccountUpdate, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.shared.lock();
+        if state.is_finished {
+            return Poll::Ready(None);
+        }
+        if let Some(status) = state.error.take() {
+            state.is_finished = true;
+            return Poll::Ready(Some(Err(status)));
+        }
+        if let Some(lag_report) = state.lag_report.take() {
+            return Poll::Ready(Some(Ok(MaybeAccountUpdate {
+                msg: Some(maybe_account_update::Msg::LagReport(lag_report)),
+            }
+            .into())));
+        }
+        if state.heartbeat {
+            state.heartbeat = false;
+            return Poll::Ready(Some(Ok(MaybeAccountUpdate {
+                msg: Some(maybe_account_update::Msg::Hb(Heartbeat {})),
+            }
+            .into())));
+        }
+        if let Some(update) = state.snapshot.pop_front() {
+            return Poll::Ready(Some(Ok(update.frame())));
+        }
+        // updates ahead of it may have been dropped by the backpressure policy
+        if state
+            .startup_complete_id
+            .is_some_and(|id| id <= state.head_id)
+        {
+            state.startup_complete_id = None;
+            return Poll::Ready(Some(Ok(MaybeAccountUpdate {
+                msg: Some(maybe_account_update::Msg::StartupComplete(
+                    StartupComplete {},
+                )),
+            }
+            .into())));
+        }
+        if let Some(update) = state.pop() {
+            return Poll::Ready(Some(Ok(update.frame())));
+        }
+        if let Some(status) = state.closing_status.take() {
+            state.is_finished = true;
+            return Poll::Ready(Some(Err(status)));
+        }
+        if state.num_senders == 0 {
+            state.is_finished = true;
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for AccountUpdateQueueReceiver {
+    fn drop(&mut self) {
+        self.shared.lock().is_receiver_closed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{FutureExt, StreamExt};
+    use jito_geyser_protos::solana::geyser::{AccountUpdate, TimestampedAccountUpdate};
+    use prost::Message;
+
+    use super::*;
+
+    fn update(pubkey: u8, seq: u64) -> Arc<SharedAccountUpdate> {
+        Arc::new(SharedAccountUpdate::new(TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                pubkey: vec![pubkey; 32],
+                seq,
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }))
+    }
+
+    /// Drains everything currently buffered, returning (pubkey, seq) of updates and any lag reports.
+    /// Heartbeats are skipped.
+    fn drain(receiver: &mut AccountUpdateQueueReceiver) -> (Vec<(u8, u64)>, Vec<LagReport>) {
+        let mut updates = vec![];
+        let mut lag_reports = vec![];
+        while let Some(Some(frame)) = receiver.next().now_or_never() {
+            let msg = MaybeAccountUpdate::decode(frame.unwrap().bytes().clone()).unwrap();
+            match msg.msg.unwrap() {
+                maybe_account_update::Msg::AccountUpdate(u) => {
+                    let u = u.account_update.unwrap();
+                    updates.push((u.pubkey[0], u.seq));
+                }
+                maybe_account_update::Msg::LagReport(r) => lag_reports.push(r),
+                maybe_account_update::Msg::Hb(_)
+                | maybe_account_update::Msg::StartupComplete(_) => {}
+            }
+        }
+        (updates, lag_reports)
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let (sender, mut receiver) = account_update_queue(2, BackpressurePolicy::DropNewest, 0);
+        for seq in 0..4 {
+            sender.try_send(update(1, seq)).unwrap();
+        }
+        sender.report_lag(0).unwrap();
+        let (updates, lag_reports) = drain(&mut receiver);
+        assert_eq!(updates, vec![(1, 0), (1, 1)]);
+        assert_eq!(lag_reports[0].dropped_updates, 2);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (sender, mut receiver) = account_update_queue(2, BackpressurePolicy::DropOldest, 0);
+        for seq in 0..4 {
+            sender.try_send(update(1, seq)).unwrap();
+        }
+        let (updates, _) = drain(&mut receiver);
+        assert_eq!(updates, vec![(1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let (sender, mut receiver) = account_update_queue(1, BackpressurePolicy::Disconnect, 0);
+        sender.try_send(update(1, 0)).unwrap();
+        assert_eq!(sender.try_send(update(1, 1)), Err(QueueSendError::Full));
+        sender
+            .stream_error(Status::resource_exhausted("slow"))
+            .unwrap();
+        assert!(receiver.next().now_or_never().unwrap().unwrap().is_err());
+        assert!(receiver.next().now_or_never().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_close_streams_status_after_buffered_updates() {
+        let (sender, mut receiver) = account_update_queue(2, BackpressurePolicy::DropOldest, 0);
+        sender.send_snapshot([update(2, 0)]).unwrap();
+        sender.try_send(update(1, 1)).unwrap();
+        sender.try_send(update(1, 2)).unwrap();
+        sender.close(Status::unavailable("shutting down")).unwrap();
+
+        let mut seqs = vec![];
+        let status = loop {
+            match receiver.next().now_or_never().unwrap().unwrap() {
+                Ok(frame) => {
+                    let msg = MaybeAccountUpdate::decode(frame.bytes().clone()).unwrap();
+                    if let Some(maybe_account_update::Msg::AccountUpdate(u)) = msg.msg {
+                        seqs.push(u.account_update.unwrap().seq);
+                    }
+                }
+                Err(status) => break status,
+            }
+        };
+        assert_eq!(seqs, vec![0, 1, 2]);
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert!(receiver.next().now_or_never().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_coalesce_latest_per_account() {
+        let (sender, mut receiver) =
+            account_update_queue(2, BackpressurePolicy::CoalesceLatestPerAccount, 0);
+        sender.try_send(update(1, 0)).unwrap();
+        sender.try_send(update(2, 1)).unwrap();
+        sender.try_send(update(1, 2)).unwrap();
+        sender.try_send(update(2, 3)).unwrap();
+        // no buffered update for account 3, the oldest is dropped
+        sender.try_send(update(3, 4)).unwrap();
+        sender.report_lag(5).unwrap();
+
+        let (updates, lag_reports) = drain(&mut receiver);
+        assert_eq!(updates, vec![(2, 3), (3, 4)]);
+        assert_eq!(lag_reports.len(), 1);
+        assert_eq!(lag_reports[0].coalesced_updates, 2);
+        assert_eq!(lag_reports[0].dropped_updates, 1);
+        assert_eq!(lag_reports[0].upstream_dropped_updates, 5);
+    }
+
+    #[test]
+    fn test_heartbeat_streamed_ahead_of_full_queue() {
+        let (sender, mut receiver) = account_update_queue(1, BackpressurePolicy::Disconnect, 0);
+        sender.try_send(update(1, 0)).unwrap();
+        sender.send_heartbeat().unwrap();
+        sender.send_heartbeat().unwrap();
+
+        let frame = receiver.next().now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(
+            MaybeAccountUpdate::decode(frame.bytes().clone())
+                .unwrap()
+                .msg,
+            Some(maybe_account_update::Msg::Hb(Heartbeat {}))
+        );
+        assert_eq!(drain(&mut receiver).0, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_startup_complete_streamed_after_buffered_updates() {
+        let (sender, mut receiver) = account_update_queue(2, BackpressurePolicy::DropOldest, 0);
+        sender.try_send(update(1, 0)).unwrap();
+        sender.try_send(update(1, 1)).unwrap();
+        sender.mark_startup_complete().unwrap();
+        // drops the update ahead of the marker
+        sender.try_send(update(1, 2)).unwrap();
+
+        let mut msgs = vec![];
+        while let Some(Some(frame)) = receiver.next().now_or_never() {
+            msgs.push(
+                MaybeAccountUpdate::decode(frame.unwrap().bytes().clone())
+                    .unwrap()
+                    .msg
+                    .unwrap(),
+            );
+        }
+        assert_eq!(msgs.len(), 3);
+        assert!(
+            matches!(&msgs[0], maybe_account_update::Msg::AccountUpdate(u) if u.account_update.as_ref().unwrap().seq == 1)
+        );
+        assert_eq!(
+            msgs[1],
+            maybe_account_update::Msg::StartupComplete(StartupComplete {})
+        );
+        assert!(
+            matches!(&msgs[2], maybe_account_update::Msg::AccountUpdate(u) if u.account_update.as_ref().unwrap().seq == 2)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_exempt_from_capacity() {
+        let (sender, mut receiver) = account_update_queue(1, BackpressurePolicy::Disconnect, 0);
+        sender.try_send(update(1, 3)).unwrap();
+        sender
+            .send_snapshot((0..3).map(|seq| update(2, seq)))
+            .unwrap();
+        assert_eq!(sender.try_send(update(1, 4)), Err(QueueSendError::Full));
+        assert_eq!(sender.queued_updates(), 4);
+        assert_eq!(drain(&mut receiver).0, vec![(2, 0), (2, 1), (2, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn test_fails_when_full_while_replaying() {
+        let (sender, mut receiver) = account_update_queue(1, BackpressurePolicy::DropNewest, 0);
+        receiver.start_replay();
+        sender.try_send(update(1, 0)).unwrap();
+        assert_eq!(sender.try_send(update(1, 1)), Err(QueueSendError::Full));
+        assert_eq!(
+            receiver
+                .next()
+                .now_or_never()
+                .unwrap()
+                .unwrap()
+                .unwrap_err()
+                .code(),
+            tonic::Code::ResourceExhausted
+        );
+
+        let (sender, mut receiver) = account_update_queue(1, BackpressurePolicy::DropNewest, 0);
+        receiver.start_replay();
+        sender.try_send(update(1, 0)).unwrap();
+        receiver.finish_replay();
+        assert_eq!(sender.try_send(update(1, 1)), Ok(true));
+        assert_eq!(drain(&mut receiver).0, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_closed() {
+        let (sender, receiver) = account_update_queue(2, BackpressurePolicy::DropNewest, 0);
+        drop(receiver);
+        assert_eq!(sender.try_send(update(1, 0)), Err(QueueSendError::Closed));
+
+        let (sender, mut receiver) = account_update_queue(2, BackpressurePolicy::DropNewest, 0);
+        let cloned_sender = sender.clone();
+        sender.try_send(update(1, 0)).unwrap();
+        drop(sender);
+        cloned_sender.try_send(update(1, 1)).unwrap();
+        drop(cloned_sender);
+        assert_eq!(drain(&mut receiver).0, vec![(1, 0), (1, 1)]);
+        assert!(receiver.next().now_or_never().unwrap().is_none());
+    }
+}
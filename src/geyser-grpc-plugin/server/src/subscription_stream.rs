@@ -1,5 +1,6 @@
 use std::{
     fmt::Display,
+    marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -9,11 +10,17 @@ use tokio::sync::{mpsc::Receiver, oneshot};
 use tokio_stream::{wrappers::ReceiverStream, Stream};
 use tonic::Status;
 
+use crate::{
+    access_control::SubscriptionPermit, compact_timestamp::get_current_time_us_u32,
+    update_timing::StampSendTime,
+};
+
 /// Used to notify another process when a client's subscription is closed.
 /// This is useful especially when you want to clean up some state associated with a stream.
-pub struct SubscriptionStream<ID: Clone + Display + Send, T> {
+pub struct SubscriptionStream<ID: Clone + Display + Send, T, S = ReceiverStream<Result<T, Status>>>
+{
     /// Inner stream object.
-    inner: ReceiverStream<Result<T, Status>>,
+    inner: S,
 
     /// Inner channel used to signal tokio task when connection is dropped.
     /// NOTE: Wrapped in an Option b/c oneshot Sender takes ownership of the object
@@ -25,6 +32,11 @@ pub struct SubscriptionStream<ID: Clone + Display + Send, T> {
 
     /// Name of this stream.
     name: &'static str,
+
+    /// Counts towards the client's concurrent subscriptions while the stream is open.
+    permit: Option<SubscriptionPermit>,
+
+    _item: PhantomData<fn() -> T>,
 }
 
 pub trait StreamClosedSender<E: Send + 'static>: Send + 'static {
@@ -33,14 +45,31 @@ pub trait StreamClosedSender<E: Send + 'static>: Send + 'static {
 }
 
 impl<ID: Clone + Display + Send + 'static, T> SubscriptionStream<ID, T> {
-    pub fn new<E: Send + 'static, S: StreamClosedSender<E>>(
+    pub fn new<E: Send + 'static, C: StreamClosedSender<E>>(
         // Channel where streamed data flows thru.
         stream_rx: Receiver<Result<T, Status>>,
         // The stream's id.
         stream_id: ID,
         // Channel used to notify other process that stream was closed along
         // with arbitrary event that gets sent.
-        (stream_closed_event_sender, event): (S, E),
+        stream_closed: (C, E),
+        stream_name: &'static str,
+    ) -> Self {
+        Self::from_stream(
+            ReceiverStream::new(stream_rx),
+            stream_id,
+            stream_closed,
+            stream_name,
+        )
+    }
+}
+
+impl<ID: Clone + Display + Send + 'static, T, S> SubscriptionStream<ID, T, S> {
+    /// Same as [SubscriptionStream::new] but for streams not backed by a channel.
+    pub fn from_stream<E: Send + 'static, C: StreamClosedSender<E>>(
+        inner: S,
+        stream_id: ID,
+        (stream_closed_event_sender, event): (C, E),
         stream_name: &'static str,
     ) -> Self {
         let (stream_closed_signal, stream_disconnected_rx) = oneshot::channel();
@@ -56,19 +85,35 @@ impl<ID: Clone + Display + Send + 'static, T> SubscriptionStream<ID, T> {
         });
 
         Self {
-            inner: ReceiverStream::new(stream_rx),
+            inner,
             stream_closed_signal: Some(stream_closed_signal),
             name: stream_name,
             stream_id,
+            permit: None,
+            _item: PhantomData,
         }
     }
+
+    /// Holds the permit until the stream is dropped.
+    pub fn with_permit(mut self, permit: Option<SubscriptionPermit>) -> Self {
+        self.permit = permit;
+        self
+    }
 }
 
-impl<ID: Clone + Display + Send, T> Stream for SubscriptionStream<ID, T> {
+impl<ID: Clone + Display + Send, T: StampSendTime, S: Stream<Item = Result<T, Status>> + Unpin>
+    Stream for SubscriptionStream<ID, T, S>
+{
     type Item = Result<T, Status>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.inner).poll_next(cx)
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(Ok(item))) = poll {
+            let mut item = item;
+            item.stamp_send_time(get_current_time_us_u32());
+            return Poll::Ready(Some(Ok(item)));
+        }
+        poll
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -76,7 +121,7 @@ impl<ID: Clone + Display + Send, T> Stream for SubscriptionStream<ID, T> {
     }
 }
 
-impl<ID: Clone + Display + Send, T> Drop for SubscriptionStream<ID, T> {
+impl<ID: Clone + Display + Send, T, S> Drop for SubscriptionStream<ID, T, S> {
     fn drop(&mut self) {
         debug!(
             "closing {} stream: [stream_id={}]",
@@ -93,4 +138,4 @@ impl<ID: Clone + Display + Send, T> Drop for SubscriptionStream<ID, T> {
     }
 }
 
-impl<ID: Clone + Display + Send, T> Unpin for SubscriptionStream<ID, T> {}
+impl<ID: Clone + Display + Send, T, S: Unpin> Unpin for SubscriptionStream<ID, T, S> {}
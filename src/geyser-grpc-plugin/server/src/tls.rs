@@ -0,0 +1,250 @@
+//! TLS for the gRPC server, with a certificate that can be rotated without dropping connections.
+//! Clients may also be required to authenticate with a certificate issued by a given CA.
+
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    sync::{Arc, RwLock},
+};
+
+use log::*;
+use rustls::{
+    crypto::ring::{default_provider, sign::any_supported_type},
+    server::{
+        danger::ClientCertVerifier, ClientHello, ResolvesServerCert, VerifierBuilderError,
+        WebPkiClientVerifier,
+    },
+    sign::CertifiedKey,
+    RootCertStore, ServerConfig,
+};
+use thiserror::Error;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::channel,
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::Request;
+use x509_parser::parse_x509_certificate;
+
+use crate::server::ServerTlsConfig;
+
+/// Max number of handshaken connections waiting to be picked up by the server.
+const ACCEPTED_CONNECTIONS_BUFFER_SIZE: usize = 128;
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("IoError {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("RustlsError {0}")]
+    RustlsError(#[from] rustls::Error),
+
+    #[error("NoPrivateKey {0}")]
+    NoPrivateKey(String),
+
+    #[error("ClientVerifierError {0}")]
+    ClientVerifierError(#[from] VerifierBuilderError),
+}
+
+/// Serves the certificate most recently loaded, so that rotating it only affects new connections.
+#[derive(Debug)]
+pub struct ReloadableCertResolver {
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadableCertResolver {
+    pub fn new(config: &ServerTlsConfig) -> Result<Self, TlsError> {
+        Ok(Self {
+            certified_key: RwLock::new(Arc::new(load_certified_key(config)?)),
+        })
+    }
+
+    /// Re-reads the certificate and key, keeping the current ones if they fail to load.
+    pub fn reload(&self, config: &ServerTlsConfig) -> Result<(), TlsError> {
+        let certified_key = Arc::new(load_certified_key(config)?);
+        *self.certified_key.write().unwrap() = certified_key;
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.read().unwrap().clone())
+    }
+}
+
+fn load_certified_key(config: &ServerTlsConfig) -> Result<CertifiedKey, TlsError> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&config.cert_path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&config.key_path)?))?
+        .ok_or_else(|| TlsError::NoPrivateKey(config.key_path.clone()))?;
+    Ok(CertifiedKey::new(certs, any_supported_type(&key)?))
+}
+
+/// Verifies client certificates chain to one of the CAs in the bundle, rejecting clients without one.
+fn load_client_verifier(client_ca_path: &str) -> Result<Arc<dyn ClientCertVerifier>, TlsError> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut BufReader::new(File::open(client_ca_path)?)) {
+        roots.add(cert?)?;
+    }
+    Ok(
+        WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::new(default_provider()))
+            .build()?,
+    )
+}
+
+/// Subject of the certificate the client authenticated with, if any e.g. `CN=alice, O=acme`.
+pub fn peer_subject<T>(request: &Request<T>) -> Option<String> {
+    // the client's own certificate comes first, followed by its chain
+    let peer_certs = request.peer_certs()?;
+    certificate_subject(peer_certs.first()?)
+}
+
+fn certificate_subject(cert_der: &[u8]) -> Option<String> {
+    let (_, cert) = parse_x509_certificate(cert_der).ok()?;
+    Some(cert.subject().to_string())
+}
+
+/// Accepts TLS connections on the listener, handshaking with each concurrently.
+/// Must be called from within a tokio runtime.
+pub fn incoming(
+    listener: std::net::TcpListener,
+    cert_resolver: Arc<ReloadableCertResolver>,
+    config: &ServerTlsConfig,
+) -> Result<impl Stream<Item = io::Result<TlsStream<TcpStream>>>, TlsError> {
+    let builder = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()?;
+    let builder = match &config.client_ca_path {
+        Some(client_ca_path) => {
+            builder.with_client_cert_verifier(load_client_verifier(client_ca_path)?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder.with_cert_resolver(cert_resolver);
+    server_config.alpn_protocols = vec![b"h2".to_vec()];
+    let acceptor = TlsAcceptor::from(Arc::new(server_config));
+
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+    let (accepted_sender, accepted_receiver) = channel(ACCEPTED_CONNECTIONS_BUFFER_SIZE);
+    tokio::spawn(async move {
+        while !accepted_sender.is_closed() {
+            let (stream, peer_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("error accepting connection: {}", e);
+                    continue;
+                }
+            };
+            let _ = stream.set_nodelay(true);
+
+            let acceptor = acceptor.clone();
+            let accepted_sender = accepted_sender.clone();
+            tokio::spawn(async move {
+                match acceptor.accept(stream).await {
+                    Ok(tls_stream) => {
+                        let _ = accepted_sender.send(Ok(tls_stream)).await;
+                    }
+                    Err(e) => debug!("tls handshake with {} failed: {}", peer_addr, e),
+                }
+            });
+        }
+    });
+
+    Ok(ReceiverStream::new(accepted_receiver))
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use rustls::{
+        pki_types::{PrivateKeyDer, ServerName},
+        ClientConfig,
+    };
+    use tokio::{io::AsyncReadExt, runtime::Runtime};
+    use tokio_rustls::TlsConnector;
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    fn write_temp_file(contents: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("geyser-tls-test-{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_requires_client_certificate_issued_by_ca() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+        let issue = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            (params.signed_by(&key, &ca_cert, &ca_key).unwrap(), key)
+        };
+        let (server_cert, server_key) = issue("localhost");
+        let (client_cert, client_key) = issue("alice");
+        let config = ServerTlsConfig {
+            cert_path: write_temp_file(&server_cert.pem()),
+            key_path: write_temp_file(&server_key.serialize_pem()),
+            client_ca_path: Some(write_temp_file(&ca_cert.pem())),
+        };
+
+        let runtime = Runtime::new().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let cert_resolver = Arc::new(ReloadableCertResolver::new(&config).unwrap());
+        let mut incoming = {
+            let _guard = runtime.enter();
+            Box::pin(incoming(listener, cert_resolver, &config).unwrap())
+        };
+
+        let mut roots = RootCertStore::empty();
+        roots.add(ca_cert.der().clone()).unwrap();
+        let client_config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let anonymous_config = client_config.clone().with_no_client_auth();
+        let authenticated_config = client_config
+            .with_client_auth_cert(
+                vec![client_cert.der().clone()],
+                PrivateKeyDer::Pkcs8(client_key.serialize_der().into()),
+            )
+            .unwrap();
+
+        runtime.block_on(async {
+            let server_name = ServerName::try_from("localhost").unwrap();
+            let connect = |client_config: ClientConfig| async {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                TlsConnector::from(Arc::new(client_config))
+                    .connect(server_name.clone(), stream)
+                    .await
+            };
+
+            // the server only rejects the missing certificate once the client has sent its own
+            // half of the handshake
+            if let Ok(mut rejected) = connect(anonymous_config).await {
+                assert!(rejected.read(&mut [0; 1]).await.is_err());
+            }
+
+            let _authenticated = connect(authenticated_config).await.unwrap();
+            let accepted =
+                tokio::time::timeout(std::time::Duration::from_secs(10), incoming.next())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .unwrap();
+            let peer_certs = accepted.get_ref().1.peer_certificates().unwrap();
+            assert_eq!(
+                certificate_subject(&peer_certs[0]).as_deref(),
+                Some("CN=alice")
+            );
+        });
+    }
+}
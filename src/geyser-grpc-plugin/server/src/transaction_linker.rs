@@ -0,0 +1,442 @@
+//! Links account writes to the transactions that caused them.
+//!
+//! The validator notifies a transaction's account writes as it's committed, while the transaction
+//! itself is notified once its status has been recorded, so writes arrive ahead of their
+//! transaction. Writes carrying a signature are therefore held until the transaction is received,
+//! or released without it after `max_delay` in case it never is.
+//!
+//! Writes to an account are released in the order they were notified, so that the last one
+//! released holds its latest state: a write whose transaction was received, or one without a
+//! signature, is held for as long as an earlier write to the same account is.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use jito_geyser_protos::solana::geyser::{
+    TimestampedAccountUpdate, TransactionContext, TransactionUpdate,
+};
+
+struct HeldWrite {
+    update: TimestampedAccountUpdate,
+    /// Set once the write no longer awaits its transaction, only an earlier write to the account.
+    is_ready: bool,
+}
+
+pub struct TransactionLinker {
+    max_delay: Duration,
+
+    /// Held writes, keyed by the order they were held in.
+    held_writes: BTreeMap<u64, HeldWrite>,
+    next_write_id: u64,
+
+    /// Writes awaiting their transaction, keyed by signature.
+    pending_writes: HashMap<String, Vec<u64>>,
+
+    /// Held writes of each account, in the order they were held.
+    held_accounts: HashMap<Vec<u8>, VecDeque<u64>>,
+
+    /// Signatures in the order their first write was held, along with the time it was.
+    held_signatures: VecDeque<(Instant, String)>,
+}
+
+impl TransactionLinker {
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            held_writes: BTreeMap::new(),
+            next_write_id: 0,
+            pending_writes: HashMap::new(),
+            held_accounts: HashMap::new(),
+            held_signatures: VecDeque::new(),
+        }
+    }
+
+    /// Holds the write until its transaction is received, returning it if it has none and no
+    /// earlier write to the account is held.
+    pub fn insert_account_update(
+        &mut self,
+        update: TimestampedAccountUpdate,
+        now: Instant,
+    ) -> Option<TimestampedAccountUpdate> {
+        let Some(account_update) = update.account_update.as_ref() else {
+            return Some(update);
+        };
+        let signature = account_update.tx_signature.clone();
+        if signature.is_none() && !self.held_accounts.contains_key(&account_update.pubkey) {
+            return Some(update);
+        }
+
+        let id = self.next_write_id;
+        self.next_write_id += 1;
+        self.held_accounts
+            .entry(account_update.pubkey.clone())
+            .or_default()
+            .push_back(id);
+        if let Some(signature) = &signature {
+            if !self.pending_writes.contains_key(signature) {
+                self.held_signatures.push_back((now, signature.clone()));
+            }
+            self.pending_writes
+                .entry(signature.clone())
+                .or_default()
+                .push(id);
+        }
+        self.held_writes.insert(
+            id,
+            HeldWrite {
+                update,
+                is_ready: signature.is_none(),
+            },
+        );
+        None
+    }
+
+    /// Seqs of the writes currently held.
+    pub fn held_seqs(&self) -> HashSet<u64> {
+        self.held_writes
+            .values()
+            .filter_map(|held_write| held_write.update.account_update.as_ref())
+            .map(|account_update| account_update.seq)
+            .collect()
+    }
+
+    /// Releases the writes held for the transaction, linked to it, along with the later writes to
+    /// the same accounts they held up.
+    pub fn insert_transaction(
+        &mut self,
+        transaction: &TransactionUpdate,
+    ) -> Vec<TimestampedAccountUpdate> {
+        let Some(ids) = self.pending_writes.remove(&transaction.signature) else {
+            return vec![];
+        };
+        let tx_context = transaction_context(transaction);
+        for id in &ids {
+            let held_write = self.held_writes.get_mut(id).unwrap();
+            if let Some(account_update) = held_write.update.account_update.as_mut() {
+                account_update.tx_context = Some(tx_context.clone());
+            }
+            held_write.is_ready = true;
+        }
+        self.release_ready(&ids)
+    }
+
+    /// Releases the writes whose transaction wasn't received within `max_delay`, along with the
+    /// later writes to the same accounts they held up, in the order they were held.
+    pub fn expire(&mut self, now: Instant) -> Vec<TimestampedAccountUpdate> {
+        let mut expired_ids = vec![];
+        while let Some((held_at, _)) = self.held_signatures.front() {
+            if now.duration_since(*held_at) < self.max_delay {
+                break;
+            }
+            let (_, signature) = self.held_signatures.pop_front().unwrap();
+            // already released if the transaction was received
+            if let Some(ids) = self.pending_writes.remove(&signature) {
+                for id in &ids {
+                    self.held_writes.get_mut(id).unwrap().is_ready = true;
+                }
+                expired_ids.extend(ids);
+            }
+        }
+        self.release_ready(&expired_ids)
+    }
+
+    /// Releases the ready writes at the front of the accounts written by `ids`, in the order
+    /// they were held.
+    fn release_ready(&mut self, ids: &[u64]) -> Vec<TimestampedAccountUpdate> {
+        let mut released_ids = vec![];
+        for id in ids {
+            let Some(pubkey) = self
+                .held_writes
+                .get(id)
+                .and_then(|held_write| held_write.update.account_update.as_ref())
+                .map(|account_update| account_update.pubkey.clone())
+            else {
+                continue;
+            };
+            let Some(held_ids) = self.held_accounts.get_mut(&pubkey) else {
+                continue;
+            };
+            while let Some(held_id) = held_ids.front() {
+                if !self.held_writes[held_id].is_ready {
+                    break;
+                }
+                released_ids.push(held_ids.pop_front().unwrap());
+            }
+            if held_ids.is_empty() {
+                self.held_accounts.remove(&pubkey);
+            }
+        }
+        released_ids.sort_unstable();
+        released_ids
+            .into_iter()
+            .filter_map(|id| self.held_writes.remove(&id))
+            .map(|held_write| held_write.update)
+            .collect()
+    }
+}
+
+fn transaction_context(transaction: &TransactionUpdate) -> TransactionContext {
+    let tx = transaction.tx.as_ref();
+    let message = tx
+        .and_then(|tx| tx.transaction.as_ref())
+        .and_then(|tx| tx.message.as_ref());
+    let meta = tx.and_then(|tx| tx.meta.as_ref());
+
+    // keys loaded from lookup tables follow the message's own, writable ones first
+    let account_keys: Vec<&Vec<u8>> = message
+        .into_iter()
+        .flat_map(|message| &message.account_keys)
+        .chain(meta.into_iter().flat_map(|meta| {
+            meta.loaded_writable_addresses
+                .iter()
+                .chain(&meta.loaded_readonly_addresses)
+        }))
+        .collect();
+
+    let mut program_ids: Vec<Vec<u8>> = vec![];
+    for (idx, instruction) in message
+        .into_iter()
+        .flat_map(|message| message.instructions.iter().enumerate())
+    {
+        let inner_program_id_indexes = meta
+            .into_iter()
+            .flat_map(|meta| &meta.inner_instructions)
+            .filter(|inner| inner.index as usize == idx)
+            .flat_map(|inner| inner.instructions.iter().map(|i| i.program_id_index));
+        for program_id_index in
+            std::iter::once(instruction.program_id_index).chain(inner_program_id_indexes)
+        {
+            if let Some(program_id) = account_keys.get(program_id_index as usize) {
+                if !program_ids.contains(program_id) {
+                    program_ids.push((*program_id).clone());
+                }
+            }
+        }
+    }
+
+    TransactionContext {
+        tx_idx: transaction.tx_idx,
+        program_ids,
+        is_success: meta.is_some_and(|meta| meta.err.is_none()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jito_geyser_protos::solana::{
+        geyser::AccountUpdate,
+        storage::confirmed_block::{
+            CompiledInstruction, ConfirmedTransaction, InnerInstruction, InnerInstructions,
+            Message, Transaction, TransactionError, TransactionStatusMeta,
+        },
+    };
+
+    use super::*;
+
+    fn update(seq: u64, tx_signature: Option<&str>) -> TimestampedAccountUpdate {
+        TimestampedAccountUpdate {
+            ts: None,
+            account_update: Some(AccountUpdate {
+                pubkey: vec![seq as u8; 32],
+                seq,
+                tx_signature: tx_signature.map(str::to_string),
+                ..AccountUpdate::default()
+            }),
+            timing: None,
+        }
+    }
+
+    fn seqs(updates: &[TimestampedAccountUpdate]) -> Vec<u64> {
+        updates
+            .iter()
+            .map(|u| u.account_update.as_ref().unwrap().seq)
+            .collect()
+    }
+
+    fn transaction(signature: &str, err: Option<TransactionError>) -> TransactionUpdate {
+        TransactionUpdate {
+            signature: signature.to_string(),
+            tx_idx: 7,
+            tx: Some(ConfirmedTransaction {
+                transaction: Some(Transaction {
+                    signatures: vec![],
+                    message: Some(Message {
+                        account_keys: vec![vec![0; 32], vec![1; 32], vec![2; 32]],
+                        instructions: vec![
+                            CompiledInstruction {
+                                program_id_index: 2,
+                                ..CompiledInstruction::default()
+                            },
+                            CompiledInstruction {
+                                program_id_index: 1,
+                                ..CompiledInstruction::default()
+                            },
+                        ],
+                        ..Message::default()
+                    }),
+                }),
+                meta: Some(TransactionStatusMeta {
+                    err,
+                    inner_instructions: vec![InnerInstructions {
+                        index: 0,
+                        instructions: vec![
+                            InnerInstruction {
+                                program_id_index: 3,
+                                ..InnerInstruction::default()
+                            },
+                            InnerInstruction {
+                                program_id_index: 2,
+                                ..InnerInstruction::default()
+                            },
+                        ],
+                    }],
+                    loaded_writable_addresses: vec![vec![3; 32]],
+                    ..TransactionStatusMeta::default()
+                }),
+            }),
+            ..TransactionUpdate::default()
+        }
+    }
+
+    #[test]
+    fn test_links_writes_to_their_transaction() {
+        let now = Instant::now();
+        let mut linker = TransactionLinker::new(Duration::from_secs(1));
+        let unsigned = linker.insert_account_update(update(1, None), now).unwrap();
+        assert_eq!(unsigned.account_update.unwrap().seq, 1);
+        assert!(linker
+            .insert_account_update(update(2, Some("a")), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(update(3, Some("b")), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(update(4, Some("a")), now)
+            .is_none());
+
+        let linked = linker.insert_transaction(&transaction("a", None));
+        assert_eq!(seqs(&linked), vec![2, 4]);
+        let tx_context = linked[0]
+            .account_update
+            .as_ref()
+            .unwrap()
+            .tx_context
+            .as_ref()
+            .unwrap();
+        assert_eq!(tx_context.tx_idx, 7);
+        assert_eq!(
+            tx_context.program_ids,
+            vec![vec![2; 32], vec![3; 32], vec![1; 32]]
+        );
+        assert!(tx_context.is_success);
+        assert!(linker
+            .insert_transaction(&transaction("a", None))
+            .is_empty());
+
+        let linked =
+            linker.insert_transaction(&transaction("b", Some(TransactionError::default())));
+        assert!(
+            !linked[0]
+                .account_update
+                .as_ref()
+                .unwrap()
+                .tx_context
+                .as_ref()
+                .unwrap()
+                .is_success
+        );
+        assert!(linker.expire(now + Duration::from_secs(2)).is_empty());
+    }
+
+    #[test]
+    fn test_expires_after_max_delay() {
+        let now = Instant::now();
+        let mut linker = TransactionLinker::new(Duration::from_millis(100));
+        assert!(linker
+            .insert_account_update(update(1, Some("a")), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(update(2, Some("b")), now + Duration::from_millis(50))
+            .is_none());
+        assert!(linker
+            .insert_account_update(update(3, Some("a")), now + Duration::from_millis(60))
+            .is_none());
+
+        assert!(linker.expire(now + Duration::from_millis(99)).is_empty());
+        let expired = linker.expire(now + Duration::from_millis(100));
+        assert_eq!(seqs(&expired), vec![1, 3]);
+        assert!(expired[0]
+            .account_update
+            .as_ref()
+            .unwrap()
+            .tx_context
+            .is_none());
+        assert_eq!(
+            seqs(&linker.expire(now + Duration::from_millis(150))),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_releases_writes_to_an_account_in_order() {
+        let now = Instant::now();
+        let mut linker = TransactionLinker::new(Duration::from_millis(100));
+        let write = |seq, pubkey: u8, tx_signature| {
+            let mut update = update(seq, tx_signature);
+            update.account_update.as_mut().unwrap().pubkey = vec![pubkey; 32];
+            update
+        };
+        // "a" is dropped, so its write is only released upon expiry
+        assert!(linker
+            .insert_account_update(write(1, 1, Some("a")), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(write(2, 1, Some("b")), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(write(3, 2, Some("b")), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(write(4, 1, None), now)
+            .is_none());
+        assert!(linker
+            .insert_account_update(write(5, 2, None), now)
+            .is_none());
+        assert_eq!(
+            seqs(&[linker
+                .insert_account_update(write(6, 3, None), now)
+                .unwrap()]),
+            vec![6]
+        );
+
+        // the writes to the other account aren't held up
+        assert_eq!(
+            seqs(&linker.insert_transaction(&transaction("b", None))),
+            vec![3, 5]
+        );
+        let released = linker.expire(now + Duration::from_millis(100));
+        assert_eq!(seqs(&released), vec![1, 2, 4]);
+        assert!(released[0]
+            .account_update
+            .as_ref()
+            .unwrap()
+            .tx_context
+            .is_none());
+        assert!(released[1]
+            .account_update
+            .as_ref()
+            .unwrap()
+            .tx_context
+            .is_some());
+
+        assert_eq!(
+            seqs(&[linker
+                .insert_account_update(write(7, 1, None), now)
+                .unwrap()]),
+            vec![7]
+        );
+        assert!(linker.held_writes.is_empty() && linker.held_accounts.is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+//! Stamps the [UpdateTiming] of updates as they make their way from the validator to subscribers,
+//! so that the stage introducing latency can be pinpointed.
+
+use jito_geyser_protos::solana::geyser::{
+    maybe_block_update, maybe_slot_entry_update, maybe_slot_update, maybe_transaction_update,
+    MaybeBlock, MaybeBlockUpdate, MaybePartialAccountUpdate, MaybeSlotEntryUpdate,
+    MaybeSlotTipUpdate, MaybeSlotUpdate, MaybeTransactionUpdate, TimestampedBlockUpdate,
+    TimestampedSlotEntryUpdate, TimestampedSlotUpdate, TimestampedTransactionUpdate, UpdateTiming,
+};
+
+use crate::{
+    compact_timestamp::get_current_time_us_u32,
+    pre_encoded::{EncodedMaybeAccountUpdate, EncodedTimestampedAccountUpdate},
+};
+
+/// Timing of an update the plugin was just notified of.
+pub fn received() -> Option<UpdateTiming> {
+    Some(UpdateTiming {
+        plugin_receive_us: get_current_time_us_u32(),
+        ..UpdateTiming::default()
+    })
+}
+
+pub fn stamp_enqueue(timing: &mut Option<UpdateTiming>) {
+    timing.get_or_insert_with(UpdateTiming::default).enqueue_us = get_current_time_us_u32();
+}
+
+pub fn stamp_dispatch(timing: &mut Option<UpdateTiming>) {
+    timing.get_or_insert_with(UpdateTiming::default).dispatch_us = get_current_time_us_u32();
+}
+
+/// Streamed messages whose update, if any, is stamped with the time it's handed to the subscriber.
+pub trait StampSendTime {
+    fn stamp_send_time(&mut self, _send_us: u32) {}
+}
+
+fn stamp_send(timing: &mut Option<UpdateTiming>, send_us: u32) {
+    timing.get_or_insert_with(UpdateTiming::default).send_us = send_us;
+}
+
+impl StampSendTime for EncodedMaybeAccountUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        self.set_send_time(send_us);
+    }
+}
+
+impl StampSendTime for EncodedTimestampedAccountUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        self.set_send_time(send_us);
+    }
+}
+
+impl StampSendTime for TimestampedSlotUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        stamp_send(&mut self.timing, send_us);
+    }
+}
+
+impl StampSendTime for TimestampedSlotEntryUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        stamp_send(&mut self.timing, send_us);
+    }
+}
+
+impl StampSendTime for TimestampedBlockUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        stamp_send(&mut self.timing, send_us);
+    }
+}
+
+impl StampSendTime for TimestampedTransactionUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        stamp_send(&mut self.timing, send_us);
+    }
+}
+
+impl StampSendTime for MaybeSlotUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        if let Some(maybe_slot_update::Msg::SlotUpdate(update)) = &mut self.msg {
+            update.stamp_send_time(send_us);
+        }
+    }
+}
+
+impl StampSendTime for MaybeSlotEntryUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        if let Some(maybe_slot_entry_update::Msg::EntryUpdate(update)) = &mut self.msg {
+            update.stamp_send_time(send_us);
+        }
+    }
+}
+
+impl StampSendTime for MaybeBlockUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        if let Some(maybe_block_update::Msg::BlockUpdate(update)) = &mut self.msg {
+            update.stamp_send_time(send_us);
+        }
+    }
+}
+
+impl StampSendTime for MaybeTransactionUpdate {
+    fn stamp_send_time(&mut self, send_us: u32) {
+        if let Some(maybe_transaction_update::Msg::TransactionUpdate(update)) = &mut self.msg {
+            update.stamp_send_time(send_us);
+        }
+    }
+}
+
+// derived from other updates rather than notified by the validator
+impl StampSendTime for MaybePartialAccountUpdate {}
+impl StampSendTime for MaybeBlock {}
+impl StampSendTime for MaybeSlotTipUpdate {}